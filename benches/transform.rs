@@ -0,0 +1,237 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use led_speakers::features::AudioFeatures;
+use led_speakers::settings::{Analysis, TransformerSettings};
+use led_speakers::transform::{
+    AudioTransformer, ConstantQKernel, FrameTransformer, TransformedAudio,
+};
+use rustfft::num_complex::Complex;
+use rustfft::num_traits::Zero;
+use rustfft::FftPlanner;
+
+use std::f32::consts::PI;
+
+const RATE: u32 = 44100;
+
+/// Transformer settings matching the default config.json.
+fn settings() -> TransformerSettings {
+    TransformerSettings {
+        sink: "".to_string(),
+        fft_len: 3000,
+        total_bands: 6,
+        lower_cutoff: 50.0,
+        upper_cutoff: 10000.0,
+        monstercat: 50.0,
+        decay: 10.0,
+        buffer_size: 65535,
+        analysis: Analysis::Fft,
+        hpss: false,
+    }
+}
+
+/// Interleaved stereo samples of a two-tone signal.
+fn samples(fft_len: usize) -> Vec<i16> {
+    (0..fft_len)
+        .flat_map(|i| {
+            let t = i as f32 / RATE as f32;
+            let left = 0.5 * (2.0 * PI * 110.0 * t).sin() + 0.3 * (2.0 * PI * 2500.0 * t).sin();
+            let right = 0.4 * (2.0 * PI * 220.0 * t).sin();
+            vec![
+                (right * i16::max_value() as f32) as i16,
+                (left * i16::max_value() as f32) as i16,
+            ]
+        })
+        .collect()
+}
+
+/// Frame processing as done before switching to the real-input FFT:
+/// two complex FFTs and newly allocated buffers for each frame.
+fn complex_fft_frame(
+    samples: &[i16],
+    fft: &dyn rustfft::Fft<f32>,
+    left: &mut Vec<Complex<f32>>,
+    right: &mut Vec<Complex<f32>>,
+    left_transformed: &mut TransformedAudio,
+    right_transformed: &mut TransformedAudio,
+    settings: &TransformerSettings,
+) -> (AudioFeatures, AudioFeatures) {
+    let norm = 1.0 / (i16::max_value() as f32);
+    let mut left_samples = vec![0.0; settings.fft_len];
+    let mut right_samples = vec![0.0; settings.fft_len];
+    for (i, sample) in samples.chunks_exact(2).enumerate() {
+        left_samples[i] = sample[1] as f32 * norm;
+        left[i] = Complex::new(left_samples[i], 0.0);
+        right_samples[i] = sample[0] as f32 * norm;
+        right[i] = Complex::new(right_samples[i], 0.0);
+    }
+
+    let (lower_cutoff_freq, upper_cutoff_freq) = AudioTransformer::cutoff_frequencies(
+        settings.total_bands,
+        settings.lower_cutoff,
+        settings.upper_cutoff,
+        RATE,
+        settings.fft_len,
+    );
+
+    let mut frames = vec![];
+    for (channel, channel_samples, transformed) in vec![
+        (&mut *left, &left_samples, left_transformed),
+        (&mut *right, &right_samples, right_transformed),
+    ] {
+        fft.process(channel);
+        let real: Vec<f32> = channel
+            .iter()
+            .map(|c| (c.im.powf(2.0) + c.re.powf(2.0)).sqrt())
+            .collect();
+        let mut bands = vec![0.0; settings.total_bands];
+        AudioTransformer::frequency_magnitudes(
+            &real,
+            &mut bands,
+            transformed,
+            &lower_cutoff_freq,
+            &upper_cutoff_freq,
+            settings.monstercat,
+            settings.decay,
+        );
+        let mut spectrum = vec![0.0; settings.fft_len / 2];
+        AudioTransformer::normalize_spectrum(&real, &mut spectrum, settings.fft_len);
+        frames.push(AudioTransformer::features(
+            channel_samples,
+            &spectrum,
+            settings.fft_len,
+            bands,
+            transformed,
+            RATE,
+        ));
+    }
+
+    let stereo = AudioTransformer::stereo_features(
+        &left_samples,
+        &right_samples,
+        left,
+        right,
+        settings.fft_len,
+        &lower_cutoff_freq,
+        &upper_cutoff_freq,
+    );
+    let mut right_frame = frames.remove(1);
+    let mut left_frame = frames.remove(0);
+    left_frame.stereo = stereo.clone();
+    right_frame.stereo = stereo;
+
+    (left_frame, right_frame)
+}
+
+fn bench_frame(c: &mut Criterion) {
+    let settings = settings();
+    let samples = samples(settings.fft_len);
+
+    let mut group = c.benchmark_group("frame");
+    // report throughput as frames per second
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("complex_fft", |b| {
+        let fft = FftPlanner::new().plan_fft_forward(settings.fft_len);
+        let mut left = vec![Zero::zero(); settings.fft_len];
+        let mut right = vec![Zero::zero(); settings.fft_len];
+        let mut left_transformed = TransformedAudio::new(settings.total_bands, settings.fft_len);
+        let mut right_transformed = TransformedAudio::new(settings.total_bands, settings.fft_len);
+        b.iter(|| {
+            complex_fft_frame(
+                &samples,
+                &*fft,
+                &mut left,
+                &mut right,
+                &mut left_transformed,
+                &mut right_transformed,
+                &settings,
+            )
+        })
+    });
+
+    group.bench_function("frame_transformer", |b| {
+        let mut frame_transformer = FrameTransformer::new(settings.clone(), RATE);
+        b.iter(|| frame_transformer.process(&samples))
+    });
+
+    group.bench_function("frame_transformer_constant_q", |b| {
+        let settings = TransformerSettings {
+            analysis: Analysis::ConstantQ,
+            ..settings.clone()
+        };
+        let mut frame_transformer = FrameTransformer::new(settings, RATE);
+        b.iter(|| frame_transformer.process(&samples))
+    });
+
+    group.bench_function("frame_transformer_hpss", |b| {
+        let settings = TransformerSettings {
+            hpss: true,
+            ..settings.clone()
+        };
+        let mut frame_transformer = FrameTransformer::new(settings, RATE);
+        b.iter(|| frame_transformer.process(&samples))
+    });
+
+    group.finish();
+}
+
+/// Compares the computation of frequency bands from the FFT output.
+fn bench_bands(c: &mut Criterion) {
+    let settings = settings();
+    let samples = samples(settings.fft_len);
+    let mut frame_transformer = FrameTransformer::new(settings.clone(), RATE);
+    frame_transformer.process(&samples);
+    let channel = &frame_transformer.left;
+
+    let mut group = c.benchmark_group("bands");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("fft", |b| {
+        let (lower_cutoff_freq, upper_cutoff_freq) = AudioTransformer::cutoff_frequencies(
+            settings.total_bands,
+            settings.lower_cutoff,
+            settings.upper_cutoff,
+            RATE,
+            settings.fft_len,
+        );
+        let mut transformed = TransformedAudio::new(settings.total_bands, settings.fft_len);
+        let mut bands = vec![0.0; settings.total_bands];
+        b.iter(|| {
+            AudioTransformer::frequency_magnitudes(
+                &channel.magnitudes,
+                &mut bands,
+                &mut transformed,
+                &lower_cutoff_freq,
+                &upper_cutoff_freq,
+                settings.monstercat,
+                settings.decay,
+            )
+        })
+    });
+
+    group.bench_function("constant_q", |b| {
+        let kernel = ConstantQKernel::new(
+            settings.total_bands,
+            settings.lower_cutoff,
+            settings.upper_cutoff,
+            RATE,
+            settings.fft_len,
+        );
+        let mut transformed = TransformedAudio::new(settings.total_bands, settings.fft_len);
+        let mut bands = vec![0.0; settings.total_bands];
+        b.iter(|| {
+            AudioTransformer::constant_q_frequency_magnitudes(
+                &channel.fft_output,
+                &mut bands,
+                &mut transformed,
+                &kernel,
+                settings.monstercat,
+                settings.decay,
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame, bench_bands);
+criterion_main!(benches);
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+use crate::features::AudioFeatures;
+use crate::settings::TransformerSettings;
+use crate::transform::FrameTransformer;
+
+/// Number of transformer iterations per second of the live pipeline
+const ITERATIONS_PER_SECOND: u32 = 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Audio features of a single frame produced by offline analysis.
+pub struct AnalysisFrame {
+    /// Index of the frame
+    pub frame: usize,
+
+    /// Position of the end of the frame in the audio in seconds
+    pub time: f32,
+
+    /// Features of the left channel
+    pub left: AudioFeatures,
+
+    /// Features of the right channel
+    pub right: AudioFeatures,
+}
+
+/// Number of interleaved stereo samples the live pipeline consumes per iteration.
+///
+/// Mirrors the audio buffer handling of `AudioTransformer::start`: each iteration
+/// consumes 1/60 of the sampling rate in bytes, rounded down to full sample pairs.
+///
+/// # Examples
+/// ```
+/// use led_speakers::analysis::hop_len;
+///
+/// assert_eq!(hop_len(44100), 366);
+/// ```
+pub fn hop_len(rate: u32) -> usize {
+    let mut hop_bytes = (rate / ITERATIONS_PER_SECOND) as usize;
+    hop_bytes -= hop_bytes % 4;
+    hop_bytes / 2
+}
+
+/// Runs the audio transformation on interleaved 16 bit stereo samples.
+///
+/// Samples are fed to the transformer in the same chunks as in the live pipeline.
+/// A frame is produced once enough samples for the FFT are available.
+///
+/// # Arguments
+/// * `samples`: interleaved 16 bit stereo samples
+/// * `rate`: sampling rate of the audio samples
+/// * `settings`: transformer settings
+///
+pub fn analyze(samples: &[i16], rate: u32, settings: TransformerSettings) -> Vec<AnalysisFrame> {
+    let fft_input_len = settings.fft_len * 2;
+    let hop = hop_len(rate);
+    let mut frame_transformer = FrameTransformer::new(settings, rate);
+    let mut frames = Vec::new();
+
+    if hop == 0 {
+        return frames;
+    }
+
+    let mut end = hop;
+    while end <= samples.len() {
+        // the transformer only processes the most recent samples
+        if end >= fft_input_len {
+            let (left, right) = frame_transformer.process(&samples[end - fft_input_len..end]);
+            frames.push(AnalysisFrame {
+                frame: frames.len(),
+                time: (end / 2) as f32 / rate as f32,
+                left,
+                right,
+            });
+        }
+        end += hop;
+    }
+
+    frames
+}
+
+/// Writes analysis frames as CSV with one row per frame and channel.
+pub fn write_csv<W: Write>(frames: &[AnalysisFrame], writer: &mut W) -> io::Result<()> {
+    let total_bands = frames.first().map(|f| f.left.bands.len()).unwrap_or(0);
+
+    let mut header = vec![
+        "frame",
+        "time",
+        "channel",
+        "rms",
+        "peak",
+        "spectral_centroid",
+        "spectral_flux",
+        "onset",
+        "drums",
+        "dominant_frequency",
+        "loudness",
+        "balance",
+        "correlation",
+        "key_root",
+        "key_minor",
+        "key_confidence",
+    ]
+    .iter()
+    .map(|c| c.to_string())
+    .collect::<Vec<String>>();
+    header.extend((0..12).map(|i| format!("chroma_{}", i)));
+    header.extend((0..total_bands).map(|i| format!("band_{}", i)));
+    writeln!(writer, "{}", header.join(","))?;
+
+    for frame in frames {
+        for (channel, features) in [("left", &frame.left), ("right", &frame.right)].iter() {
+            let mut row = vec![
+                frame.frame.to_string(),
+                frame.time.to_string(),
+                channel.to_string(),
+                features.rms.to_string(),
+                features.peak.to_string(),
+                features.spectral_centroid.to_string(),
+                features.spectral_flux.to_string(),
+                features.onset.to_string(),
+                features
+                    .drums
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                features.dominant_frequency.to_string(),
+                features.loudness.to_string(),
+                features.stereo.balance.to_string(),
+                features.stereo.correlation.to_string(),
+                features.key.root.to_string(),
+                features.key.minor.to_string(),
+                features.key.confidence.to_string(),
+            ];
+            row.extend(features.chroma.iter().map(|c| c.to_string()));
+            row.extend(features.bands.iter().map(|b| b.to_string()));
+            writeln!(writer, "{}", row.join(","))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes analysis frames as JSON array.
+pub fn write_json<W: Write>(frames: &[AnalysisFrame], writer: &mut W) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, frames)?;
+    writeln!(writer)
+}
@@ -1,6 +1,6 @@
 use crate::settings::Settings;
 use crate::theme::Theme;
-use crate::viz::VizRunner;
+use crate::viz::{ScriptViz, Viz, VizRunner};
 use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
@@ -27,9 +27,37 @@ pub struct AppState {
     /// Available themes.
     pub themes: Vec<Theme>,
 
-    /// Available visualizations.
-    pub visualizations: Vec<Visualization>,
-
-    /// Shared settings.
+    /// Shared settings; if both are needed, `settings` is locked before `viz_runner`.
     pub settings: Arc<Mutex<Settings>>,
+
+    /// Path of the config file settings are persisted to.
+    pub config_path: String,
+}
+
+impl AppState {
+    /// Returns the visualizations that can be shown.
+    ///
+    /// The script directory is rescanned first, so scripts added since the start are listed
+    /// and deleted scripts aren't listed anymore.
+    ///
+    pub fn visualizations(&self) -> Vec<Visualization> {
+        // only new scripts are compiled, and without holding the settings lock
+        let (scripts, loaded) = {
+            let mut settings = self.settings.lock().unwrap();
+            settings.remove_deleted_scripts();
+            (settings.scripts.clone(), settings.script_names())
+        };
+        let new_scripts = ScriptViz::load_new(&scripts, &loaded);
+
+        let mut settings = self.settings.lock().unwrap();
+        settings.add_scripts(new_scripts);
+        settings
+            .available_visualizations()
+            .map(|v| Visualization {
+                pretty_name: v.get_pretty_name().to_string(),
+                identifier: v.get_name().to_string(),
+                settings: Some(v.get_settings()),
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use led_speakers::analysis::{analyze, write_csv, write_json};
+use led_speakers::settings::TransformerSettings;
+
+const CONFIG: &str = "config.json";
+
+const USAGE: &str = "Runs the audio transformation on a WAV file and dumps the features of each frame.
+
+Usage: led-speakers-analyze [options] <input.wav>
+
+Options:
+    --config <file>     config file to read transformer settings from [default: config.json]
+    --format <format>   output format: csv or json [default: csv]
+    --output <file>     output file [default: stdout]
+    --<setting> <value> overrides a transformer setting, e.g. --fft_len 2048";
+
+/// Command line arguments
+struct Args {
+    /// Path to the WAV file
+    input: String,
+
+    /// Path to the config file
+    config: String,
+
+    /// Output format
+    format: String,
+
+    /// Path to the output file; stdout if not set
+    output: Option<String>,
+
+    /// Transformer settings overriding the config
+    overrides: HashMap<String, String>,
+}
+
+/// Parses the command line arguments.
+fn parse_args() -> Result<Args> {
+    let mut input = None;
+    let mut config = CONFIG.to_string();
+    let mut format = "csv".to_string();
+    let mut output = None;
+    let mut overrides = HashMap::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+
+        if let Some(name) = arg.strip_prefix("--") {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {}\n\n{}", arg, USAGE))?;
+            match name {
+                "config" => config = value,
+                "format" => format = value,
+                "output" => output = Some(value),
+                _ => {
+                    overrides.insert(name.to_string(), value);
+                }
+            }
+        } else if input.is_none() {
+            input = Some(arg);
+        } else {
+            return Err(anyhow!("Unexpected argument {}\n\n{}", arg, USAGE));
+        }
+    }
+
+    Ok(Args {
+        input: input.ok_or_else(|| anyhow!("Missing input file\n\n{}", USAGE))?,
+        config,
+        format,
+        output,
+        overrides,
+    })
+}
+
+/// Reads the transformer settings from the config file and applies overrides.
+fn read_settings(config: &str, overrides: HashMap<String, String>) -> Result<TransformerSettings> {
+    let mut conf = config::Config::default();
+    conf.merge(config::File::with_name(config))?;
+    let settings: TransformerSettings = conf.get("transformer")?;
+
+    let mut settings_map = settings.to_map();
+    for (name, value) in overrides {
+        if !settings_map.contains_key(&name) {
+            return Err(anyhow!("Unknown transformer setting {}", name));
+        }
+        settings_map.insert(name, value);
+    }
+
+    Ok(TransformerSettings::from_map(settings_map))
+}
+
+/// Reads a WAV file and returns interleaved 16 bit stereo samples and the sampling rate.
+///
+/// Mono files are duplicated to both channels; only the first two channels of files
+/// with more channels are used.
+fn read_wav(path: &str) -> Result<(Vec<i16>, u32)> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples: Vec<i16> = match spec.sample_format {
+        hound::SampleFormat::Int if spec.bits_per_sample <= 16 => reader
+            .samples::<i16>()
+            .map(|s| s.map(|s| s << (16 - spec.bits_per_sample)))
+            .collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => reader
+            .samples::<i32>()
+            .map(|s| s.map(|s| (s >> (spec.bits_per_sample - 16)) as i16))
+            .collect::<Result<_, _>>()?,
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|s| s.map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let channels = spec.channels as usize;
+    let stereo = samples
+        .chunks_exact(channels)
+        .flat_map(|s| {
+            if channels == 1 {
+                vec![s[0], s[0]]
+            } else {
+                vec![s[0], s[1]]
+            }
+        })
+        .collect();
+
+    Ok((stereo, spec.sample_rate))
+}
+
+fn main() -> Result<()> {
+    let args = parse_args()?;
+    let settings = read_settings(&args.config, args.overrides)?;
+    let (samples, rate) = read_wav(&args.input)?;
+
+    let frames = analyze(&samples, rate, settings);
+
+    let mut writer: Box<dyn Write> = match args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    match args.format.as_str() {
+        "csv" => write_csv(&frames, &mut writer)?,
+        "json" => write_json(&frames, &mut writer)?,
+        _ => return Err(anyhow!("Unknown output format {}", args.format)),
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Loudness in LUFS reported for silence
+pub const MIN_LOUDNESS: f32 = -70.0;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Audio features extracted from a single frame of one audio channel.
+pub struct AudioFeatures {
+    /// Frequency band magnitudes scaled to values between 0 and 100
+    pub bands: Vec<f32>,
+
+    /// Band magnitudes of the percussive part of the audio;
+    /// empty if harmonic/percussive separation is disabled
+    pub percussive_bands: Vec<f32>,
+
+    /// Band magnitudes of the harmonic part of the audio;
+    /// empty if harmonic/percussive separation is disabled
+    pub harmonic_bands: Vec<f32>,
+
+    /// Root mean square of the normalized audio samples
+    pub rms: f32,
+
+    /// Maximum absolute value of the normalized audio samples
+    pub peak: f32,
+
+    /// Spectral centroid in Hz; "center of mass" of the spectrum
+    pub spectral_centroid: f32,
+
+    /// Spectral flux; increase of spectrum magnitudes compared to previous frame
+    pub spectral_flux: f32,
+
+    /// Whether an onset, like a beat, has been detected in this frame
+    pub onset: bool,
+
+    /// Drum hits classified in this frame; drum hits are classified a few frames after their onset
+    pub drums: Vec<DrumEvent>,
+
+    /// Frequency with the largest magnitude in Hz
+    pub dominant_frequency: f32,
+
+    /// Time the frame has been processed
+    pub timestamp: DateTime<Utc>,
+
+    /// Stereo field features; shared between left and right channel
+    pub stereo: StereoFeatures,
+
+    /// Energy of each of the 12 pitch classes starting at C; normalized to a maximum of 1
+    pub chroma: Vec<f32>,
+
+    /// Estimated musical key
+    pub key: Key,
+
+    /// A-weighted short-term loudness in LUFS; approximates momentary loudness
+    pub loudness: f32,
+}
+
+impl AudioFeatures {
+    /// Creates a new `AudioFeatures` instance representing silence.
+    ///
+    /// # Examples
+    /// ```
+    /// use led_speakers::features::AudioFeatures;
+    ///
+    /// let features = AudioFeatures::new(6);
+    /// ```
+    pub fn new(total_bands: usize) -> Self {
+        AudioFeatures {
+            bands: vec![0.0; total_bands],
+            percussive_bands: Vec::new(),
+            harmonic_bands: Vec::new(),
+            rms: 0.0,
+            peak: 0.0,
+            spectral_centroid: 0.0,
+            spectral_flux: 0.0,
+            onset: false,
+            drums: Vec::new(),
+            dominant_frequency: 0.0,
+            timestamp: Utc::now(),
+            stereo: StereoFeatures::new(total_bands),
+            chroma: vec![0.0; 12],
+            key: Key::default(),
+            loudness: MIN_LOUDNESS,
+        }
+    }
+
+    /// Creates a new `AudioFeatures` instance with the provided band magnitudes.
+    ///
+    /// All other features are set to represent silence.
+    ///
+    /// # Examples
+    /// ```
+    /// use led_speakers::features::AudioFeatures;
+    ///
+    /// let features = AudioFeatures::from_bands(vec![100.0, 50.0, 0.0]);
+    /// assert_eq!(features.dominant_band(), 0);
+    /// ```
+    pub fn from_bands(bands: Vec<f32>) -> Self {
+        AudioFeatures {
+            bands,
+            ..AudioFeatures::new(0)
+        }
+    }
+
+    /// Returns the overall band energy as value between 0 and 1.
+    pub fn energy(&self) -> f32 {
+        self.source_energy(BandSource::All)
+    }
+
+    /// Returns the band magnitudes of the provided source.
+    ///
+    /// Falls back to `bands` if the source is not available.
+    ///
+    /// # Examples
+    /// ```
+    /// use led_speakers::features::{AudioFeatures, BandSource};
+    ///
+    /// let mut features = AudioFeatures::from_bands(vec![100.0, 50.0]);
+    /// assert_eq!(features.source_bands(BandSource::Percussive), &vec![100.0, 50.0]);
+    ///
+    /// features.percussive_bands = vec![0.0, 50.0];
+    /// assert_eq!(features.source_bands(BandSource::Percussive), &vec![0.0, 50.0]);
+    /// ```
+    pub fn source_bands(&self, source: BandSource) -> &Vec<f32> {
+        let bands = match source {
+            BandSource::All => &self.bands,
+            BandSource::Percussive => &self.percussive_bands,
+            BandSource::Harmonic => &self.harmonic_bands,
+        };
+
+        if bands.is_empty() {
+            &self.bands
+        } else {
+            bands
+        }
+    }
+
+    /// Returns the band energy of the provided source as value between 0 and 1.
+    pub fn source_energy(&self, source: BandSource) -> f32 {
+        let bands = self.source_bands(source);
+        if bands.is_empty() {
+            return 0.0;
+        }
+
+        let magnitude: f32 = bands.iter().sum();
+        magnitude / (100.0 * bands.len() as f32)
+    }
+
+    /// Returns the perceived loudness as value between 0 and 1.
+    ///
+    /// Loudness is mapped linearly from `MIN_LOUDNESS` (0) to 0 LUFS (1).
+    ///
+    /// # Examples
+    /// ```
+    /// use led_speakers::features::AudioFeatures;
+    ///
+    /// let mut features = AudioFeatures::new(6);
+    /// assert_eq!(features.loudness_level(), 0.0);
+    ///
+    /// features.loudness = -35.0;
+    /// assert_eq!(features.loudness_level(), 0.5);
+    /// ```
+    pub fn loudness_level(&self) -> f32 {
+        ((self.loudness - MIN_LOUDNESS) / -MIN_LOUDNESS).clamp(0.0, 1.0)
+    }
+
+    /// Returns the index of the band with the largest magnitude.
+    pub fn dominant_band(&self) -> usize {
+        self.source_dominant_band(BandSource::All)
+    }
+
+    /// Returns the index of the band of the provided source with the largest magnitude.
+    pub fn source_dominant_band(&self, source: BandSource) -> usize {
+        let mut dominant_band = 0;
+        let mut max_magnitude = 0.0;
+        for (i, magnitude) in self.source_bands(source).iter().enumerate() {
+            if *magnitude > max_magnitude {
+                max_magnitude = *magnitude;
+                dominant_band = i;
+            }
+        }
+        dominant_band
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines which band magnitudes are used by visualizations.
+pub enum BandSource {
+    /// Band magnitudes of the complete audio
+    All,
+
+    /// Band magnitudes of the percussive part of the audio, like drums
+    Percussive,
+
+    /// Band magnitudes of the harmonic part of the audio, like sustained notes
+    Harmonic,
+}
+
+impl Default for BandSource {
+    fn default() -> Self {
+        BandSource::All
+    }
+}
+
+impl fmt::Display for BandSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BandSource::All => write!(f, "all"),
+            BandSource::Percussive => write!(f, "percussive"),
+            BandSource::Harmonic => write!(f, "harmonic"),
+        }
+    }
+}
+
+impl FromStr for BandSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(BandSource::All),
+            "percussive" => Ok(BandSource::Percussive),
+            "harmonic" => Ok(BandSource::Harmonic),
+            _ => Err(format!("Unknown band source {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Type of a detected drum hit.
+pub enum DrumEvent {
+    /// Bass drum; mostly low frequencies
+    Kick,
+
+    /// Snare drum; mostly mid frequencies
+    Snare,
+
+    /// Hi-hat or cymbal; mostly high frequencies
+    HiHat,
+}
+
+impl fmt::Display for DrumEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrumEvent::Kick => write!(f, "kick"),
+            DrumEvent::Snare => write!(f, "snare"),
+            DrumEvent::HiHat => write!(f, "hi_hat"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Stereo field features computed from both audio channels of a single frame.
+pub struct StereoFeatures {
+    /// Balance between left and right channel; -1 is left only, 1 is right only
+    pub balance: f32,
+
+    /// Energy of the mid signal (L + R) / 2 for each frequency band
+    pub mid_bands: Vec<f32>,
+
+    /// Energy of the side signal (L - R) / 2 for each frequency band
+    pub side_bands: Vec<f32>,
+
+    /// Phase correlation between left and right channel;
+    /// 1 is mono, 0 is uncorrelated and -1 is out of phase
+    pub correlation: f32,
+}
+
+impl StereoFeatures {
+    /// Creates a new `StereoFeatures` instance representing silence.
+    pub fn new(total_bands: usize) -> Self {
+        StereoFeatures {
+            balance: 0.0,
+            mid_bands: vec![0.0; total_bands],
+            side_bands: vec![0.0; total_bands],
+            correlation: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+/// Estimated musical key.
+pub struct Key {
+    /// Pitch class of the key root; 0 is C, 1 is C#, ..., 11 is B
+    pub root: usize,
+
+    /// Whether the key is minor
+    pub minor: bool,
+
+    /// Correlation of the pitch class distribution with the key profile
+    pub confidence: f32,
+}
@@ -1,6 +1,8 @@
+pub mod analysis;
 pub mod app;
 pub mod audio;
 pub mod buffer;
+pub mod features;
 pub mod led;
 pub mod routes;
 pub mod settings;
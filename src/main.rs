@@ -1,12 +1,13 @@
 use actix_cors::Cors;
 use actix_web::{http, web, App, Error, HttpServer};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
 mod app;
 mod audio;
 mod buffer;
+mod features;
 mod led;
 mod routes;
 mod settings;
@@ -14,11 +15,11 @@ mod theme;
 mod transform;
 mod viz;
 
-use crate::app::{AppState, Visualization};
+use crate::app::AppState;
 use crate::routes::init;
 use crate::settings::Settings;
 use crate::transform::AudioTransformer;
-use crate::viz::VizRunner;
+use crate::viz::{BlendMode, FrameStats, Layer, OutputThemes, Playlist, SystemClock, VizRunner};
 
 extern crate dotenv_codegen;
 
@@ -39,20 +40,10 @@ async fn main() -> Result<()> {
     let mut conf = config::Config::default();
     conf.merge(config::File::with_name(CONFIG)).unwrap();
 
-    let settings: Settings = conf.try_into().unwrap();
+    let mut settings: Settings = conf.try_into().unwrap();
+    settings.load_scripts();
+    settings.load_plugins();
     let shared_settings = Arc::new(Mutex::new(settings)).clone();
-    let visualizations = shared_settings
-        .lock()
-        .unwrap()
-        .visualizations
-        .iter()
-        .map(|v| Visualization {
-            pretty_name: v.get_pretty_name().to_string(),
-            identifier: v.get_name().to_string(),
-            settings: Some(v.get_settings()),
-        })
-        .collect::<Vec<Visualization>>()
-        .clone();
 
     // new audio transformer instance from settings
     // has access to audio stream
@@ -60,24 +51,77 @@ async fn main() -> Result<()> {
         AudioTransformer::new(shared_settings.clone().lock().unwrap().transformer.clone());
     transformer.start();
 
-    // instantiate visualization
-    let mut viz_left = dyn_clone::clone_box(&*shared_settings.lock().unwrap().visualizations[0]);
-    viz_left.set_total_pixels(shared_settings.lock().unwrap().output.left.total_leds as usize);
-    let viz_right = dyn_clone::clone_box(&*viz_left);
+    // instantiate visualization layers; layers that can't be created, e.g. of disabled flashing
+    // visualizations, are skipped; show the first visualization if no layers are left
+    let layers = {
+        let settings = shared_settings.lock().unwrap();
+        let layers = settings
+            .layers
+            .iter()
+            .filter_map(|l| {
+                let layer = Layer::from_settings(l, &settings);
+                if layer.is_none() {
+                    eprintln!(
+                        "Skipping layer with unknown or unavailable visualization or theme {:?}",
+                        l
+                    );
+                }
+                layer
+            })
+            .collect::<Vec<Layer>>();
+
+        if layers.is_empty() {
+            let viz = settings
+                .available_visualizations()
+                .next()
+                .ok_or_else(|| anyhow!("No visualization available"))?;
+            vec![Layer::new(
+                dyn_clone::clone_box(viz),
+                &settings.output,
+                None,
+                1.0,
+                BlendMode::Normal,
+            )]
+        } else {
+            layers
+        }
+    };
+
+    // playlist that rotates through visualizations and themes if enabled
+    let playlist = {
+        let settings = shared_settings.lock().unwrap();
+        if settings.playlist.enabled {
+            let playlist = Playlist::from_settings(&settings.playlist, &settings);
+            if playlist.is_none() {
+                eprintln!(
+                    "Playlist is empty or has an unknown visualization or theme; playlist is disabled"
+                );
+            }
+            playlist
+        } else {
+            None
+        }
+    };
 
     let output_settings = shared_settings.lock().unwrap().output.clone();
     let theme = shared_settings.lock().unwrap().themes[0].clone();
+    let transition_settings = shared_settings.lock().unwrap().transition;
 
     // viz runner will update the visualization periodically
-    let viz_runner = VizRunner {
-        viz_left: Arc::new(Mutex::new(viz_left)),
-        viz_right: Arc::new(Mutex::new(viz_right)),
+    let mut viz_runner = VizRunner {
+        layers: Arc::new(Mutex::new(layers)),
         output_settings: output_settings,
         is_stopped: Arc::new(AtomicBool::from(false)),
-        theme: Arc::new(Mutex::new(theme)),
+        themes: Arc::new(Mutex::new(OutputThemes::new(theme))),
         transformer: Arc::new(Mutex::new(transformer)),
+        clock: Arc::new(SystemClock::new()),
+        stats: Arc::new(Mutex::new(FrameStats::default())),
+        playlist: Arc::new(Mutex::new(None)),
+        transition: Arc::new(Mutex::new(None)),
+        transition_settings: transition_settings,
     };
 
+    viz_runner.set_playlist(playlist);
     viz_runner.start();
 
     // settings to set up web server
@@ -103,8 +147,8 @@ async fn main() -> Result<()> {
             .app_data(web::Data::new(AppState {
                 viz_runner: shared_viz_runner.clone(),
                 themes: themes.clone(),
-                visualizations: visualizations.clone(),
                 settings: shared_settings.clone(),
+                config_path: CONFIG.to_string(),
             }))
             .configure(init)
             .service(
@@ -1,13 +1,15 @@
-use actix_web::{get, post, put, web, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
 
 use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
-use std::sync::Arc;
-
 use crate::app::{AppState, Visualization};
-use crate::settings::TransformerSettings;
+use crate::settings::{
+    persist_setting, LayerSettings, PlaylistSettings, Speaker, TransformerSettings,
+    TransitionSettings,
+};
 use crate::theme::Theme;
+use crate::viz::{FrameStats, Layer, Playlist};
 
 #[derive(Serialize, Deserialize, Clone)]
 struct VisualizationsResponse {
@@ -31,12 +33,20 @@ struct ThemesResponse {
 struct ChangeVisualization {
     /// Identifier of visualization to be activated.
     pub visualization: String,
+
+    #[serde(default)]
+    /// Transition to the new visualization; the default transition is used if not set.
+    pub transition: Option<TransitionSettings>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 struct ChangeTheme {
     /// Identifier of theme to be activated.
     pub theme: String,
+
+    #[serde(default)]
+    /// Transition to the new theme; the default transition is used if not set.
+    pub transition: Option<TransitionSettings>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -45,6 +55,15 @@ struct StatusResponse {
     pub is_stopped: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+struct PlaylistResponse {
+    /// Playlist settings.
+    pub playlist: PlaylistSettings,
+
+    /// Index of the current playlist entry; not set if the playlist isn't running.
+    pub current: Option<usize>,
+}
+
 /// Initializes available routes
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_viz);
@@ -54,23 +73,32 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(turn_off);
     cfg.service(turn_on);
     cfg.service(get_status);
+    cfg.service(get_stats);
     cfg.service(get_transformer_settings);
     cfg.service(update_transformer_settings);
     cfg.service(update_viz_settings);
     cfg.service(set_custom_theme);
+    cfg.service(get_layers);
+    cfg.service(add_layer);
+    cfg.service(update_layer);
+    cfg.service(remove_layer);
+    cfg.service(get_playlist);
+    cfg.service(update_playlist);
+    cfg.service(next_playlist_entry);
+    cfg.service(get_output_viz);
+    cfg.service(update_output_visualization);
+    cfg.service(update_output_viz_settings);
+    cfg.service(get_output_theme);
+    cfg.service(update_output_theme);
 }
 
 #[get("/api/visualization")]
 async fn get_viz(data: web::Data<AppState>) -> impl Responder {
     // Return the current and available visualizations.
-    let visualizations = data.visualizations.clone();
+    let visualizations = data.visualizations();
     let current_viz = data.viz_runner.lock().unwrap();
     let response = VisualizationsResponse {
-        current: Arc::clone(&current_viz.viz_left)
-            .lock()
-            .unwrap()
-            .get_name()
-            .to_string(),
+        current: current_viz.current_visualization(),
         visualizations: visualizations,
     };
     HttpResponse::Ok().json(response)
@@ -82,7 +110,7 @@ async fn get_themes(data: web::Data<AppState>) -> impl Responder {
     let themes = data.themes.clone();
     let current_theme = data.viz_runner.lock().unwrap();
     let response = ThemesResponse {
-        current: current_theme.theme.lock().unwrap().name.clone(),
+        current: current_theme.current_theme(Speaker::Left),
         themes: themes,
     };
     HttpResponse::Ok().json(response)
@@ -94,18 +122,16 @@ async fn update_visualization(
     data: web::Data<AppState>,
 ) -> impl Responder {
     // Activate a new viz.
-    if let Some(viz) = data
+    let viz = data
         .settings
         .lock()
         .unwrap()
-        .visualizations
-        .iter()
-        .find(|&v| v.get_name() == new_visualization.visualization)
-    {
+        .visualization(&new_visualization.visualization);
+    if let Some(viz) = viz {
         data.viz_runner
             .lock()
             .unwrap()
-            .set_visualization(dyn_clone::clone_box(&**viz));
+            .set_visualization(viz, new_visualization.transition);
         HttpResponse::Ok().json(true)
     } else {
         HttpResponse::Ok().json(false)
@@ -119,9 +145,10 @@ async fn update_theme(
 ) -> impl Responder {
     // Activate a new theme.
     let themes = data.themes.clone();
+    let transition = new_theme.transition;
     let new_theme = themes.into_iter().find(|t| t.name == new_theme.theme);
     if let Some(theme) = new_theme {
-        data.viz_runner.lock().unwrap().set_theme(theme);
+        data.viz_runner.lock().unwrap().set_theme(theme, transition);
         HttpResponse::Ok().json(true)
     } else {
         HttpResponse::Ok().json(false)
@@ -134,7 +161,7 @@ async fn set_custom_theme(theme: web::Json<Theme>, data: web::Data<AppState>) ->
     data.viz_runner
         .lock()
         .unwrap()
-        .set_theme(theme.into_inner());
+        .set_theme(theme.into_inner(), None);
     HttpResponse::Ok().json(true)
 }
 
@@ -162,6 +189,13 @@ async fn get_status(data: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().json(response)
 }
 
+#[get("/api/stats")]
+async fn get_stats(data: web::Data<AppState>) -> impl Responder {
+    // Return frame rate statistics.
+    let stats: FrameStats = data.viz_runner.lock().unwrap().stats.lock().unwrap().clone();
+    HttpResponse::Ok().json(stats)
+}
+
 #[get("/api/settings")]
 async fn get_transformer_settings(data: web::Data<AppState>) -> impl Responder {
     // Return the current settings.
@@ -199,3 +233,200 @@ async fn update_viz_settings(
         .update_viz_settings(new_settings.into_inner());
     HttpResponse::Ok().json(true)
 }
+
+#[get("/api/layers")]
+async fn get_layers(data: web::Data<AppState>) -> impl Responder {
+    // Return the visualization layers from bottom to top.
+    let layers = data.viz_runner.lock().unwrap().layer_settings();
+    HttpResponse::Ok().json(layers)
+}
+
+#[post("/api/layers")]
+async fn add_layer(
+    layer_settings: web::Json<LayerSettings>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Add a new layer on top of the other layers.
+    let layer = Layer::from_settings(&layer_settings, &data.settings.lock().unwrap());
+    if let Some(layer) = layer {
+        data.viz_runner.lock().unwrap().add_layer(layer);
+        HttpResponse::Ok().json(true)
+    } else {
+        HttpResponse::Ok().json(false)
+    }
+}
+
+#[put("/api/layers/{index}")]
+async fn update_layer(
+    index: web::Path<usize>,
+    layer_settings: web::Json<LayerSettings>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Change visualization, theme, opacity or blend mode of a layer.
+    let settings = data.settings.lock().unwrap();
+    let updated = data.viz_runner.lock().unwrap().update_layer(
+        index.into_inner(),
+        &layer_settings,
+        &settings,
+    );
+    HttpResponse::Ok().json(updated)
+}
+
+#[delete("/api/layers/{index}")]
+async fn remove_layer(index: web::Path<usize>, data: web::Data<AppState>) -> impl Responder {
+    // Remove a layer; the last remaining layer can't be removed.
+    let removed = data
+        .viz_runner
+        .lock()
+        .unwrap()
+        .remove_layer(index.into_inner());
+    HttpResponse::Ok().json(removed)
+}
+
+#[get("/api/playlist")]
+async fn get_playlist(data: web::Data<AppState>) -> impl Responder {
+    // Return the playlist and its current entry.
+    let playlist = data.settings.lock().unwrap().playlist.clone();
+    let response = PlaylistResponse {
+        playlist,
+        current: data.viz_runner.lock().unwrap().current_playlist_entry(),
+    };
+    HttpResponse::Ok().json(response)
+}
+
+#[put("/api/playlist")]
+async fn update_playlist(
+    playlist_settings: web::Json<PlaylistSettings>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Replace the playlist, start or stop it and persist it to the config file.
+    // Entries with invalid durations or beats are already rejected with 400 when parsing.
+    let playlist_settings = playlist_settings.into_inner();
+    let is_enabled = playlist_settings.enabled;
+    let playlist = {
+        let mut settings = data.settings.lock().unwrap();
+        let playlist = Playlist::from_settings(&playlist_settings, &settings);
+
+        // entries need to exist, and an enabled playlist can't be empty
+        let is_valid = match playlist {
+            Some(_) => true,
+            None => playlist_settings.entries.is_empty() && !playlist_settings.enabled,
+        };
+        if !is_valid || persist_setting(&data.config_path, "playlist", &playlist_settings).is_err()
+        {
+            return HttpResponse::Ok().json(false);
+        }
+
+        settings.playlist = playlist_settings;
+        playlist
+    };
+
+    // settings are unlocked before locking the runner
+    data.viz_runner
+        .lock()
+        .unwrap()
+        .set_playlist(playlist.filter(|_| is_enabled));
+    HttpResponse::Ok().json(true)
+}
+
+#[post("/api/playlist/next")]
+async fn next_playlist_entry(data: web::Data<AppState>) -> impl Responder {
+    // Skip to the next playlist entry.
+    let skipped = data.viz_runner.lock().unwrap().next_playlist_entry();
+    HttpResponse::Ok().json(skipped)
+}
+
+#[get("/api/output/{speaker}/visualization")]
+async fn get_output_viz(speaker: web::Path<Speaker>, data: web::Data<AppState>) -> impl Responder {
+    // Return the current visualization of a speaker and the available visualizations.
+    let visualizations = data.visualizations();
+    let response = VisualizationsResponse {
+        current: data
+            .viz_runner
+            .lock()
+            .unwrap()
+            .current_output_visualization(speaker.into_inner()),
+        visualizations,
+    };
+    HttpResponse::Ok().json(response)
+}
+
+#[put("/api/output/{speaker}/visualization")]
+async fn update_output_visualization(
+    speaker: web::Path<Speaker>,
+    new_visualization: web::Json<ChangeVisualization>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Activate a new viz for a single speaker.
+    let viz = data
+        .settings
+        .lock()
+        .unwrap()
+        .visualization(&new_visualization.visualization);
+    if let Some(viz) = viz {
+        data.viz_runner.lock().unwrap().set_output_visualization(
+            speaker.into_inner(),
+            viz,
+            new_visualization.transition,
+        );
+        HttpResponse::Ok().json(true)
+    } else {
+        HttpResponse::Ok().json(false)
+    }
+}
+
+#[put("/api/output/{speaker}/visualization/{id}")]
+async fn update_output_viz_settings(
+    path: web::Path<(Speaker, String)>,
+    new_settings: web::Json<HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Set new settings for the active viz of a single speaker.
+    let (speaker, _) = path.into_inner();
+    data.viz_runner
+        .lock()
+        .unwrap()
+        .update_output_viz_settings(speaker, new_settings.into_inner());
+    HttpResponse::Ok().json(true)
+}
+
+#[get("/api/output/{speaker}/theme")]
+async fn get_output_theme(
+    speaker: web::Path<Speaker>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Return the current theme of a speaker and the available themes.
+    let response = ThemesResponse {
+        current: data
+            .viz_runner
+            .lock()
+            .unwrap()
+            .current_theme(speaker.into_inner()),
+        themes: data.themes.clone(),
+    };
+    HttpResponse::Ok().json(response)
+}
+
+#[put("/api/output/{speaker}/theme")]
+async fn update_output_theme(
+    speaker: web::Path<Speaker>,
+    new_theme: web::Json<ChangeTheme>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Activate a new theme for a single speaker.
+    let theme = data
+        .themes
+        .iter()
+        .find(|t| t.name == new_theme.theme)
+        .cloned();
+    if let Some(theme) = theme {
+        data.viz_runner.lock().unwrap().set_output_theme(
+            speaker.into_inner(),
+            theme,
+            new_theme.transition,
+        );
+        HttpResponse::Ok().json(true)
+    } else {
+        HttpResponse::Ok().json(false)
+    }
+}
@@ -1,15 +1,22 @@
+use anyhow::anyhow;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::ser::PrettyFormatter;
 use serde_json::Value;
 
 use crate::led::Led;
 use crate::theme::Theme;
 use crate::viz::{
-    BlendViz, BlendVizConfig, CenterViz, CenterVizConfig, FadingBeatViz, FadingBeatVizConfig,
-    RotatingViz, RotatingVizConfig, SolidBeatViz, SolidBeatVizConfig, SolidViz, SolidVizConfig,
-    SparkleViz, SparkleVizConfig, Viz,
+    BlendMode, BlendViz, BlendVizConfig, CenterViz, CenterVizConfig, FadingBeatViz,
+    FadingBeatVizConfig, FireViz, FireVizConfig, PlasmaViz, PlasmaVizConfig, PluginViz, RippleViz,
+    RippleVizConfig, RotatingViz, RotatingVizConfig, ScriptViz, SolidBeatViz, SolidBeatVizConfig,
+    SolidViz, SolidVizConfig, SparkleViz, SparkleVizConfig, SpectrumViz, SpectrumVizConfig,
+    StrobeViz, StrobeVizConfig, Viz, WaterfallViz, WaterfallVizConfig,
 };
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Clone)]
 /// Settings for a single output
@@ -28,6 +35,38 @@ impl Output {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Identifies one of the speakers and its output.
+pub enum Speaker {
+    /// Left speaker
+    Left,
+
+    /// Right speaker
+    Right,
+}
+
+impl fmt::Display for Speaker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Speaker::Left => write!(f, "left"),
+            Speaker::Right => write!(f, "right"),
+        }
+    }
+}
+
+impl FromStr for Speaker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Speaker::Left),
+            "right" => Ok(Speaker::Right),
+            _ => Err(format!("Unknown speaker {}", s)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 /// Settings for left and right outputs.
 pub struct OutputSettings {
@@ -36,6 +75,124 @@ pub struct OutputSettings {
 
     /// Right output settings
     pub right: Output,
+
+    #[serde(default = "default_fps", deserialize_with = "parse_fps")]
+    /// Target number of frames shown per second; limited to `MIN_FPS` and `MAX_FPS`
+    pub fps: f32,
+
+    #[serde(default)]
+    /// Settings for treating both outputs as a single strip
+    pub linked: LinkedSettings,
+}
+
+impl OutputSettings {
+    /// Returns the settings of the output of the provided speaker.
+    pub fn get(&self, speaker: Speaker) -> &Output {
+        match speaker {
+            Speaker::Left => &self.left,
+            Speaker::Right => &self.right,
+        }
+    }
+
+    /// Returns the number of pixels the visualizations of the provided speaker render.
+    ///
+    /// If the outputs are linked, the visualizations of the left speaker render both outputs.
+    ///
+    pub fn total_pixels(&self, speaker: Speaker) -> usize {
+        match speaker {
+            Speaker::Left if self.linked.enabled => {
+                (self.left.total_leds + self.right.total_leds) as usize
+            }
+            _ => self.get(speaker).total_leds as usize,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Order in which linked outputs form a single strip.
+pub enum LinkOrder {
+    /// Left output first, followed by the right output
+    LeftRight,
+
+    /// Right output first, followed by the left output
+    RightLeft,
+}
+
+impl Default for LinkOrder {
+    fn default() -> Self {
+        LinkOrder::LeftRight
+    }
+}
+
+impl fmt::Display for LinkOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkOrder::LeftRight => write!(f, "left_right"),
+            LinkOrder::RightLeft => write!(f, "right_left"),
+        }
+    }
+}
+
+impl FromStr for LinkOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left_right" => Ok(LinkOrder::LeftRight),
+            "right_left" => Ok(LinkOrder::RightLeft),
+            _ => Err(format!("Unknown link order {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+/// Settings for treating both outputs as a single continuous strip.
+pub struct LinkedSettings {
+    #[serde(default)]
+    /// Whether a single visualization renders both outputs
+    pub enabled: bool,
+
+    #[serde(default)]
+    /// Order in which the outputs form the strip
+    pub order: LinkOrder,
+
+    #[serde(default)]
+    /// Whether the pixels of the left output run in reverse direction along the strip
+    pub reverse_left: bool,
+
+    #[serde(default)]
+    /// Whether the pixels of the right output run in reverse direction along the strip
+    pub reverse_right: bool,
+}
+
+/// Default target frames per second of the outputs.
+fn default_fps() -> f32 {
+    60.0
+}
+
+/// Lowest target frames per second of the outputs.
+pub const MIN_FPS: f32 = 1.0;
+
+/// Highest target frames per second of the outputs.
+pub const MAX_FPS: f32 = 240.0;
+
+/// Custom target frames per second parsing from JSON file.
+///
+/// Values outside of `MIN_FPS` and `MAX_FPS` are clamped; the default is used for values
+/// that are not a positive finite number, since frame durations can't be computed from them.
+///
+fn parse_fps<'de, D>(d: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let fps = f32::deserialize(d)?;
+    if fps.is_finite() && fps > 0.0 {
+        Ok(fps.clamp(MIN_FPS, MAX_FPS))
+    } else {
+        eprintln!("Invalid fps {}, using {}", fps, default_fps());
+        Ok(default_fps())
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -63,6 +220,52 @@ pub struct TransformerSettings {
 
     /// Audio buffer size
     pub buffer_size: usize,
+
+    #[serde(default)]
+    /// Analysis used to compute frequency bands
+    pub analysis: Analysis,
+
+    #[serde(default)]
+    /// Whether audio is separated into harmonic and percussive parts
+    pub hpss: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines how the audio spectrum is divided into frequency bands.
+pub enum Analysis {
+    /// FFT bins are merged into log-spaced bands
+    Fft,
+
+    /// Constant-Q transform; each band has the same resolution per octave
+    ConstantQ,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Analysis::Fft
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Analysis::Fft => write!(f, "fft"),
+            Analysis::ConstantQ => write!(f, "constant_q"),
+        }
+    }
+}
+
+impl FromStr for Analysis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fft" => Ok(Analysis::Fft),
+            "constant_q" => Ok(Analysis::ConstantQ),
+            _ => Err(format!("Unknown analysis {}", s)),
+        }
+    }
 }
 
 impl TransformerSettings {
@@ -77,6 +280,8 @@ impl TransformerSettings {
         settings.insert("monstercat".to_string(), self.monstercat.to_string());
         settings.insert("decay".to_string(), self.decay.to_string());
         settings.insert("buffer_size".to_string(), self.buffer_size.to_string());
+        settings.insert("analysis".to_string(), self.analysis.to_string());
+        settings.insert("hpss".to_string(), self.hpss.to_string());
         settings
     }
 
@@ -122,6 +327,345 @@ impl TransformerSettings {
                 .unwrap_or(&"0".to_string())
                 .parse::<usize>()
                 .unwrap(),
+            analysis: settings
+                .get(&"analysis".to_string())
+                .unwrap_or(&"fft".to_string())
+                .parse::<Analysis>()
+                .unwrap(),
+            hpss: settings
+                .get(&"hpss".to_string())
+                .unwrap_or(&"false".to_string())
+                .parse::<bool>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Settings of a layer in the visualization layer stack.
+pub struct LayerSettings {
+    /// Identifier of the visualization
+    pub viz: String,
+
+    #[serde(default)]
+    /// Visualization settings overriding the defaults of the visualization
+    pub settings: Option<HashMap<String, String>>,
+
+    #[serde(default)]
+    /// Name of the layer theme; the active theme is used if not set
+    pub theme: Option<String>,
+
+    #[serde(default = "default_opacity")]
+    /// Opacity of the layer between 0 and 1
+    pub opacity: f32,
+
+    #[serde(default)]
+    /// Determines how the layer is combined with the layers below
+    pub blend_mode: BlendMode,
+}
+
+/// Default opacity of layers.
+fn default_opacity() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization and theme shown for some time as part of the playlist.
+pub struct PlaylistEntry {
+    /// Identifier of the visualization
+    pub viz: String,
+
+    #[serde(default)]
+    /// Name of the theme; the active theme is kept if not set
+    pub theme: Option<String>,
+
+    #[serde(
+        default = "default_entry_duration",
+        deserialize_with = "parse_entry_duration"
+    )]
+    /// Maximum time in seconds the entry is shown; at least `MIN_ENTRY_DURATION`
+    pub duration: f32,
+
+    #[serde(
+        default = "default_entry_beats",
+        deserialize_with = "parse_entry_beats"
+    )]
+    /// Number of beats after which the playlist advances if it advances on beats; at least 1
+    pub beats: u32,
+}
+
+/// Default duration of playlist entries in seconds.
+fn default_entry_duration() -> f32 {
+    60.0
+}
+
+/// Default number of beats playlist entries are shown.
+fn default_entry_beats() -> u32 {
+    64
+}
+
+/// Shortest time in seconds playlist entries are shown.
+pub const MIN_ENTRY_DURATION: f32 = 1.0;
+
+/// Custom playlist entry duration parsing from JSON file.
+///
+/// Durations shorter than `MIN_ENTRY_DURATION` are rejected, since the playlist would
+/// advance and start a new transition in almost every frame.
+///
+fn parse_entry_duration<'de, D>(d: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = f32::deserialize(d)?;
+    if duration.is_finite() && duration >= MIN_ENTRY_DURATION {
+        Ok(duration)
+    } else {
+        Err(D::Error::custom(format!(
+            "Invalid playlist entry duration {}, needs to be at least {}",
+            duration, MIN_ENTRY_DURATION
+        )))
+    }
+}
+
+/// Custom playlist entry beats parsing from JSON file.
+///
+/// Entries shown for no beats are rejected for the same reason as too short durations.
+///
+fn parse_entry_beats<'de, D>(d: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let beats = u32::deserialize(d)?;
+    if beats >= 1 {
+        Ok(beats)
+    } else {
+        Err(D::Error::custom(
+            "Invalid playlist entry beats 0, needs to be at least 1",
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines when the playlist advances to the next entry.
+pub enum PlaylistAdvance {
+    /// After the duration of the entry
+    Timer,
+
+    /// When a new song starts or after the duration of the entry
+    SongChange,
+
+    /// After the number of beats of the entry or after its duration
+    Beats,
+}
+
+impl Default for PlaylistAdvance {
+    fn default() -> Self {
+        PlaylistAdvance::Timer
+    }
+}
+
+impl fmt::Display for PlaylistAdvance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaylistAdvance::Timer => write!(f, "timer"),
+            PlaylistAdvance::SongChange => write!(f, "song_change"),
+            PlaylistAdvance::Beats => write!(f, "beats"),
+        }
+    }
+}
+
+impl FromStr for PlaylistAdvance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "timer" => Ok(PlaylistAdvance::Timer),
+            "song_change" => Ok(PlaylistAdvance::SongChange),
+            "beats" => Ok(PlaylistAdvance::Beats),
+            _ => Err(format!("Unknown playlist advance {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+/// Settings of the visualization playlist.
+pub struct PlaylistSettings {
+    #[serde(default)]
+    /// Whether the playlist is running
+    pub enabled: bool,
+
+    #[serde(default)]
+    /// Visualizations and themes of the playlist
+    pub entries: Vec<PlaylistEntry>,
+
+    #[serde(default)]
+    /// Whether entries are shown in random order instead of the configured order
+    pub shuffle: bool,
+
+    #[serde(default)]
+    /// Determines when the playlist advances to the next entry
+    pub advance: PlaylistAdvance,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines how the previous and the new visualization are blended during a transition.
+pub enum TransitionCurve {
+    /// Crossfade at constant speed
+    Linear,
+
+    /// Crossfade that starts and ends slowly
+    Ease,
+
+    /// New visualization spreads from the center of the strip to its ends
+    Wipe,
+}
+
+impl Default for TransitionCurve {
+    fn default() -> Self {
+        TransitionCurve::Linear
+    }
+}
+
+impl fmt::Display for TransitionCurve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransitionCurve::Linear => write!(f, "linear"),
+            TransitionCurve::Ease => write!(f, "ease"),
+            TransitionCurve::Wipe => write!(f, "wipe"),
+        }
+    }
+}
+
+impl FromStr for TransitionCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(TransitionCurve::Linear),
+            "ease" => Ok(TransitionCurve::Ease),
+            "wipe" => Ok(TransitionCurve::Wipe),
+            _ => Err(format!("Unknown transition curve {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+/// Settings of transitions between visualizations or themes.
+pub struct TransitionSettings {
+    #[serde(default = "default_transition_duration")]
+    /// Duration of the transition in seconds; switches instantly if 0
+    pub duration: f32,
+
+    #[serde(default)]
+    /// Determines how the previous and the new visualization are blended
+    pub curve: TransitionCurve,
+}
+
+/// Default duration of transitions in seconds.
+fn default_transition_duration() -> f32 {
+    1.0
+}
+
+impl Default for TransitionSettings {
+    fn default() -> Self {
+        TransitionSettings {
+            duration: default_transition_duration(),
+            curve: TransitionCurve::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Settings of visualizations defined by scripts.
+pub struct ScriptSettings {
+    #[serde(default = "default_script_dir")]
+    /// Directory script visualizations are loaded from
+    pub dir: String,
+
+    #[serde(default = "default_max_operations")]
+    /// Maximum number of operations a script can run per update before it is aborted
+    pub max_operations: u64,
+}
+
+/// Default directory of script visualizations.
+fn default_script_dir() -> String {
+    "scripts".to_string()
+}
+
+/// Default maximum number of operations per script update.
+fn default_max_operations() -> u64 {
+    100_000
+}
+
+impl Default for ScriptSettings {
+    fn default() -> Self {
+        ScriptSettings {
+            dir: default_script_dir(),
+            max_operations: default_max_operations(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Settings of visualizations implemented as WebAssembly plugins.
+pub struct PluginSettings {
+    #[serde(default = "default_plugin_dir")]
+    /// Directory plugin modules are loaded from
+    pub dir: String,
+
+    #[serde(default = "default_max_fuel")]
+    /// Maximum fuel a plugin can consume per call before it is aborted; roughly one per instruction
+    pub max_fuel: u64,
+
+    #[serde(default = "default_max_memory")]
+    /// Maximum size of the memory of a plugin in bytes
+    pub max_memory: usize,
+}
+
+/// Default directory of plugin modules.
+fn default_plugin_dir() -> String {
+    "plugins".to_string()
+}
+
+/// Default maximum fuel per plugin call; a call running out of it takes about 2 ms on a desktop CPU.
+fn default_max_fuel() -> u64 {
+    1_000_000
+}
+
+/// Default maximum plugin memory size in bytes.
+fn default_max_memory() -> usize {
+    16 * 1024 * 1024
+}
+
+impl Default for PluginSettings {
+    fn default() -> Self {
+        PluginSettings {
+            dir: default_plugin_dir(),
+            max_fuel: default_max_fuel(),
+            max_memory: default_max_memory(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Settings protecting viewers from flashing lights.
+pub struct SafetySettings {
+    #[serde(default = "default_strobe_enabled")]
+    /// Whether the built-in `strobe_viz` is available; scripts and plugins can flash regardless
+    pub strobe_enabled: bool,
+}
+
+/// Default availability of flashing visualizations.
+fn default_strobe_enabled() -> bool {
+    true
+}
+
+impl Default for SafetySettings {
+    fn default() -> Self {
+        SafetySettings {
+            strobe_enabled: default_strobe_enabled(),
         }
     }
 }
@@ -139,6 +683,30 @@ pub struct Settings {
     /// Available themes
     pub themes: Vec<Theme>,
 
+    #[serde(default)]
+    /// Visualization layers from bottom to top; only the first visualization is shown if empty
+    pub layers: Vec<LayerSettings>,
+
+    #[serde(default)]
+    /// Visualization playlist
+    pub playlist: PlaylistSettings,
+
+    #[serde(default)]
+    /// Transition used when switching visualization or theme
+    pub transition: TransitionSettings,
+
+    #[serde(default)]
+    /// Script visualization settings
+    pub scripts: ScriptSettings,
+
+    #[serde(default)]
+    /// Plugin visualization settings
+    pub plugins: PluginSettings,
+
+    #[serde(default)]
+    /// Photosensitivity safety settings
+    pub safety: SafetySettings,
+
     pub transformer: TransformerSettings,
 
     /// Server host IP
@@ -153,6 +721,118 @@ impl Settings {
     pub fn apply_transformer_settings(&mut self, transformer_settings: TransformerSettings) {
         self.transformer = transformer_settings;
     }
+
+    /// Adds the visualizations of the scripts in the script directory to the available visualizations.
+    ///
+    /// Scripts that are already available are kept and scripts whose files have been deleted are
+    /// removed, so the directory can be rescanned for changes.
+    ///
+    pub fn load_scripts(&mut self) {
+        self.remove_deleted_scripts();
+        let scripts = ScriptViz::load_new(&self.scripts, &self.script_names());
+        self.add_scripts(scripts);
+    }
+
+    /// Returns the identifiers of the available script visualizations.
+    pub fn script_names(&self) -> Vec<String> {
+        self.visualizations
+            .iter()
+            .map(|v| v.get_name().to_string())
+            .filter(|name| ScriptViz::path(name, &self.scripts).is_some())
+            .collect()
+    }
+
+    /// Adds script visualizations unless visualizations with the same identifiers are available.
+    pub fn add_scripts(&mut self, scripts: Vec<ScriptViz>) {
+        for viz in scripts {
+            if !self
+                .visualizations
+                .iter()
+                .any(|v| v.get_name() == viz.get_name())
+            {
+                self.visualizations.push(Box::new(viz));
+            }
+        }
+    }
+
+    /// Removes the script visualizations whose script files don't exist anymore.
+    pub fn remove_deleted_scripts(&mut self) {
+        let scripts = &self.scripts;
+        self.visualizations
+            .retain(|v| match ScriptViz::path(v.get_name(), scripts) {
+                Some(path) => path.is_file(),
+                None => true,
+            });
+    }
+
+    /// Adds the visualizations of the modules in the plugin directory to the available visualizations.
+    pub fn load_plugins(&mut self) {
+        for viz in PluginViz::load_dir(&self.plugins) {
+            self.visualizations.push(Box::new(viz));
+        }
+    }
+
+    /// Returns whether the visualization with the provided identifier can be shown.
+    ///
+    /// The built-in strobe isn't available if it is disabled in the safety settings; scripts and
+    /// plugins aren't covered by the safety settings.
+    ///
+    pub fn is_available(&self, name: &str) -> bool {
+        self.safety.strobe_enabled || name != "strobe_viz"
+    }
+
+    /// Returns the visualizations that can be shown.
+    pub fn available_visualizations(&self) -> impl Iterator<Item = &(dyn Viz + 'static)> {
+        self.visualizations
+            .iter()
+            .map(|v| &**v)
+            .filter(move |v| self.is_available(v.get_name()))
+    }
+
+    /// Returns a copy of the visualization with the provided identifier.
+    ///
+    /// Returns `None` if the visualization doesn't exist or isn't available. Scripts added to
+    /// the script directory since scripts have been loaded are loaded on demand.
+    ///
+    pub fn visualization(&self, name: &str) -> Option<Box<dyn Viz>> {
+        if !self.is_available(name) {
+            return None;
+        }
+
+        self.visualizations
+            .iter()
+            .find(|v| v.get_name() == name)
+            .map(|v| dyn_clone::clone_box(&**v))
+            .or_else(|| ScriptViz::load(name, &self.scripts).map(|v| Box::new(v) as Box<dyn Viz>))
+    }
+
+    /// Returns the theme with the provided name.
+    pub fn theme(&self, name: &str) -> Option<Theme> {
+        self.themes.iter().find(|t| t.name == name).cloned()
+    }
+}
+
+/// Writes a value to a top-level key of the config file.
+///
+/// The rest of the config file, including the order of keys, is kept as it is.
+///
+pub fn persist_setting<T: Serialize>(path: &str, key: &str, value: &T) -> anyhow::Result<()> {
+    let mut config: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    config
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("{} is not a JSON object", path))?
+        .insert(key.to_string(), serde_json::to_value(value)?);
+
+    // keep the indentation of config.json
+    let mut serialized = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(
+        &mut serialized,
+        PrettyFormatter::with_indent(b"    "),
+    );
+    config.serialize(&mut serializer)?;
+    serialized.push(b'\n');
+    fs::write(path, serialized)?;
+    Ok(())
 }
 
 /// Creates a new visualization from the settings.
@@ -199,6 +879,32 @@ where
                         serde_json::from_value(args.clone()).unwrap();
                     Ok(Box::new(FadingBeatViz::new(viz_config)))
                 }
+                "spectrum_viz" => {
+                    let viz_config: SpectrumVizConfig =
+                        serde_json::from_value(args.clone()).unwrap();
+                    Ok(Box::new(SpectrumViz::new(viz_config)))
+                }
+                "fire_viz" => {
+                    let viz_config: FireVizConfig = serde_json::from_value(args.clone()).unwrap();
+                    Ok(Box::new(FireViz::new(viz_config)))
+                }
+                "waterfall_viz" => {
+                    let viz_config: WaterfallVizConfig =
+                        serde_json::from_value(args.clone()).unwrap();
+                    Ok(Box::new(WaterfallViz::new(viz_config)))
+                }
+                "ripple_viz" => {
+                    let viz_config: RippleVizConfig = serde_json::from_value(args.clone()).unwrap();
+                    Ok(Box::new(RippleViz::new(viz_config)))
+                }
+                "plasma_viz" => {
+                    let viz_config: PlasmaVizConfig = serde_json::from_value(args.clone()).unwrap();
+                    Ok(Box::new(PlasmaViz::new(viz_config)))
+                }
+                "strobe_viz" => {
+                    let viz_config: StrobeVizConfig = serde_json::from_value(args.clone()).unwrap();
+                    Ok(Box::new(StrobeViz::new(viz_config)))
+                }
                 _ => Err(D::Error::custom(format!("Unknown {:?}", name.as_str()))),
             };
             viz
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::features::AudioFeatures;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
 /// Represents the RGB values of a color.
 pub struct Color {
@@ -27,6 +29,11 @@ impl Color {
             b: if v[2] == 0 { 1 } else { v[2] },
         }
     }
+
+    /// Returns the perceived luminance of the color between 0 and 1.
+    pub fn luminance(&self) -> f32 {
+        (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -38,6 +45,61 @@ pub struct Theme {
     #[serde(deserialize_with = "parse_colors")]
     /// Set of colors theme consists of
     pub colors: Vec<Color>,
+
+    #[serde(default)]
+    /// Determines how theme colors are assigned
+    pub mode: ThemeMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines how theme colors are assigned.
+pub enum ThemeMode {
+    /// Colors are assigned based on the frequency band index
+    Bands,
+
+    /// Colors follow the estimated musical key; the color of the key root comes first
+    Harmonic,
+}
+
+impl Default for ThemeMode {
+    fn default() -> Self {
+        ThemeMode::Bands
+    }
+}
+
+impl Theme {
+    /// Returns the theme colors to use for the provided audio features.
+    ///
+    /// In harmonic mode, each of the 12 pitch classes is mapped to a theme color and
+    /// colors are ordered by the energy of their pitch classes, so the color of the
+    /// strongest pitch class, like the root of the current chord, comes first. Pitch classes
+    /// with the same energy are ordered starting at the estimated key root.
+    ///
+    pub fn colors_for(&self, features: &AudioFeatures) -> Vec<Color> {
+        match self.mode {
+            ThemeMode::Bands => self.colors.clone(),
+            ThemeMode::Harmonic => {
+                let total_colors = self.colors.len();
+                let energy =
+                    |pitch_class: usize| features.chroma.get(pitch_class).copied().unwrap_or(0.0);
+
+                let mut pitch_classes: Vec<usize> =
+                    (0..12).map(|i| (features.key.root + i) % 12).collect();
+                pitch_classes.sort_by(|a, b| energy(*b).total_cmp(&energy(*a)));
+
+                // colors of pitch classes first, then colors no pitch class is mapped to
+                let mut order: Vec<usize> = Vec::with_capacity(total_colors);
+                let mapped = pitch_classes.iter().map(|p| p * total_colors / 12);
+                for index in mapped.chain(0..total_colors) {
+                    if !order.contains(&index) {
+                        order.push(index);
+                    }
+                }
+                order.into_iter().map(|i| self.colors[i]).collect()
+            }
+        }
+    }
 }
 
 /// Custom color parsing from JSON file.
@@ -3,9 +3,12 @@ use byteorder::LittleEndian as Le;
 use bytes::buf::BufMut;
 use bytes::Buf;
 use bytes::BytesMut;
-use rustfft::num_complex::Complex;
-use rustfft::num_traits::Zero;
+use chrono::prelude::*;
+use realfft::num_complex::Complex;
+use realfft::num_traits::Zero;
+use realfft::{RealFftPlanner, RealToComplex};
 use rustfft::FftPlanner;
+use std::f32::consts::PI;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -13,7 +16,75 @@ use std::thread::JoinHandle;
 use std::time;
 
 use crate::audio::AudioStream;
-use crate::settings::TransformerSettings;
+use crate::features::{AudioFeatures, DrumEvent, Key, StereoFeatures, MIN_LOUDNESS};
+use crate::settings::{Analysis, TransformerSettings};
+
+/// Number of past spectral flux values used for detecting onsets
+const ONSET_HISTORY_LEN: usize = 30;
+
+/// Number of standard deviations spectral flux needs to exceed the average to be an onset
+const ONSET_SENSITIVITY: f32 = 2.0;
+
+/// Minimum spectral flux required for an onset; prevents onsets on noise
+const ONSET_MIN_FLUX: f32 = 0.01;
+
+/// Minimum number of frames between two onsets
+const ONSET_COOLDOWN: usize = 6;
+
+/// Frequencies in Hz below this cutoff are attributed to kicks
+const DRUM_KICK_CUTOFF: f32 = 150.0;
+
+/// Frequencies in Hz above this cutoff are attributed to hi-hats
+const DRUM_HIHAT_CUTOFF: f32 = 5000.0;
+
+/// Number of frames after an onset used to classify drum hits; the first frames of a hit
+/// only contain its broadband attack
+const DRUM_CLASSIFY_FRAMES: usize = 4;
+
+/// Minimum energy increase after an onset relative to the energy of the frame for a drum hit;
+/// filters out onsets of sustained sounds
+const DRUM_MIN_INCREASE: f32 = 0.25;
+
+/// Minimum share of low frequency energy increase for a kick
+const DRUM_KICK_SHARE: f32 = 0.4;
+
+/// Minimum share of high frequency energy increase for a hi-hat
+const DRUM_HIHAT_SHARE: f32 = 0.1;
+
+/// Lowest frequency in Hz taken into account for the chromagram
+const CHROMA_LOWER_CUTOFF: f32 = 55.0;
+
+/// Highest frequency in Hz taken into account for the chromagram
+const CHROMA_UPPER_CUTOFF: f32 = 5000.0;
+
+/// Factor determining how much previous frames contribute to the key estimate
+const CHROMA_SMOOTHING: f32 = 0.98;
+
+/// Number of frames averaged for the momentary loudness; roughly 400ms in the live loop
+const LOUDNESS_HISTORY_LEN: usize = 96;
+
+/// Number of previous frames median filtered to enhance the harmonic part
+const HPSS_HARMONIC_LEN: usize = 17;
+
+/// Only every n-th frame is kept for median filtering the harmonic part; consecutive frames
+/// overlap, so the history needs to cover a longer time than percussive sounds are visible
+const HPSS_HISTORY_INTERVAL: usize = 4;
+
+/// Number of FFT bins median filtered to enhance the percussive part
+const HPSS_PERCUSSIVE_LEN: usize = 17;
+
+/// Spectral kernel values below this fraction of the kernel maximum are dropped
+const CONSTANT_Q_SPARSITY: f32 = 0.01;
+
+/// Krumhansl-Schmuckler key profile for major keys starting at the root
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+
+/// Krumhansl-Schmuckler key profile for minor keys starting at the root
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
 
 /// Audio stream transformed into frequency bands.
 pub struct TransformedAudio {
@@ -28,6 +99,30 @@ pub struct TransformedAudio {
 
     /// Falloff factor for each frequency band
     pub falloff: Vec<f32>,
+
+    /// Normalized spectrum of the previous frame
+    pub spectrum: Vec<f32>,
+
+    /// Previous spectral flux values; most recent value is stored at index=0
+    pub flux_history: Vec<f32>,
+
+    /// Number of frames since the last detected onset
+    pub frames_since_onset: usize,
+
+    /// Energy increase of low, mid and high frequencies accumulated since the last onset
+    pub drum_flux: [f32; 3],
+
+    /// Number of frames remaining until a drum hit is classified
+    pub drum_frames: usize,
+
+    /// Smoothed chroma vector used for key estimation
+    pub chroma: Vec<f32>,
+
+    /// Previous A-weighted mean square values; most recent value is stored at index=0
+    pub loudness_history: Vec<f32>,
+
+    /// A-weighting gain of each spectrum bin; computed on first use
+    pub loudness_weights: Vec<f32>,
 }
 
 impl TransformedAudio {
@@ -38,10 +133,514 @@ impl TransformedAudio {
             bands: vec![0.0; total_bands],
             band_peaks: vec![0.0; total_bands],
             falloff: vec![0.0; total_bands],
+            spectrum: vec![0.0; fft_len / 2],
+            flux_history: vec![0.0; ONSET_HISTORY_LEN],
+            frames_since_onset: ONSET_COOLDOWN,
+            drum_flux: [0.0; 3],
+            drum_frames: 0,
+            chroma: vec![0.0; 12],
+            loudness_history: vec![0.0; LOUDNESS_HISTORY_LEN],
+            loudness_weights: Vec::new(),
         }
     }
 }
 
+/// Buffers and transformation state of a single audio channel.
+pub struct FrameChannel {
+    /// Normalized audio samples
+    pub samples: Vec<f32>,
+
+    /// FFT input; gets overwritten when computing the FFT
+    fft_input: Vec<f32>,
+
+    /// FFT output; only contains the first half of the spectrum
+    pub fft_output: Vec<Complex<f32>>,
+
+    /// FFT magnitudes; the upper half mirrors the lower half as for a complex FFT
+    pub magnitudes: Vec<f32>,
+
+    /// Normalized magnitudes of the first half of the spectrum
+    pub spectrum: Vec<f32>,
+
+    /// Frequency band magnitudes
+    pub bands: Vec<f32>,
+
+    /// Transformation state
+    pub transformed: TransformedAudio,
+
+    /// Harmonic/percussive separation; `None` if disabled
+    pub hpss: Option<Hpss>,
+}
+
+impl FrameChannel {
+    /// Allocates all buffers required for transforming a channel.
+    fn new(total_bands: usize, fft_len: usize, hpss: bool) -> FrameChannel {
+        FrameChannel {
+            hpss: if hpss {
+                Some(Hpss::new(total_bands, fft_len))
+            } else {
+                None
+            },
+            samples: vec![0.0; fft_len],
+            fft_input: vec![0.0; fft_len],
+            fft_output: vec![Zero::zero(); fft_len / 2 + 1],
+            magnitudes: vec![0.0; fft_len],
+            spectrum: vec![0.0; fft_len / 2],
+            bands: vec![0.0; total_bands],
+            transformed: TransformedAudio::new(total_bands, fft_len),
+        }
+    }
+
+    /// Computes the FFT of the current samples and derives the FFT magnitudes.
+    fn fft(&mut self, fft: &dyn RealToComplex<f32>, fft_scratch: &mut [Complex<f32>]) {
+        self.fft_input.copy_from_slice(&self.samples);
+        fft.process_with_scratch(&mut self.fft_input, &mut self.fft_output, fft_scratch)
+            .unwrap();
+
+        // the spectrum of real input is symmetric; mirror the upper half so that
+        // band magnitudes are computed the same way as for a complex FFT
+        let fft_len = self.magnitudes.len();
+        for (i, c) in self.fft_output.iter().enumerate() {
+            let magnitude = c.norm_sqr().sqrt();
+            self.magnitudes[i] = magnitude;
+            if i > 0 && fft_len - i > i {
+                self.magnitudes[fft_len - i] = magnitude;
+            }
+        }
+    }
+}
+
+/// Separates the spectrum into a harmonic and a percussive part.
+///
+/// Harmonic sounds, like sustained notes, are stable over time while percussive sounds, like
+/// drums, are spread across many frequencies. Median filtering the magnitudes of each FFT bin
+/// over previous frames enhances the harmonic part, median filtering neighbouring FFT bins
+/// of the current frame enhances the percussive part. Both are turned into soft masks which are
+/// applied to the FFT output (Fitzgerald, 2010).
+///
+pub struct Hpss {
+    /// Magnitudes of the first half of the spectrum of previous frames; stores all frames of
+    /// a bin consecutively, frames of each bin are used as ring buffer
+    history: Vec<f32>,
+
+    /// Index of the oldest frame in `history`
+    history_index: usize,
+
+    /// Number of frames processed since the history has been updated
+    frames_since_update: usize,
+
+    /// Scratch buffer for computing medians
+    window: Vec<f32>,
+
+    /// Magnitudes median filtered over time; only changes when the history is updated
+    harmonic_median: Vec<f32>,
+
+    /// FFT output of the percussive part
+    pub percussive_output: Vec<Complex<f32>>,
+
+    /// FFT magnitudes of the percussive part; the upper half mirrors the lower half
+    pub percussive_magnitudes: Vec<f32>,
+
+    /// Frequency band magnitudes of the percussive part
+    pub percussive_bands: Vec<f32>,
+
+    /// Transformation state of the percussive part
+    pub percussive_transformed: TransformedAudio,
+
+    /// FFT output of the harmonic part
+    pub harmonic_output: Vec<Complex<f32>>,
+
+    /// FFT magnitudes of the harmonic part; the upper half mirrors the lower half
+    pub harmonic_magnitudes: Vec<f32>,
+
+    /// Frequency band magnitudes of the harmonic part
+    pub harmonic_bands: Vec<f32>,
+
+    /// Transformation state of the harmonic part
+    pub harmonic_transformed: TransformedAudio,
+}
+
+impl Hpss {
+    /// Allocates all buffers required for the separation.
+    pub fn new(total_bands: usize, fft_len: usize) -> Hpss {
+        let output_len = fft_len / 2 + 1;
+        Hpss {
+            history: vec![0.0; output_len * HPSS_HARMONIC_LEN],
+            history_index: 0,
+            frames_since_update: HPSS_HISTORY_INTERVAL,
+            window: Vec::with_capacity(HPSS_HARMONIC_LEN.max(HPSS_PERCUSSIVE_LEN)),
+            harmonic_median: vec![0.0; output_len],
+            percussive_output: vec![Zero::zero(); output_len],
+            percussive_magnitudes: vec![0.0; fft_len],
+            percussive_bands: vec![0.0; total_bands],
+            percussive_transformed: TransformedAudio::new(total_bands, fft_len),
+            harmonic_output: vec![Zero::zero(); output_len],
+            harmonic_magnitudes: vec![0.0; fft_len],
+            harmonic_bands: vec![0.0; total_bands],
+            harmonic_transformed: TransformedAudio::new(total_bands, fft_len),
+        }
+    }
+
+    /// Separates the FFT output of the current frame into a harmonic and a percussive part.
+    ///
+    /// # Arguments
+    /// * `fft_output`: first half of the FFT output
+    /// * `magnitudes`: FFT magnitudes
+    ///
+    pub fn separate(&mut self, fft_output: &[Complex<f32>], magnitudes: &[f32]) {
+        let output_len = fft_output.len().min(self.harmonic_median.len());
+        let fft_len = self.percussive_magnitudes.len();
+
+        // periodically replace the oldest frame in the history with the current one
+        if self.frames_since_update >= HPSS_HISTORY_INTERVAL {
+            for (bin, frames) in self
+                .history
+                .chunks_exact_mut(HPSS_HARMONIC_LEN)
+                .take(output_len)
+                .enumerate()
+            {
+                frames[self.history_index] = magnitudes[bin];
+                self.window.clear();
+                self.window.extend_from_slice(frames);
+                self.harmonic_median[bin] = Self::median(&mut self.window);
+            }
+            self.history_index = (self.history_index + 1) % HPSS_HARMONIC_LEN;
+            self.frames_since_update = 0;
+        }
+        self.frames_since_update += 1;
+
+        // sliding window over neighbouring bins; kept sorted to look up the median
+        let half_len = HPSS_PERCUSSIVE_LEN / 2;
+        self.window.clear();
+        let (mut lower, mut upper) = (0, 0);
+        for bin in 0..output_len {
+            while upper < (bin + half_len + 1).min(output_len) {
+                let value = magnitudes[upper];
+                let index = self.window.partition_point(|v| *v < value);
+                self.window.insert(index, value);
+                upper += 1;
+            }
+            while lower < bin.saturating_sub(half_len) {
+                let value = magnitudes[lower];
+                let index = self.window.partition_point(|v| *v < value);
+                self.window.remove(index);
+                lower += 1;
+            }
+
+            let percussive_median = self.window[self.window.len() / 2];
+            let percussive = percussive_median * percussive_median;
+            let harmonic = self.harmonic_median[bin] * self.harmonic_median[bin];
+
+            // soft masks; split evenly if there is no energy
+            let percussive_mask = if percussive + harmonic > 0.0 {
+                percussive / (percussive + harmonic)
+            } else {
+                0.5
+            };
+            let harmonic_mask = 1.0 - percussive_mask;
+
+            self.percussive_output[bin] = fft_output[bin] * percussive_mask;
+            self.harmonic_output[bin] = fft_output[bin] * harmonic_mask;
+            self.percussive_magnitudes[bin] = magnitudes[bin] * percussive_mask;
+            self.harmonic_magnitudes[bin] = magnitudes[bin] * harmonic_mask;
+            if bin > 0 && fft_len - bin > bin {
+                self.percussive_magnitudes[fft_len - bin] = self.percussive_magnitudes[bin];
+                self.harmonic_magnitudes[fft_len - bin] = self.harmonic_magnitudes[bin];
+            }
+        }
+    }
+
+    /// Returns the median of the provided values; reorders the values.
+    fn median(values: &mut [f32]) -> f32 {
+        if values.is_empty() {
+            return 0.0;
+        }
+
+        let middle = values.len() / 2;
+        *values
+            .select_nth_unstable_by(middle, |a, b| a.partial_cmp(b).unwrap())
+            .1
+    }
+}
+
+/// Sparse spectral kernels of a constant-Q transform.
+///
+/// Each band is centered at a log-spaced frequency and analysed with a window whose length is
+/// inversely proportional to its center frequency, so that all bands have the same resolution
+/// per octave. Windows are limited to `fft_len` samples which widens the lowest bands.
+/// Band magnitudes are computed from the FFT output by multiplying with the precomputed
+/// spectral kernels (Brown and Puckette, 1992).
+///
+pub struct ConstantQKernel {
+    /// Non-zero FFT bin indices and spectral kernel values for each band
+    pub kernels: Vec<Vec<(usize, Complex<f32>)>>,
+}
+
+impl ConstantQKernel {
+    /// Computes the spectral kernels for all bands.
+    ///
+    /// # Arguments
+    /// * `total_bands`: number of bands
+    /// * `lower_cutoff`: lower frequency of the lowest band in Hz
+    /// * `upper_cutoff`: upper frequency of the highest band in Hz
+    /// * `rate`: sampling rate
+    /// * `fft_len`: number of FFT input samples
+    ///
+    pub fn new(
+        total_bands: usize,
+        lower_cutoff: f32,
+        upper_cutoff: f32,
+        rate: u32,
+        fft_len: usize,
+    ) -> ConstantQKernel {
+        let mut planner = FftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(fft_len);
+
+        // ratio between the frequencies of neighbouring bands
+        let ratio = (upper_cutoff / lower_cutoff).powf(1.0 / total_bands as f32);
+        // ratio of center frequency to bandwidth; the same for all bands
+        let q = 1.0 / (ratio.sqrt() - 1.0 / ratio.sqrt());
+
+        let kernels = (0..total_bands)
+            .map(|n| {
+                let center_frequency = lower_cutoff * ratio.powf(n as f32 + 0.5);
+                // window length at which the -6 dB bandwidth of the Hann window matches the band
+                let window_len = ((2.0 * q * rate as f32 / center_frequency).ceil() as usize)
+                    .max(1)
+                    .min(fft_len);
+
+                // temporal kernel: Hann windowed complex sinusoid aligned to the most recent samples;
+                // normalized so that a sine at the center frequency results in the same magnitude
+                // as in the FFT
+                let window: Vec<f32> = (0..window_len)
+                    .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / window_len as f32).cos())
+                    .collect();
+                let window_sum: f32 = window.iter().sum();
+                let mut kernel = vec![Complex::zero(); fft_len];
+                for (i, w) in window.iter().enumerate() {
+                    let phase = 2.0 * PI * center_frequency * i as f32 / rate as f32;
+                    kernel[fft_len - window_len + i] =
+                        Complex::from_polar(w / window_sum, phase) * (fft_len as f32 / 2.0);
+                }
+
+                // spectral kernel is concentrated around the center frequency; drop everything else
+                fft.process(&mut kernel);
+                let max = kernel.iter().map(|k| k.norm()).fold(0.0, f32::max);
+                kernel
+                    .iter()
+                    .enumerate()
+                    .take(fft_len / 2 + 1)
+                    .filter(|(_, k)| k.norm() > max * CONSTANT_Q_SPARSITY)
+                    .map(|(i, k)| (i, k.conj() / fft_len as f32))
+                    .collect()
+            })
+            .collect();
+
+        ConstantQKernel { kernels }
+    }
+}
+
+/// Transforms the audio samples of a single frame into audio features.
+///
+/// All buffers are allocated once and reused for each frame.
+///
+pub struct FrameTransformer {
+    /// Transformer settings
+    settings: TransformerSettings,
+
+    /// Sampling rate
+    rate: u32,
+
+    /// Real-to-complex FFT
+    fft: Arc<dyn RealToComplex<f32>>,
+
+    /// Scratch buffer used when computing the FFT
+    fft_scratch: Vec<Complex<f32>>,
+
+    /// Index of the lowest FFT bin for each band
+    lower_cutoff_freq: Vec<usize>,
+
+    /// Index of the highest FFT bin for each band
+    upper_cutoff_freq: Vec<usize>,
+
+    /// Spectral kernels if the constant-Q transform is used for computing bands
+    constant_q: Option<ConstantQKernel>,
+
+    /// Left channel
+    pub left: FrameChannel,
+
+    /// Right channel
+    pub right: FrameChannel,
+}
+
+impl FrameTransformer {
+    /// Instantiates a new `FrameTransformer`.
+    ///
+    /// # Arguments
+    /// * `settings`: transformer settings
+    /// * `rate`: sampling rate of the audio samples
+    ///
+    pub fn new(settings: TransformerSettings, rate: u32) -> FrameTransformer {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(settings.fft_len);
+        let fft_scratch = fft.make_scratch_vec();
+
+        // FFT bins that are merged into each frequency band
+        let (lower_cutoff_freq, upper_cutoff_freq) = AudioTransformer::cutoff_frequencies(
+            settings.total_bands,
+            settings.lower_cutoff,
+            settings.upper_cutoff,
+            rate,
+            settings.fft_len,
+        );
+
+        let constant_q = match settings.analysis {
+            Analysis::Fft => None,
+            Analysis::ConstantQ => Some(ConstantQKernel::new(
+                settings.total_bands,
+                settings.lower_cutoff,
+                settings.upper_cutoff,
+                rate,
+                settings.fft_len,
+            )),
+        };
+
+        FrameTransformer {
+            left: FrameChannel::new(settings.total_bands, settings.fft_len, settings.hpss),
+            right: FrameChannel::new(settings.total_bands, settings.fft_len, settings.hpss),
+            settings,
+            rate,
+            fft,
+            fft_scratch,
+            lower_cutoff_freq,
+            upper_cutoff_freq,
+            constant_q,
+        }
+    }
+
+    /// Transforms interleaved 16 bit stereo samples into audio features for the left and right channel.
+    ///
+    /// The right channel sample comes first in each sample pair. At most `fft_len` sample pairs are used.
+    ///
+    pub fn process(&mut self, samples: &[i16]) -> (AudioFeatures, AudioFeatures) {
+        // factor used to normalize input samples
+        let norm = 1.0 / (i16::max_value() as f32);
+
+        // normalize left and right channel samples
+        for (i, sample) in samples
+            .chunks_exact(2)
+            .take(self.settings.fft_len)
+            .enumerate()
+        {
+            self.left.samples[i] = (sample[1] as f32) * norm;
+            self.right.samples[i] = (sample[0] as f32) * norm;
+        }
+
+        let mut left_frame = self.transform_channel(true);
+        let mut right_frame = self.transform_channel(false);
+
+        // stereo field features are shared by both channels
+        let stereo = AudioTransformer::stereo_features(
+            &self.left.samples,
+            &self.right.samples,
+            &self.left.fft_output,
+            &self.right.fft_output,
+            self.settings.fft_len,
+            &self.lower_cutoff_freq,
+            &self.upper_cutoff_freq,
+        );
+        left_frame.stereo = stereo.clone();
+        right_frame.stereo = stereo;
+
+        (left_frame, right_frame)
+    }
+
+    /// Transforms the current samples of the left or right channel into audio features.
+    fn transform_channel(&mut self, left: bool) -> AudioFeatures {
+        let channel = if left {
+            &mut self.left
+        } else {
+            &mut self.right
+        };
+
+        channel.fft(&*self.fft, &mut self.fft_scratch);
+
+        // determine frequency magnitudes
+        let constant_q = &self.constant_q;
+        let lower_cutoff_freq = &self.lower_cutoff_freq;
+        let upper_cutoff_freq = &self.upper_cutoff_freq;
+        let settings = &self.settings;
+        let frequency_magnitudes = |magnitudes: &[f32],
+                                    fft_output: &[Complex<f32>],
+                                    bands: &mut Vec<f32>,
+                                    transformed: &mut TransformedAudio| {
+            match constant_q {
+                Some(kernel) => AudioTransformer::constant_q_frequency_magnitudes(
+                    fft_output,
+                    bands,
+                    transformed,
+                    kernel,
+                    settings.monstercat,
+                    settings.decay,
+                ),
+                None => AudioTransformer::frequency_magnitudes(
+                    magnitudes,
+                    bands,
+                    transformed,
+                    lower_cutoff_freq,
+                    upper_cutoff_freq,
+                    settings.monstercat,
+                    settings.decay,
+                ),
+            }
+        };
+
+        frequency_magnitudes(
+            &channel.magnitudes,
+            &channel.fft_output,
+            &mut channel.bands,
+            &mut channel.transformed,
+        );
+
+        // separate harmonic and percussive parts and determine their frequency magnitudes
+        if let Some(hpss) = &mut channel.hpss {
+            hpss.separate(&channel.fft_output, &channel.magnitudes);
+            frequency_magnitudes(
+                &hpss.percussive_magnitudes,
+                &hpss.percussive_output,
+                &mut hpss.percussive_bands,
+                &mut hpss.percussive_transformed,
+            );
+            frequency_magnitudes(
+                &hpss.harmonic_magnitudes,
+                &hpss.harmonic_output,
+                &mut hpss.harmonic_bands,
+                &mut hpss.harmonic_transformed,
+            );
+        }
+
+        // extract audio features
+        AudioTransformer::normalize_spectrum(
+            &channel.magnitudes,
+            &mut channel.spectrum,
+            self.settings.fft_len,
+        );
+        let mut frame = AudioTransformer::features(
+            &channel.samples,
+            &channel.spectrum,
+            self.settings.fft_len,
+            channel.bands.clone(),
+            &mut channel.transformed,
+            self.rate,
+        );
+        if let Some(hpss) = &channel.hpss {
+            frame.percussive_bands = hpss.percussive_bands.clone();
+            frame.harmonic_bands = hpss.harmonic_bands.clone();
+        }
+        frame
+    }
+}
+
 /// Transforms an audio stream to frequency bands.
 pub struct AudioTransformer {
     /// Handle on the thread running the audio transformation
@@ -52,11 +651,11 @@ pub struct AudioTransformer {
     /// Whether the audio transformer is still running
     killed: Arc<AtomicBool>,
 
-    /// Frequency bands for left channel
-    pub left_bands: Arc<Mutex<Vec<f32>>>,
+    /// Audio features for left channel
+    pub left_features: Arc<Mutex<AudioFeatures>>,
 
-    /// Frequency bands for right channel
-    pub right_bands: Arc<Mutex<Vec<f32>>>,
+    /// Audio features for right channel
+    pub right_features: Arc<Mutex<AudioFeatures>>,
 }
 
 impl AudioTransformer {
@@ -70,8 +669,8 @@ impl AudioTransformer {
             handle: None,
             settings: settings.clone(),
             killed: Arc::new(AtomicBool::from(false)),
-            left_bands: Arc::new(Mutex::new(vec![0.0; settings.total_bands])),
-            right_bands: Arc::new(Mutex::new(vec![0.0; settings.total_bands])),
+            left_features: Arc::new(Mutex::new(AudioFeatures::new(settings.total_bands))),
+            right_features: Arc::new(Mutex::new(AudioFeatures::new(settings.total_bands))),
         };
 
         transformer
@@ -82,8 +681,8 @@ impl AudioTransformer {
         // make fields available in thread
         let settings = self.settings.clone();
         let killed = self.killed.clone();
-        let right_bands = self.right_bands.clone();
-        let left_bands = self.left_bands.clone();
+        let right_features = self.right_features.clone();
+        let left_features = self.left_features.clone();
 
         // transform audio in separate thread
         self.handle = Some(thread::spawn(move || {
@@ -94,27 +693,16 @@ impl AudioTransformer {
                 settings.buffer_size,
             );
 
-            // instance to compute forward FFTs
-            let mut planner = FftPlanner::new();
-            let fft = planner.plan_fft_forward(settings.fft_len);
-
             // audio buffer
             let buffer = audio.buffer.unwrap();
 
-            // audio inputs for FFT for left and right channel
-            let mut left: Vec<Complex<f32>> = vec![Zero::zero(); settings.fft_len];
-            let mut right: Vec<Complex<f32>> = vec![Zero::zero(); settings.fft_len];
-
-            // transformed audio for each channel
-            let mut left_transformed =
-                TransformedAudio::new(settings.total_bands, settings.fft_len);
-            let mut right_transformed =
-                TransformedAudio::new(settings.total_bands, settings.fft_len);
-
             // get bytes per audio frame
             let byte_rate = *(*audio.rate).lock().unwrap();
             let target_bytes_per_frame = (byte_rate / 60) as usize;
 
+            // transforms audio samples into audio features; reuses buffers for each frame
+            let mut frame_transformer = FrameTransformer::new(settings.clone(), byte_rate);
+
             // number of bytes required as FFT input
             let fft_byte_len: usize = settings.fft_len * 4;
 
@@ -125,9 +713,6 @@ impl AudioTransformer {
             // input buffer for FFT
             let mut fft_input_buffer: Vec<i16> = vec![0; settings.fft_len * 2];
 
-            // factor used to normalize input samples
-            let norm = 1.0 / (i16::max_value() as f32);
-
             while !killed.load(Ordering::Relaxed) {
                 // check if there is enough data in the audio buffer
                 let available = buffer.available();
@@ -158,69 +743,12 @@ impl AudioTransformer {
                     continue;
                 }
 
-                {
-                    // if enough data is in the stream buffer, copy data to FFT input buffer
-                    Le::read_i16_into(
-                        &stream_buf.clone().split_to(fft_byte_len),
-                        &mut fft_input_buffer,
-                    );
-
-                    // extract left and right channel data from FFT input buffer
-                    let mut left_channel_input = left.iter_mut();
-                    let mut right_channel_input = right.iter_mut();
-
-                    // normalize left and right channel samples
-                    for sample in fft_input_buffer.chunks_exact(2) {
-                        let normed = (sample[1] as f32) * norm;
-                        *left_channel_input.next().unwrap() = Complex::new(normed, 0.0);
-                        let normed = (sample[0] as f32) * norm;
-                        *right_channel_input.next().unwrap() = Complex::new(normed, 0.0);
-                    }
-                }
-
-                // FFT for left channel
-                fft.process(&mut left);
-
-                // convert complex values to real values
-                let left_real: Vec<f32> = left
-                    .iter()
-                    .map(|c| (c.im.powf(2.0) + c.re.powf(2.0)).sqrt())
-                    .collect();
-
-                // determine frequency magnitudes for left channel
-                *(left_bands.lock().unwrap()) = Self::frequency_magnitudes(
-                    left_real,
-                    &mut left_transformed,
-                    settings.lower_cutoff,
-                    settings.upper_cutoff,
-                    settings.total_bands,
-                    byte_rate,
-                    settings.fft_len,
-                    settings.monstercat,
-                    settings.decay,
-                );
-
-                // FFT for right channel
-                fft.process(&mut right);
-
-                // convert complex values to real values
-                let right_real: Vec<f32> = right
-                    .iter()
-                    .map(|c| (c.im.powf(2.0) + c.re.powf(2.0)).sqrt())
-                    .collect();
+                // if enough data is in the stream buffer, copy data to FFT input buffer
+                Le::read_i16_into(&stream_buf[..fft_byte_len], &mut fft_input_buffer);
 
-                // determine frequency magnitudes for right channel
-                *(right_bands.lock().unwrap()) = Self::frequency_magnitudes(
-                    right_real,
-                    &mut right_transformed,
-                    settings.lower_cutoff,
-                    settings.upper_cutoff,
-                    settings.total_bands,
-                    byte_rate,
-                    settings.fft_len,
-                    settings.monstercat,
-                    settings.decay,
-                );
+                let (left_frame, right_frame) = frame_transformer.process(&fft_input_buffer);
+                *(left_features.lock().unwrap()) = left_frame;
+                *(right_features.lock().unwrap()) = right_frame;
             }
         }));
     }
@@ -232,8 +760,8 @@ impl AudioTransformer {
     pub fn update_settings(&mut self, settings: TransformerSettings) {
         self.killed.swap(true, Ordering::Relaxed);
         self.settings = settings.clone();
-        *self.left_bands.lock().unwrap() = vec![0.0; settings.total_bands];
-        *self.right_bands.lock().unwrap() = vec![0.0; settings.total_bands];
+        *self.left_features.lock().unwrap() = AudioFeatures::new(settings.total_bands);
+        *self.right_features.lock().unwrap() = AudioFeatures::new(settings.total_bands);
         self.handle = None;
         self.killed.swap(false, Ordering::Relaxed);
         self.start();
@@ -243,29 +771,485 @@ impl AudioTransformer {
     ///
     /// The sound spectrum is re-binned into log scale and a fewer number of bins.
     /// This means that buckets of the FFT will be merged together.
+    /// Results are written into the provided `bands` to avoid allocations.
     ///
     pub fn frequency_magnitudes(
-        input: Vec<f32>,
+        input: &[f32],
+        bands: &mut Vec<f32>,
         transformed_audio: &mut TransformedAudio,
-        lower_cutoff: f32,
-        upper_cutoff: f32,
-        total_bands: usize,
-        rate: u32,
-        fft_len: usize,
+        lower_cutoff_freq: &Vec<usize>,
+        upper_cutoff_freq: &Vec<usize>,
         monstercat: f32,
         decay: f32,
-    ) -> Vec<f32> {
-        // frequency magnitudes for each band
-        let mut bands: Vec<f32> = vec![0.0; total_bands];
+    ) {
+        Self::magnitudes(bands, input, lower_cutoff_freq, upper_cutoff_freq);
+        Self::smooth(bands, monstercat);
+        Self::scale(bands, transformed_audio);
+        Self::falloff(bands, transformed_audio, decay);
+    }
+
+    /// Compute frequency bands and magnitudes using a constant-Q transform.
+    ///
+    /// Same as `frequency_magnitudes`, but band magnitudes are derived from the FFT output
+    /// using the constant-Q spectral kernels.
+    ///
+    pub fn constant_q_frequency_magnitudes(
+        fft_output: &[Complex<f32>],
+        bands: &mut Vec<f32>,
+        transformed_audio: &mut TransformedAudio,
+        kernel: &ConstantQKernel,
+        monstercat: f32,
+        decay: f32,
+    ) {
+        Self::constant_q_magnitudes(bands, fft_output, kernel);
+        Self::smooth(bands, monstercat);
+        Self::scale(bands, transformed_audio);
+        Self::falloff(bands, transformed_audio, decay);
+    }
+
+    /// Extracts audio features from the audio samples and FFT magnitudes of a frame.
+    ///
+    /// # Arguments
+    /// * `samples`: normalized audio samples used as FFT input
+    /// * `spectrum`: normalized FFT magnitudes; see `normalize_spectrum`
+    /// * `fft_len`: number of FFT output buckets
+    /// * `bands`: frequency band magnitudes computed from the FFT magnitudes
+    /// * `transformed_audio`: transformation state of the audio channel
+    /// * `rate`: sampling rate
+    ///
+    pub fn features(
+        samples: &[f32],
+        spectrum: &[f32],
+        fft_len: usize,
+        bands: Vec<f32>,
+        transformed_audio: &mut TransformedAudio,
+        rate: u32,
+    ) -> AudioFeatures {
+        let drum_flux = Self::drum_flux(spectrum, rate, fft_len, transformed_audio);
+        let spectral_flux = Self::spectral_flux(spectrum, transformed_audio);
+        let onset = Self::onset(spectral_flux, transformed_audio);
+        let drums = Self::classify_drums(spectrum, drum_flux, onset, transformed_audio);
+        let chroma = Self::chroma(spectrum, rate, fft_len);
+        let key = Self::estimate_key(&chroma, transformed_audio);
+
+        AudioFeatures {
+            bands,
+            percussive_bands: Vec::new(),
+            harmonic_bands: Vec::new(),
+            rms: Self::rms(samples),
+            peak: Self::peak(samples),
+            spectral_centroid: Self::spectral_centroid(spectrum, rate, fft_len),
+            spectral_flux,
+            onset,
+            drums,
+            dominant_frequency: Self::dominant_frequency(spectrum, rate, fft_len),
+            timestamp: Utc::now(),
+            stereo: StereoFeatures::new(0),
+            chroma,
+            key,
+            loudness: Self::loudness(spectrum, rate, fft_len, transformed_audio),
+        }
+    }
+
+    /// Normalizes FFT magnitudes.
+    ///
+    /// Only the first half of the FFT output contains distinct frequencies. Magnitudes are
+    /// normalized so that a full scale sine results in a magnitude of 1.
+    ///
+    pub fn normalize_spectrum(magnitudes: &[f32], spectrum: &mut [f32], fft_len: usize) {
+        let norm = 2.0 / fft_len as f32;
+        for (normalized, magnitude) in spectrum.iter_mut().zip(magnitudes.iter()) {
+            *normalized = magnitude * norm;
+        }
+    }
+
+    /// Computes the A-weighted short-term loudness in LUFS.
+    ///
+    /// The A-weighted mean square of the frame is averaged over the previous frames to
+    /// approximate momentary loudness. A full scale 1 kHz sine results in about -3 LUFS.
+    ///
+    pub fn loudness(
+        spectrum: &[f32],
+        rate: u32,
+        fft_len: usize,
+        transformed_audio: &mut TransformedAudio,
+    ) -> f32 {
+        if transformed_audio.loudness_weights.len() != spectrum.len() {
+            transformed_audio.loudness_weights = (0..spectrum.len())
+                .map(|bin| Self::a_weighting(Self::bin_frequency(bin, rate, fft_len)))
+                .collect();
+        }
+
+        // normalized magnitudes are amplitudes; mean square of a sine is half its squared amplitude
+        let mean_square: f32 = spectrum
+            .iter()
+            .zip(transformed_audio.loudness_weights.iter())
+            .map(|(magnitude, weight)| (magnitude * weight).powf(2.0) / 2.0)
+            .sum();
+
+        // keep track of recent mean square values; most recent value is stored at index=0
+        if transformed_audio.loudness_history.is_empty() {
+            return MIN_LOUDNESS;
+        }
+        transformed_audio.loudness_history.rotate_right(1);
+        transformed_audio.loudness_history[0] = mean_square;
+
+        let history_len = transformed_audio.loudness_history.len() as f32;
+        let mean: f32 = transformed_audio.loudness_history.iter().sum::<f32>() / history_len;
+
+        if mean > 0.0 {
+            (10.0 * mean.log10()).max(MIN_LOUDNESS)
+        } else {
+            MIN_LOUDNESS
+        }
+    }
+
+    /// Returns the A-weighting gain for the provided frequency in Hz; 1 at 1 kHz.
+    pub fn a_weighting(frequency: f32) -> f32 {
+        let f2 = frequency.powf(2.0);
+        let response = 12194.0_f32.powf(2.0) * f2.powf(2.0)
+            / ((f2 + 20.6_f32.powf(2.0))
+                * ((f2 + 107.7_f32.powf(2.0)) * (f2 + 737.9_f32.powf(2.0))).sqrt()
+                * (f2 + 12194.0_f32.powf(2.0)));
+
+        // normalize to 0 dB at 1 kHz
+        response * 10.0_f32.powf(2.0 / 20.0)
+    }
+
+    /// Folds the spectrum into a chroma vector of 12 pitch classes.
+    ///
+    /// The energy of each FFT bin is assigned to the pitch class closest to its frequency.
+    /// Pitch classes start at C. The chroma vector is normalized to a maximum of 1.
+    ///
+    pub fn chroma(spectrum: &[f32], rate: u32, fft_len: usize) -> Vec<f32> {
+        let mut chroma = vec![0.0; 12];
+        for (i, magnitude) in spectrum.iter().enumerate() {
+            let frequency = Self::bin_frequency(i, rate, fft_len);
+            if frequency < CHROMA_LOWER_CUTOFF || frequency > CHROMA_UPPER_CUTOFF {
+                continue;
+            }
+
+            // MIDI note number; A4 = 440 Hz = 69
+            let note = (12.0 * (frequency / 440.0).log(2.0) + 69.0).round() as usize;
+            chroma[note % 12] += magnitude * magnitude;
+        }
+
+        let max = chroma.iter().cloned().fold(0.0, f32::max);
+        if max > 0.0 {
+            for c in chroma.iter_mut() {
+                *c /= max;
+            }
+        }
+        chroma
+    }
+
+    /// Estimates the musical key from the chroma vector of the current frame.
+    ///
+    /// The chroma vector is smoothed over time and correlated with the
+    /// Krumhansl-Schmuckler profiles of all 24 major and minor keys.
+    ///
+    pub fn estimate_key(chroma: &[f32], transformed_audio: &mut TransformedAudio) -> Key {
+        for (smoothed, c) in transformed_audio.chroma.iter_mut().zip(chroma.iter()) {
+            *smoothed = *smoothed * CHROMA_SMOOTHING + c * (1.0 - CHROMA_SMOOTHING);
+        }
+
+        let mut key = Key::default();
+        for root in 0..12 {
+            for (minor, profile) in [(false, &MAJOR_PROFILE), (true, &MINOR_PROFILE)].iter() {
+                // rotate profile so that it starts at the key root
+                let mut rotated = [0.0; 12];
+                for (pc, value) in rotated.iter_mut().enumerate() {
+                    *value = profile[(pc + 12 - root) % 12];
+                }
+                let confidence = Self::correlation(&transformed_audio.chroma, &rotated);
+                if confidence > key.confidence {
+                    key = Key {
+                        root,
+                        minor: *minor,
+                        confidence,
+                    };
+                }
+            }
+        }
+
+        key
+    }
+
+    /// Computes the Pearson correlation coefficient of two vectors.
+    pub fn correlation(a: &[f32], b: &[f32]) -> f32 {
+        let len = a.len().min(b.len()) as f32;
+        let mean_a: f32 = a.iter().sum::<f32>() / len;
+        let mean_b: f32 = b.iter().sum::<f32>() / len;
+
+        let mut covariance: f32 = 0.0;
+        let mut variance_a: f32 = 0.0;
+        let mut variance_b: f32 = 0.0;
+        for (x, y) in a.iter().zip(b.iter()) {
+            covariance += (x - mean_a) * (y - mean_b);
+            variance_a += (x - mean_a).powf(2.0);
+            variance_b += (y - mean_b).powf(2.0);
+        }
+
+        if variance_a > 0.0 && variance_b > 0.0 {
+            covariance / (variance_a * variance_b).sqrt()
+        } else {
+            0.0
+        }
+    }
+
+    /// Computes stereo field features from the left and right channel.
+    ///
+    /// # Arguments
+    /// * `left_samples`: normalized audio samples of left channel
+    /// * `right_samples`: normalized audio samples of right channel
+    /// * `left_fft`: FFT output of left channel
+    /// * `right_fft`: FFT output of right channel
+    /// * `fft_len`: number of FFT output buckets
+    /// * `lower_cutoff_freq`: index of lowest FFT bin for each band
+    /// * `upper_cutoff_freq`: index of highest FFT bin for each band
+    ///
+    pub fn stereo_features(
+        left_samples: &[f32],
+        right_samples: &[f32],
+        left_fft: &[Complex<f32>],
+        right_fft: &[Complex<f32>],
+        fft_len: usize,
+        lower_cutoff_freq: &Vec<usize>,
+        upper_cutoff_freq: &Vec<usize>,
+    ) -> StereoFeatures {
+        // balance is based on loudness difference between channels
+        let left_rms = Self::rms(left_samples);
+        let right_rms = Self::rms(right_samples);
+        let balance = if left_rms + right_rms > 0.0 {
+            (right_rms - left_rms) / (left_rms + right_rms)
+        } else {
+            0.0
+        };
 
-        let (lower_cutoff_freq, upper_cutoff_freq) =
-            Self::cutoff_frequencies(total_bands, lower_cutoff, upper_cutoff, rate, fft_len);
-        Self::magnitudes(&mut bands, input, &lower_cutoff_freq, &upper_cutoff_freq);
-        Self::smooth(&mut bands, monstercat);
-        Self::scale(&mut bands, transformed_audio);
-        Self::falloff(&mut bands, transformed_audio, decay);
+        // normalized cross-correlation at zero lag
+        let mut cross_sum: f32 = 0.0;
+        let mut left_sum: f32 = 0.0;
+        let mut right_sum: f32 = 0.0;
+        for (l, r) in left_samples.iter().zip(right_samples.iter()) {
+            cross_sum += l * r;
+            left_sum += l * l;
+            right_sum += r * r;
+        }
+        let correlation = if left_sum > 0.0 && right_sum > 0.0 {
+            cross_sum / (left_sum * right_sum).sqrt()
+        } else {
+            0.0
+        };
+
+        // the FFT is linear, so mid and side spectra can be derived from the channel spectra;
+        // only the first half of the FFT output contains distinct frequencies
+        let distinct_bins = (fft_len / 2).min(left_fft.len()).min(right_fft.len());
+        let norm = 1.0 / fft_len as f32;
+        let total_bands = lower_cutoff_freq.len();
+        let mut mid_bands = vec![0.0; total_bands];
+        let mut side_bands = vec![0.0; total_bands];
+        for n in 0..total_bands {
+            let mut cutoff_freq = lower_cutoff_freq[n];
+            while cutoff_freq <= upper_cutoff_freq[n] && cutoff_freq < distinct_bins {
+                let mid = (left_fft[cutoff_freq] + right_fft[cutoff_freq]) * norm;
+                let side = (left_fft[cutoff_freq] - right_fft[cutoff_freq]) * norm;
+                mid_bands[n] += mid.norm_sqr();
+                side_bands[n] += side.norm_sqr();
+                cutoff_freq += 1;
+            }
+        }
+
+        StereoFeatures {
+            balance,
+            mid_bands,
+            side_bands,
+            correlation,
+        }
+    }
+
+    /// Computes the root mean square of audio samples.
+    pub fn rms(samples: &[f32]) -> f32 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f32 = samples.iter().map(|s| s * s).sum();
+        (sum / samples.len() as f32).sqrt()
+    }
+
+    /// Determines the maximum absolute value of audio samples.
+    pub fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |peak: f32, s| peak.max(s.abs()))
+    }
+
+    /// Computes the spectral centroid in Hz.
+    ///
+    /// The spectral centroid is the magnitude-weighted average frequency of the spectrum
+    /// and indicates how "bright" a sound is.
+    ///
+    pub fn spectral_centroid(spectrum: &[f32], rate: u32, fft_len: usize) -> f32 {
+        let mut weighted_sum: f32 = 0.0;
+        let mut sum: f32 = 0.0;
+        for (i, magnitude) in spectrum.iter().enumerate() {
+            weighted_sum += Self::bin_frequency(i, rate, fft_len) * magnitude;
+            sum += magnitude;
+        }
+
+        if sum > 0.0 {
+            weighted_sum / sum
+        } else {
+            0.0
+        }
+    }
+
+    /// Determines the frequency in Hz of the FFT bin with the largest magnitude.
+    pub fn dominant_frequency(spectrum: &[f32], rate: u32, fft_len: usize) -> f32 {
+        let mut dominant_bin = 0;
+        let mut max_magnitude: f32 = 0.0;
+        for (i, magnitude) in spectrum.iter().enumerate() {
+            if *magnitude > max_magnitude {
+                max_magnitude = *magnitude;
+                dominant_bin = i;
+            }
+        }
+
+        Self::bin_frequency(dominant_bin, rate, fft_len)
+    }
+
+    /// Returns the center frequency in Hz of an FFT bin.
+    pub fn bin_frequency(bin: usize, rate: u32, fft_len: usize) -> f32 {
+        bin as f32 * rate as f32 / fft_len as f32
+    }
+
+    /// Computes the spectral flux compared to the spectrum of the previous frame.
+    ///
+    /// Only increasing magnitudes are taken into account, since onsets result in
+    /// a sudden increase of energy. The provided spectrum is stored for the next frame.
+    ///
+    pub fn spectral_flux(spectrum: &[f32], transformed_audio: &mut TransformedAudio) -> f32 {
+        let mut flux: f32 = 0.0;
+        for (magnitude, prev_magnitude) in spectrum.iter().zip(transformed_audio.spectrum.iter()) {
+            flux += (magnitude - prev_magnitude).max(0.0);
+        }
+
+        transformed_audio.spectrum.clear();
+        transformed_audio.spectrum.extend_from_slice(spectrum);
+        flux
+    }
+
+    /// Computes the energy increase of low, mid and high frequencies compared to the previous frame.
+    ///
+    /// Needs to be called before `spectral_flux` which replaces the previous spectrum.
+    ///
+    pub fn drum_flux(
+        spectrum: &[f32],
+        rate: u32,
+        fft_len: usize,
+        transformed_audio: &TransformedAudio,
+    ) -> [f32; 3] {
+        let mut flux = [0.0; 3];
+        for (bin, (magnitude, prev_magnitude)) in spectrum
+            .iter()
+            .zip(transformed_audio.spectrum.iter())
+            .enumerate()
+        {
+            let frequency = Self::bin_frequency(bin, rate, fft_len);
+            let region = if frequency < DRUM_KICK_CUTOFF {
+                0
+            } else if frequency < DRUM_HIHAT_CUTOFF {
+                1
+            } else {
+                2
+            };
+            flux[region] += (magnitude * magnitude - prev_magnitude * prev_magnitude).max(0.0);
+        }
+        flux
+    }
+
+    /// Classifies drum hits based on the energy increase of low, mid and high frequencies.
+    ///
+    /// The energy increase is accumulated for a few frames after an onset. Kicks have a
+    /// large share of low frequencies, snares mostly increase mid frequencies and hi-hats
+    /// increase high frequencies more than mid frequencies. More than one drum can be hit
+    /// at the same time.
+    ///
+    pub fn classify_drums(
+        spectrum: &[f32],
+        flux: [f32; 3],
+        onset: bool,
+        transformed_audio: &mut TransformedAudio,
+    ) -> Vec<DrumEvent> {
+        if onset {
+            transformed_audio.drum_flux = [0.0; 3];
+            transformed_audio.drum_frames = DRUM_CLASSIFY_FRAMES;
+        }
 
-        return bands;
+        if transformed_audio.drum_frames == 0 {
+            return Vec::new();
+        }
+
+        for (accumulated, f) in transformed_audio.drum_flux.iter_mut().zip(flux.iter()) {
+            *accumulated += f;
+        }
+        transformed_audio.drum_frames -= 1;
+        if transformed_audio.drum_frames > 0 {
+            return Vec::new();
+        }
+
+        let total: f32 = transformed_audio.drum_flux.iter().sum();
+        let energy: f32 = spectrum.iter().map(|m| m * m).sum();
+        if total <= 0.0 || total < energy * DRUM_MIN_INCREASE {
+            return Vec::new();
+        }
+        let [low, mid, high] = transformed_audio.drum_flux;
+
+        let mut drums = Vec::new();
+        if low / total >= DRUM_KICK_SHARE {
+            drums.push(DrumEvent::Kick);
+        }
+        if mid >= low && mid >= high {
+            drums.push(DrumEvent::Snare);
+        }
+        if high > mid && high / total >= DRUM_HIHAT_SHARE {
+            drums.push(DrumEvent::HiHat);
+        }
+        drums
+    }
+
+    /// Detects whether the spectral flux indicates an onset.
+    ///
+    /// An onset is detected when the spectral flux exceeds an adaptive threshold
+    /// based on the average and standard deviation of previous spectral flux values.
+    ///
+    pub fn onset(flux: f32, transformed_audio: &mut TransformedAudio) -> bool {
+        let history_len = transformed_audio.flux_history.len();
+        if history_len == 0 {
+            return false;
+        }
+
+        let mean: f32 = transformed_audio.flux_history.iter().sum::<f32>() / history_len as f32;
+        let squared_mean: f32 = transformed_audio
+            .flux_history
+            .iter()
+            .map(|f| f * f)
+            .sum::<f32>()
+            / history_len as f32;
+        let std_dev = (squared_mean - mean.powf(2.0)).max(0.0).sqrt();
+        let threshold = (mean + ONSET_SENSITIVITY * std_dev).max(ONSET_MIN_FLUX);
+
+        let onset = flux > threshold && transformed_audio.frames_since_onset >= ONSET_COOLDOWN;
+
+        // keep track of recent flux values; most recent value is stored at index=0
+        transformed_audio.flux_history.rotate_right(1);
+        transformed_audio.flux_history[0] = flux;
+
+        if onset {
+            transformed_audio.frames_since_onset = 0;
+        } else {
+            transformed_audio.frames_since_onset += 1;
+        }
+
+        onset
     }
 
     /// Compute lower and upper cutoff frequency indices.
@@ -317,7 +1301,7 @@ impl AudioTransformer {
     /// Computes frequency magnitudes for each band.
     pub fn magnitudes(
         bands: &mut Vec<f32>,
-        input: Vec<f32>,
+        input: &[f32],
         lower_cutoff_freq: &Vec<usize>,
         upper_cutoff_freq: &Vec<usize>,
     ) {
@@ -334,14 +1318,40 @@ impl AudioTransformer {
             }
 
             // compute frequency magnitude average
-            bands[n] =
-                frequency_magnitude / ((upper_cutoff_freq[n] - lower_cutoff_freq[n] + 1) as f32);
-            // different weighting of frequencies; higher freqencies are more prominent
-            bands[n] *= (2.0 + (n as f32)).log(2.0) * (100.0 / (total_bands as f32));
-            bands[n] = bands[n].sqrt();
+            bands[n] = Self::weight(
+                frequency_magnitude / ((upper_cutoff_freq[n] - lower_cutoff_freq[n] + 1) as f32),
+                n,
+                total_bands,
+            );
+        }
+    }
+
+    /// Computes frequency magnitudes for each band from the FFT output using constant-Q spectral kernels.
+    pub fn constant_q_magnitudes(
+        bands: &mut Vec<f32>,
+        fft_output: &[Complex<f32>],
+        kernel: &ConstantQKernel,
+    ) {
+        let total_bands = bands.len();
+        for (n, band_kernel) in kernel.kernels.iter().enumerate().take(total_bands) {
+            let mut frequency_magnitude: Complex<f32> = Complex::zero();
+            for (bin, k) in band_kernel {
+                if let Some(x) = fft_output.get(*bin) {
+                    frequency_magnitude += x * k;
+                }
+            }
+
+            // the kernel only covers positive frequencies; account for the mirrored half
+            bands[n] = Self::weight(frequency_magnitude.norm() * 2.0, n, total_bands);
         }
     }
 
+    /// Applies weighting to the magnitude of a band.
+    fn weight(magnitude: f32, band: usize, total_bands: usize) -> f32 {
+        // different weighting of frequencies; higher freqencies are more prominent
+        (magnitude * (2.0 + (band as f32)).log(2.0) * (100.0 / (total_bands as f32))).sqrt()
+    }
+
     /// Applies monstercat filter to smooth frequency magnitudes.
     pub fn smooth(bands: &mut Vec<f32>, monstercat: f32) {
         let total_bands = bands.len();
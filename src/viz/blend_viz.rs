@@ -1,10 +1,10 @@
-use chrono::prelude::*;
-use chrono::Duration;
 use rand::{distributions::Uniform, Rng};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::features::AudioFeatures;
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
 
@@ -23,7 +23,7 @@ pub struct BlendVizConfig {
     /// Determines the range when computing initial color offsets.
     pub offset_weight: i64,
 
-    /// Factor determining how much the current pixel color changes to the target color in each iteration.
+    /// Factor determining how much the current pixel color changes to the target color per frame at `REFERENCE_FPS`.
     pub blend_factor: u8,
 }
 
@@ -75,14 +75,17 @@ pub struct BlendViz {
     /// Total number of pixels.
     total_pixels: usize,
 
-    /// Elapsed time for each pixel until it blends to different color.
-    elapsed_time: Vec<DateTime<Utc>>,
+    /// Elapsed time in seconds for each pixel until it blends to different color.
+    elapsed: Vec<f32>,
 
     /// Color of each pixel.
     pixels: Vec<PixelViz>,
 
     /// Colors pixels blend to.
     target_colors: Vec<Color>,
+
+    /// Fraction of a color step that has not been applied when blending.
+    blend_remainder: f32,
 }
 
 #[typetag::serde]
@@ -95,20 +98,31 @@ impl Viz for BlendViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, input: &Vec<f32>, colors: &Vec<Color>) -> Vec<PixelViz> {
-        let total_magnitude: f32 = input.iter().sum();
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let bands = &input.bands;
+        let total_magnitude: f32 = bands.iter().sum();
         let mut rng = rand::thread_rng();
 
         // magnitude of each frequency band
-        let freq_amounts: Vec<i64> = input
+        let freq_amounts: Vec<i64> = bands
             .iter()
             .map(|m| (100.0 * m / (total_magnitude + 0.001)) as i64)
             .collect::<Vec<i64>>();
-        let now = Utc::now();
+
+        // color steps applied when blending; fractions are carried over to the next update
+        self.blend_remainder += self.config.blend_factor as f32 * time.frames();
+        let blend_step = self.blend_remainder.floor().min(u8::MAX as f32);
+        self.blend_remainder -= blend_step;
+        let blend_step = blend_step as u8;
 
         // update pixel colors
         for pixel_index in 0..self.total_pixels {
-            let elapsed = (now - self.elapsed_time[pixel_index]).num_seconds();
+            self.elapsed[pixel_index] += time.delta;
 
             // determine current pixel color based on multipliers
             let current_color = Color {
@@ -122,7 +136,7 @@ impl Viz for BlendViz {
 
             let mut target_color = self.target_colors[pixel_index];
 
-            if elapsed > (self.config.blend_speed as i64) {
+            if self.elapsed[pixel_index] > self.config.blend_speed as f32 {
                 // if time is elapsed, blend to target color
                 let mut r = rng.gen_range(0..100) as i64;
                 let mut color_index = 0;
@@ -140,11 +154,11 @@ impl Viz for BlendViz {
                 // compute target color
                 target_color = colors[color_index % colors.len()];
                 target_color.r =
-                    (target_color.r as f32 * (input[color_index] as f32 / 100.0)) as u8;
+                    (target_color.r as f32 * (bands[color_index] as f32 / 100.0)) as u8;
                 target_color.g =
-                    (target_color.g as f32 * (input[color_index] as f32 / 100.0)) as u8;
+                    (target_color.g as f32 * (bands[color_index] as f32 / 100.0)) as u8;
                 target_color.b =
-                    (target_color.b as f32 * (input[color_index] as f32 / 100.0)) as u8;
+                    (target_color.b as f32 * (bands[color_index] as f32 / 100.0)) as u8;
                 target_color.r = if target_color.r == 0 {
                     1
                 } else {
@@ -163,13 +177,13 @@ impl Viz for BlendViz {
 
                 // update state
                 self.target_colors[pixel_index] = target_color;
-                self.elapsed_time[pixel_index] = now;
+                self.elapsed[pixel_index] = 0.0;
                 self.pixels[pixel_index].color_index = color_index;
             }
 
             // compute blending
             let actual_color = colors[self.pixels[pixel_index].color_index % colors.len()];
-            let blend_color = Self::blend(&current_color, &target_color, self.config.blend_factor);
+            let blend_color = Self::blend(&current_color, &target_color, blend_step);
 
             // update pixels
             self.pixels[pixel_index].red_mul = (blend_color.r as f32) / (actual_color.r as f32);
@@ -183,14 +197,8 @@ impl Viz for BlendViz {
     fn set_total_pixels(&mut self, pixels: usize) {
         self.total_pixels = pixels;
         self.pixels = vec![PixelViz::default(); pixels];
-        self.elapsed_time = vec![Utc::now(); pixels];
         self.target_colors = vec![Color { r: 1, g: 1, b: 1 }; pixels];
-        let offsets = self.offsets();
-
-        for pixel_index in 0..self.total_pixels {
-            self.elapsed_time[pixel_index] =
-                Utc::now() + Duration::milliseconds(offsets[pixel_index]);
-        }
+        self.elapsed = self.initial_elapsed();
     }
 
     fn get_settings(&self) -> HashMap<String, String> {
@@ -201,14 +209,8 @@ impl Viz for BlendViz {
         let new_settings = BlendVizConfig::from_map(self.get_pretty_name().to_string(), settings);
         self.config = new_settings;
         self.pixels = vec![PixelViz::default(); self.total_pixels];
-        self.elapsed_time = vec![Utc::now(); self.total_pixels];
         self.target_colors = vec![Color { r: 1, g: 1, b: 1 }; self.total_pixels];
-        let offsets = self.offsets();
-
-        for pixel_index in 0..self.total_pixels {
-            self.elapsed_time[pixel_index] =
-                Utc::now() + Duration::milliseconds(offsets[pixel_index]);
-        }
+        self.elapsed = self.initial_elapsed();
     }
 }
 
@@ -221,12 +223,21 @@ impl BlendViz {
         BlendViz {
             config,
             total_pixels: 0,
-            elapsed_time: Vec::new(),
+            elapsed: Vec::new(),
             pixels: Vec::new(),
             target_colors: Vec::new(),
+            blend_remainder: 0.0,
         }
     }
 
+    /// Compute the initial elapsed time of each pixel from the color offsets in milliseconds.
+    fn initial_elapsed(&mut self) -> Vec<f32> {
+        self.offsets()
+            .iter()
+            .map(|offset| -(*offset as f32) / 1000.0)
+            .collect()
+    }
+
     /// Compute the initial state of the pixels.
     fn offsets(&mut self) -> Vec<i64> {
         let mut rng = rand::thread_rng();
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::features::AudioFeatures;
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
 
@@ -46,8 +48,14 @@ impl Viz for CenterViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, input: &Vec<f32>, _colors: &Vec<Color>) -> Vec<PixelViz> {
-        let total_bands = input.len();
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        _colors: &Vec<Color>,
+        _time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let bands = &input.bands;
+        let total_bands = bands.len();
         // separate pixel bands for each frequency
         // pixel visualization gets mirrored at the speaker center, so divide by 2
         let pixels_per_band: usize = (self.total_pixels / 2) / total_bands;
@@ -55,7 +63,7 @@ impl Viz for CenterViz {
 
         // total number of pixels that will be turned on
         let mut overall_intensity = 0;
-        for (_band_index, band) in input.iter().enumerate() {
+        for (_band_index, band) in bands.iter().enumerate() {
             let intensity = ((band / 100.0) * (pixels_per_band as f32)) as usize;
             overall_intensity += intensity;
         }
@@ -64,7 +72,7 @@ impl Viz for CenterViz {
 
         // determine color for pixels
         let mut pixel_index = 0;
-        for (band_index, band) in input.iter().enumerate() {
+        for (band_index, band) in bands.iter().enumerate() {
             // determine magnitude of frequency
             let intensity = ((band / 100.0) * (pixels_per_band as f32)) as usize;
 
@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Update rate in frames per second that per-frame factors in viz configs, like falloffs, refer to
+pub const REFERENCE_FPS: f32 = 60.0;
+
+/// Remaining time until a deadline below which `SystemClock` yields instead of sleeping;
+/// sleeping can overshoot by up to the scheduler granularity of the OS
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Source of monotonic time for visualizations.
+pub trait Clock: Send + Sync {
+    /// Returns the time elapsed since the clock has been created.
+    fn now(&self) -> Duration;
+
+    /// Blocks until the clock reaches the provided time.
+    fn sleep_until(&self, deadline: Duration);
+}
+
+/// Clock based on the monotonic system clock.
+pub struct SystemClock {
+    /// Time the clock has been created
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: Duration) {
+        loop {
+            let now = self.now();
+            if now >= deadline {
+                return;
+            }
+
+            // sleep for most of the time and yield for the rest to wake up precisely
+            let remaining = deadline - now;
+            if remaining > SPIN_THRESHOLD {
+                thread::sleep(remaining - SPIN_THRESHOLD);
+            } else {
+                thread::yield_now();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+/// Clock that only advances when told to; allows to control time in tests.
+///
+/// # Examples
+/// ```
+/// use led_speakers::viz::{Clock, ManualClock};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// clock.advance(Duration::from_millis(250));
+/// assert_eq!(clock.now(), Duration::from_millis(250));
+/// ```
+pub struct ManualClock {
+    /// Current time; shared between clones
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    /// Advances the clock to the deadline instead of blocking.
+    fn sleep_until(&self, deadline: Duration) {
+        let mut now = self.now.lock().unwrap();
+        if *now < deadline {
+            *now = deadline;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// Timing of a visualization update.
+pub struct FrameTime {
+    /// Monotonic time of the update
+    pub now: Duration,
+
+    /// Time since the previous update in seconds
+    pub delta: f32,
+}
+
+impl FrameTime {
+    pub fn new(now: Duration, delta: f32) -> Self {
+        FrameTime { now, delta }
+    }
+
+    /// Returns the number of frames at `REFERENCE_FPS` that have passed since the previous update.
+    ///
+    /// # Examples
+    /// ```
+    /// use led_speakers::viz::FrameTime;
+    /// use std::time::Duration;
+    ///
+    /// assert_eq!(FrameTime::new(Duration::from_millis(50), 0.05).frames(), 3.0);
+    /// ```
+    pub fn frames(&self) -> f32 {
+        self.delta * REFERENCE_FPS
+    }
+}
+
+/// Determines the frame time of consecutive visualization updates.
+pub struct FrameTimer {
+    /// Clock time is read from
+    clock: Arc<dyn Clock>,
+
+    /// Time of the previous update
+    last: Option<Duration>,
+}
+
+impl FrameTimer {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        FrameTimer { clock, last: None }
+    }
+
+    /// Returns the frame time of the current update; the delta of the first update is 0.
+    pub fn tick(&mut self) -> FrameTime {
+        let now = self.clock.now();
+        let delta = match self.last {
+            Some(last) => now.saturating_sub(last).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last = Some(now);
+        FrameTime::new(now, delta)
+    }
+}
@@ -1,7 +1,8 @@
+use crate::features::{AudioFeatures, BandSource};
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
-use chrono::prelude::*;
 
 use std::collections::HashMap;
 
@@ -21,6 +22,10 @@ pub struct FadingBeatVizConfig {
 
     /// Size of buffer keeping track of past frequency magnitudes.
     pub frequency_magnitude_buffer_size: i64,
+
+    #[serde(default)]
+    /// Band magnitudes brightness and dominant frequency are based on.
+    pub band_source: BandSource,
 }
 
 impl FadingBeatVizConfig {
@@ -36,6 +41,7 @@ impl FadingBeatVizConfig {
             "frequency_magnitude_buffer_size".to_string(),
             self.frequency_magnitude_buffer_size.to_string(),
         );
+        settings.insert("band_source".to_string(), self.band_source.to_string());
         settings
     }
 
@@ -58,6 +64,11 @@ impl FadingBeatVizConfig {
                 .unwrap_or(&"0".to_string())
                 .parse::<i64>()
                 .unwrap(),
+            band_source: settings
+                .get(&"band_source".to_string())
+                .unwrap_or(&"all".to_string())
+                .parse::<BandSource>()
+                .unwrap(),
         }
     }
 }
@@ -72,8 +83,8 @@ pub struct FadingBeatViz {
     /// Total number of pixels.
     total_pixels: usize,
 
-    /// Elapsed time from when pixels faded to a different color.
-    elapsed_time: DateTime<Utc>,
+    /// Elapsed time in seconds from when pixels faded to a different color.
+    elapsed: f32,
 
     /// Current color displayed.
     color_index: usize,
@@ -95,28 +106,22 @@ impl Viz for FadingBeatViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, input: &Vec<f32>, colors: &Vec<Color>) -> Vec<PixelViz> {
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
         // determine color and brightness of pixels based on frequency magnitudes
-        let total_bands = input.len();
-        let max_magnitude = 100.0 * total_bands as f32;
-        let magnitude: f32 = input.iter().sum();
         let mut viz = PixelViz::default();
-        viz.brightness = magnitude / max_magnitude;
+        viz.brightness = input.source_energy(self.config.band_source);
         viz.color_index = self.color_index;
 
         let next_color_index = (self.color_index + 1) % colors.len();
-        let now = Utc::now();
-        let elapsed = (now - self.elapsed_time).num_seconds();
+        self.elapsed += time.delta;
 
         // determine current dominant frequency
-        let mut dominant_frequency = 0;
-        let mut max_magnitude = 0.0;
-        for (i, mag) in input.into_iter().enumerate() {
-            if mag > &max_magnitude {
-                max_magnitude = *mag;
-                dominant_frequency = i;
-            }
-        }
+        let dominant_frequency = input.source_dominant_band(self.config.band_source);
 
         // Update past dominant frequency buffer and add current
         let prev_dominant_frequency = Self::mode(&self.dominant_frequencies);
@@ -131,10 +136,10 @@ impl Viz for FadingBeatViz {
         let current_dominant_frequency = Self::mode(&self.dominant_frequencies);
 
         // check if pixels are currently fading to a different color
-        if elapsed > self.config.fade_threshold && !self.is_fading {
+        if self.elapsed > self.config.fade_threshold as f32 && !self.is_fading {
             if current_dominant_frequency != prev_dominant_frequency {
                 self.is_fading = true;
-                self.elapsed_time = now;
+                self.elapsed = 0.0;
             }
         }
 
@@ -142,7 +147,7 @@ impl Viz for FadingBeatViz {
             // fade pixel colors
             let current_color = colors[self.color_index % colors.len()];
             let next_color = colors[next_color_index % colors.len()];
-            let elapsed_perc: f32 = elapsed as f32 / self.config.fade_duration as f32;
+            let elapsed_perc: f32 = (self.elapsed / self.config.fade_duration as f32).min(1.0);
             viz.red_mul =
                 (((next_color.r as f32 / current_color.r as f32) - 1.0) * elapsed_perc) + 1.0;
             viz.green_mul =
@@ -153,7 +158,7 @@ impl Viz for FadingBeatViz {
             if elapsed_perc >= 1.0 {
                 // stop fading if target color is reached
                 self.color_index = next_color_index;
-                self.elapsed_time = now;
+                self.elapsed = 0.0;
                 self.is_fading = false;
             }
         }
@@ -186,7 +191,7 @@ impl FadingBeatViz {
         FadingBeatViz {
             config: config.clone(),
             total_pixels: 0,
-            elapsed_time: Utc::now(),
+            elapsed: 0.0,
             color_index: 0,
             dominant_frequencies: vec![0; config.clone().frequency_magnitude_buffer_size as usize],
             is_fading: false,
@@ -0,0 +1,299 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::features::AudioFeatures;
+use crate::theme::Color;
+use crate::viz::viz::finite_or;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// Maximum number of simulation steps per update; avoids long updates after pauses
+const MAX_STEPS: f32 = 4.0;
+
+/// Number of pixels at the bottom of the fire new sparks are ignited in
+const SPARK_PIXELS: usize = 7;
+
+/// Cooling used if the configured one isn't a number
+const DEFAULT_COOLING: f32 = 55.0;
+
+/// Spark probability used if the configured one isn't a number
+const DEFAULT_SPARKING: f32 = 0.8;
+
+/// Flicker used if the configured one isn't a number
+const DEFAULT_FLICKER: f32 = 0.3;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines how the heat of the fire is mapped to colors.
+pub enum FirePalette {
+    /// Black body colors from red over yellow to white
+    Heat,
+
+    /// Theme colors from the first color for cold to the last color for hot pixels
+    Theme,
+}
+
+impl Default for FirePalette {
+    fn default() -> Self {
+        FirePalette::Heat
+    }
+}
+
+impl fmt::Display for FirePalette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirePalette::Heat => write!(f, "heat"),
+            FirePalette::Theme => write!(f, "theme"),
+        }
+    }
+}
+
+impl FromStr for FirePalette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heat" => Ok(FirePalette::Heat),
+            "theme" => Ok(FirePalette::Theme),
+            _ => Err(format!("Unknown fire palette {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct FireVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Determines how fast pixels cool down per step at `REFERENCE_FPS`; higher values result in shorter flames.
+    /// At least 0, `DEFAULT_COOLING` is used if not a number.
+    pub cooling: f32,
+
+    /// Probability of a new spark per step at full bass energy between 0 and 1;
+    /// `DEFAULT_SPARKING` is used if not a number.
+    pub sparking: f32,
+
+    /// Maximum brightness variation of pixels at full treble energy between 0 and 1;
+    /// `DEFAULT_FLICKER` is used if not a number.
+    pub flicker: f32,
+
+    /// Determines how heat is mapped to colors.
+    pub palette: FirePalette,
+}
+
+impl FireVizConfig {
+    /// Convert settings in map of strings to visualization config.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        settings.insert("cooling".to_string(), self.cooling.to_string());
+        settings.insert("sparking".to_string(), self.sparking.to_string());
+        settings.insert("flicker".to_string(), self.flicker.to_string());
+        settings.insert("palette".to_string(), self.palette.to_string());
+        settings
+    }
+
+    /// Create visualization config from map of strings.
+    pub fn from_map(name: String, settings: HashMap<String, String>) -> Self {
+        Self {
+            pretty_name: name,
+            cooling: settings
+                .get(&"cooling".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            sparking: settings
+                .get(&"sparking".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            flicker: settings
+                .get(&"flicker".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            palette: settings
+                .get(&"palette".to_string())
+                .unwrap_or(&"heat".to_string())
+                .parse::<FirePalette>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+/// Visualization simulating fire rising from the first pixel of the strip.
+/// Heat diffuses upwards and cools down; bass ignites new sparks at the bottom
+/// and treble makes the flames flicker.
+pub struct FireViz {
+    /// Visualization config.
+    pub config: FireVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    /// Heat of each pixel between 0 and 1.
+    heat: Vec<f32>,
+
+    /// Fraction of a simulation step that has not been run yet.
+    step_remainder: f32,
+}
+
+#[typetag::serde]
+impl Viz for FireViz {
+    fn get_name(&self) -> &str {
+        "fire_viz"
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let bands = &input.bands;
+        let third = (bands.len() / 3).max(1).min(bands.len());
+        let bass = Self::band_energy(&bands[..third]);
+        let treble = Self::band_energy(&bands[bands.len() - third..]);
+
+        // the simulation runs at a fixed step rate independent of the update rate
+        self.step_remainder = (self.step_remainder + time.frames()).min(MAX_STEPS);
+        while self.step_remainder >= 1.0 {
+            self.step(bass);
+            self.step_remainder -= 1.0;
+        }
+
+        let mut rng = rand::thread_rng();
+        let total_colors = colors.len().max(1);
+        let flicker = self.flicker();
+        self.heat
+            .iter()
+            .map(|heat| {
+                let mut pixel = match self.config.palette {
+                    FirePalette::Heat => Self::heat_pixel(*heat, colors),
+                    FirePalette::Theme => PixelViz {
+                        color_index: (heat * (total_colors - 1) as f32).round() as usize,
+                        brightness: *heat,
+                        ..PixelViz::default()
+                    },
+                };
+                pixel.brightness *= 1.0 - rng.gen::<f32>() * flicker * treble;
+                pixel
+            })
+            .collect()
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+        self.heat = vec![0.0; pixels];
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.to_map()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        let new_settings = FireVizConfig::from_map(self.get_pretty_name().to_string(), settings);
+        self.config = new_settings;
+        self.heat = vec![0.0; self.total_pixels];
+    }
+}
+
+unsafe impl Send for FireViz {}
+unsafe impl Sync for FireViz {}
+
+impl FireViz {
+    pub fn new(config: FireVizConfig) -> Self {
+        FireViz {
+            config,
+            total_pixels: 0,
+            heat: Vec::new(),
+            step_remainder: 0.0,
+        }
+    }
+
+    /// Runs a simulation step: cools down all pixels, lets heat rise and ignites new sparks.
+    fn step(&mut self, bass: f32) {
+        let mut rng = rand::thread_rng();
+        let total_pixels = self.heat.len();
+        if total_pixels == 0 {
+            return;
+        }
+
+        // shorter strips need to cool down faster for flames of the same relative height
+        let max_cooling = (self.cooling() * 10.0 / total_pixels as f32 + 2.0) / 255.0;
+        for heat in self.heat.iter_mut() {
+            *heat = (*heat - rng.gen::<f32>() * max_cooling).max(0.0);
+        }
+
+        // heat drifts up and diffuses
+        for i in (2..total_pixels).rev() {
+            self.heat[i] = (self.heat[i - 1] + 2.0 * self.heat[i - 2]) / 3.0;
+        }
+
+        if rng.gen::<f32>() < self.sparking() * bass {
+            let pixel = rng.gen_range(0..SPARK_PIXELS.min(total_pixels));
+            self.heat[pixel] = (self.heat[pixel] + rng.gen_range(0.63..1.0)).min(1.0);
+        }
+    }
+
+    /// Returns how fast pixels cool down per step.
+    fn cooling(&self) -> f32 {
+        finite_or(self.config.cooling, DEFAULT_COOLING).max(0.0)
+    }
+
+    /// Returns the probability of a new spark per step at full bass energy.
+    fn sparking(&self) -> f32 {
+        finite_or(self.config.sparking, DEFAULT_SPARKING).clamp(0.0, 1.0)
+    }
+
+    /// Returns the maximum brightness variation of pixels at full treble energy.
+    fn flicker(&self) -> f32 {
+        finite_or(self.config.flicker, DEFAULT_FLICKER).clamp(0.0, 1.0)
+    }
+
+    /// Returns the average magnitude of the provided bands between 0 and 1.
+    fn band_energy(bands: &[f32]) -> f32 {
+        if bands.is_empty() {
+            return 0.0;
+        }
+        (bands.iter().sum::<f32>() / (bands.len() as f32 * 100.0)).clamp(0.0, 1.0)
+    }
+
+    /// Returns the black body color of the provided heat.
+    ///
+    /// Colors range from dark red over red, orange and yellow to white;
+    /// dark colors are represented by the brightness of the pixel.
+    ///
+    fn heat_pixel(heat: f32, colors: &[Color]) -> PixelViz {
+        let scaled = heat * 3.0;
+        let ramp = |v: f32| (v.clamp(0.0, 1.0) * 255.0) as u8;
+        let color = if scaled > 2.0 {
+            Color {
+                r: 255,
+                g: 255,
+                b: ramp(scaled - 2.0),
+            }
+        } else if scaled > 1.0 {
+            Color {
+                r: 255,
+                g: ramp(scaled - 1.0),
+                b: 0,
+            }
+        } else {
+            Color { r: 255, g: 0, b: 0 }
+        };
+
+        let mut pixel = PixelViz::with_color(color, colors);
+        pixel.brightness = scaled.min(1.0);
+        pixel
+    }
+}
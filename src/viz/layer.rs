@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::settings::{LayerSettings, LinkOrder, OutputSettings, Settings, Speaker};
+use crate::theme::{Color, Theme};
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines how the pixels of a layer are combined with the layers below.
+pub enum BlendMode {
+    /// Layer pixels replace the pixels below
+    Normal,
+
+    /// Color values are added
+    Add,
+
+    /// Color values are multiplied; darkens the pixels below
+    Multiply,
+
+    /// Inverted color values are multiplied; brightens the pixels below
+    Screen,
+
+    /// Maximum of the color values
+    Max,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Normal
+    }
+}
+
+impl fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlendMode::Normal => write!(f, "normal"),
+            BlendMode::Add => write!(f, "add"),
+            BlendMode::Multiply => write!(f, "multiply"),
+            BlendMode::Screen => write!(f, "screen"),
+            BlendMode::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl FromStr for BlendMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(BlendMode::Normal),
+            "add" => Ok(BlendMode::Add),
+            "multiply" => Ok(BlendMode::Multiply),
+            "screen" => Ok(BlendMode::Screen),
+            "max" => Ok(BlendMode::Max),
+            _ => Err(format!("Unknown blend mode {}", s)),
+        }
+    }
+}
+
+impl BlendMode {
+    /// Blends a color value of a layer with the color value below; values are between 0 and 255.
+    pub fn blend(&self, below: f32, value: f32) -> f32 {
+        match self {
+            BlendMode::Normal => value,
+            BlendMode::Add => below + value,
+            BlendMode::Multiply => below * value / 255.0,
+            BlendMode::Screen => 255.0 - (255.0 - below) * (255.0 - value) / 255.0,
+            BlendMode::Max => below.max(value),
+        }
+    }
+}
+
+/// A visualization in the layer stack of the `VizRunner`.
+pub struct Layer {
+    /// Visualization of the left output
+    pub viz_left: Box<dyn Viz>,
+
+    /// Visualization of the right output
+    pub viz_right: Box<dyn Viz>,
+
+    /// Theme of the layer; the theme of the `VizRunner` is used if not set
+    pub theme: Option<Theme>,
+
+    /// Opacity of the layer between 0 and 1
+    pub opacity: f32,
+
+    /// Determines how the layer is combined with the layers below
+    pub blend_mode: BlendMode,
+}
+
+impl Clone for Layer {
+    fn clone(&self) -> Self {
+        Layer {
+            viz_left: dyn_clone::clone_box(&*self.viz_left),
+            viz_right: dyn_clone::clone_box(&*self.viz_right),
+            theme: self.theme.clone(),
+            opacity: self.opacity,
+            blend_mode: self.blend_mode,
+        }
+    }
+}
+
+impl Layer {
+    pub fn new(
+        viz: Box<dyn Viz>,
+        output: &OutputSettings,
+        theme: Option<Theme>,
+        opacity: f32,
+        blend_mode: BlendMode,
+    ) -> Self {
+        let mut viz_left = dyn_clone::clone_box(&*viz);
+        viz_left.set_total_pixels(output.total_pixels(Speaker::Left));
+        let mut viz_right = viz;
+        viz_right.set_total_pixels(output.total_pixels(Speaker::Right));
+
+        Layer {
+            viz_left,
+            viz_right,
+            theme,
+            opacity,
+            blend_mode,
+        }
+    }
+
+    /// Creates a layer from its settings.
+    ///
+    /// Returns `None` if the visualization or theme doesn't exist.
+    ///
+    pub fn from_settings(layer: &LayerSettings, settings: &Settings) -> Option<Self> {
+        let mut viz = settings.visualization(&layer.viz)?;
+        if let Some(viz_settings) = &layer.settings {
+            viz.update_settings(Self::merge_settings(viz.get_settings(), viz_settings));
+        }
+        let theme = match &layer.theme {
+            Some(name) => Some(settings.theme(name)?),
+            None => None,
+        };
+
+        Some(Layer::new(
+            viz,
+            &settings.output,
+            theme,
+            layer.opacity,
+            layer.blend_mode,
+        ))
+    }
+
+    /// Returns the settings of the layer.
+    pub fn to_settings(&self) -> LayerSettings {
+        LayerSettings {
+            viz: self.viz_left.get_name().to_string(),
+            settings: Some(self.viz_left.get_settings()),
+            theme: self.theme.as_ref().map(|t| t.name.clone()),
+            opacity: self.opacity,
+            blend_mode: self.blend_mode,
+        }
+    }
+
+    /// Returns the visualization of the provided speaker.
+    pub fn viz(&self, speaker: Speaker) -> &dyn Viz {
+        match speaker {
+            Speaker::Left => &*self.viz_left,
+            Speaker::Right => &*self.viz_right,
+        }
+    }
+
+    /// Returns the mutable visualization of the provided speaker.
+    fn viz_mut(&mut self, speaker: Speaker) -> &mut Box<dyn Viz> {
+        match speaker {
+            Speaker::Left => &mut self.viz_left,
+            Speaker::Right => &mut self.viz_right,
+        }
+    }
+
+    /// Replaces the visualization of the provided speaker.
+    pub fn set_viz(&mut self, speaker: Speaker, mut viz: Box<dyn Viz>, output: &OutputSettings) {
+        viz.set_total_pixels(output.total_pixels(speaker));
+        *self.viz_mut(speaker) = viz;
+    }
+
+    /// Replaces the settings of the layer visualizations of both speakers.
+    pub fn update_viz_settings(&mut self, settings: &HashMap<String, String>) {
+        self.update_output_viz_settings(Speaker::Left, settings);
+        self.update_output_viz_settings(Speaker::Right, settings);
+    }
+
+    /// Replaces the settings of the layer visualization of the provided speaker.
+    pub fn update_output_viz_settings(
+        &mut self,
+        speaker: Speaker,
+        settings: &HashMap<String, String>,
+    ) {
+        self.viz_mut(speaker).update_settings(settings.clone());
+    }
+
+    /// Updates the settings of the layer visualizations of both speakers.
+    ///
+    /// Settings that are not provided keep their current value.
+    ///
+    pub fn merge_viz_settings(&mut self, settings: &HashMap<String, String>) {
+        for speaker in [Speaker::Left, Speaker::Right] {
+            let viz = self.viz_mut(speaker);
+            let merged = Self::merge_settings(viz.get_settings(), settings);
+            viz.update_settings(merged);
+        }
+    }
+
+    /// Overrides current settings with the provided settings.
+    fn merge_settings(
+        mut current: HashMap<String, String>,
+        settings: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        current.extend(settings.iter().map(|(k, v)| (k.clone(), v.clone())));
+        current
+    }
+}
+
+/// Combines the pixels of a layer with the composited color values of the layers below.
+///
+/// Pixel brightness is used as alpha, so pixels that are turned off keep the color of the
+/// layers below. Color values are between 0 and 255.
+///
+/// # Arguments
+/// * `frame`: RGB values of the layers below; updated in place
+/// * `pixels`: pixel visualizations of the layer
+/// * `colors`: theme colors of the layer
+/// * `opacity`: opacity of the layer
+/// * `blend_mode`: determines how the layer is combined with the layers below
+///
+pub fn composite(
+    frame: &mut [[f32; 3]],
+    pixels: &[PixelViz],
+    colors: &[Color],
+    opacity: f32,
+    blend_mode: BlendMode,
+) {
+    if colors.is_empty() {
+        return;
+    }
+
+    for (below, pixel) in frame.iter_mut().zip(pixels.iter()) {
+        let color = colors[pixel.color_index % colors.len()];
+        let values = [
+            (color.r as f32 * pixel.red_mul).clamp(0.0, 255.0),
+            (color.g as f32 * pixel.green_mul).clamp(0.0, 255.0),
+            (color.b as f32 * pixel.blue_mul).clamp(0.0, 255.0),
+        ];
+        let alpha = (pixel.brightness * opacity).clamp(0.0, 1.0);
+
+        for (b, value) in below.iter_mut().zip(values.iter()) {
+            let blended = blend_mode.blend(*b, *value).min(255.0);
+            *b += (blended - *b) * alpha;
+        }
+    }
+}
+
+/// Splits the color values of linked outputs into the values of the left and right output.
+///
+/// # Arguments
+/// * `frame`: RGB values of both outputs as a single strip
+/// * `output`: output settings determining the order and direction of the outputs
+///
+pub fn split_linked(frame: &[[f32; 3]], output: &OutputSettings) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let left_leds = output.left.total_leds as usize;
+    let right_leds = output.right.total_leds as usize;
+
+    let (mut left, mut right) = match output.linked.order {
+        LinkOrder::LeftRight => {
+            let (left, right) = frame.split_at(left_leds.min(frame.len()));
+            (left.to_vec(), right.to_vec())
+        }
+        LinkOrder::RightLeft => {
+            let (right, left) = frame.split_at(right_leds.min(frame.len()));
+            (left.to_vec(), right.to_vec())
+        }
+    };
+
+    if output.linked.reverse_left {
+        left.reverse();
+    }
+    if output.linked.reverse_right {
+        right.reverse();
+    }
+    (left, right)
+}
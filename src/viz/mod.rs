@@ -1,26 +1,79 @@
 mod blend_viz;
 mod center_viz;
+mod clock;
 mod fading_beat_viz;
+mod fire_viz;
+mod layer;
+mod plasma_viz;
+mod playlist;
+mod plugin_viz;
+mod ripple_viz;
 mod rotating_viz;
+mod scheduler;
+mod script_viz;
 mod solid_beat_viz;
 mod solid_viz;
 mod sparkle_viz;
+mod spectrum_viz;
+mod strobe_viz;
+mod transition;
 mod viz;
+mod waterfall_viz;
 
 pub use blend_viz::BlendViz;
 pub use blend_viz::BlendVizConfig;
 pub use center_viz::CenterViz;
 pub use center_viz::CenterVizConfig;
+pub use clock::Clock;
+pub use clock::FrameTime;
+pub use clock::FrameTimer;
+pub use clock::ManualClock;
+pub use clock::SystemClock;
+pub use clock::REFERENCE_FPS;
 pub use fading_beat_viz::FadingBeatViz;
 pub use fading_beat_viz::FadingBeatVizConfig;
+pub use fire_viz::FirePalette;
+pub use fire_viz::FireViz;
+pub use fire_viz::FireVizConfig;
+pub use layer::composite;
+pub use layer::split_linked;
+pub use layer::BlendMode;
+pub use layer::Layer;
+pub use plasma_viz::PlasmaViz;
+pub use plasma_viz::PlasmaVizConfig;
+pub use playlist::Playlist;
+pub use playlist::PlaylistItem;
+pub use playlist::SongChangeDetector;
+pub use plugin_viz::PluginViz;
+pub use plugin_viz::PluginVizConfig;
+pub use ripple_viz::RipplePosition;
+pub use ripple_viz::RippleViz;
+pub use ripple_viz::RippleVizConfig;
 pub use rotating_viz::RotatingViz;
 pub use rotating_viz::RotatingVizConfig;
+pub use scheduler::FrameScheduler;
+pub use scheduler::FrameStats;
+pub use script_viz::ScriptViz;
+pub use script_viz::ScriptVizConfig;
 pub use solid_beat_viz::SolidBeatViz;
 pub use solid_beat_viz::SolidBeatVizConfig;
 pub use solid_viz::SolidViz;
 pub use solid_viz::SolidVizConfig;
 pub use sparkle_viz::SparkleViz;
 pub use sparkle_viz::SparkleVizConfig;
+pub use spectrum_viz::SpectrumOrientation;
+pub use spectrum_viz::SpectrumViz;
+pub use spectrum_viz::SpectrumVizConfig;
+pub use strobe_viz::StrobeColorMode;
+pub use strobe_viz::StrobeViz;
+pub use strobe_viz::StrobeVizConfig;
+pub use strobe_viz::MAX_BRIGHTNESS_CHANGE;
+pub use strobe_viz::MAX_FLASH_RATE;
+pub use transition::Transition;
+pub use viz::OutputThemes;
 pub use viz::PixelViz;
 pub use viz::Viz;
 pub use viz::VizRunner;
+pub use waterfall_viz::WaterfallColorSource;
+pub use waterfall_viz::WaterfallViz;
+pub use waterfall_viz::WaterfallVizConfig;
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::features::AudioFeatures;
+use crate::theme::Color;
+use crate::viz::viz::finite_or;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// Period of the noise field along the time axis; keeps the time offset small to avoid
+/// losing float precision when running for a long time
+const NOISE_PERIOD: i32 = 256;
+
+/// Factor of how much the smoothed energy approaches the current energy per frame at `REFERENCE_FPS`
+const ENERGY_SMOOTHING: f32 = 0.9;
+
+/// Speed used if the configured one isn't a number
+const DEFAULT_SPEED: f32 = 0.3;
+
+/// Scale used if the configured one isn't a number
+const DEFAULT_SCALE: f32 = 0.08;
+
+/// Reactivity used if the configured one isn't a number
+const DEFAULT_REACTIVITY: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct PlasmaVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Distance the noise field moves per second without audio; `DEFAULT_SPEED` is used if
+    /// not a number.
+    pub speed: f32,
+
+    /// Distance in the noise field between neighboring pixels without audio;
+    /// larger values result in smaller blobs. `DEFAULT_SCALE` is used if not a number.
+    pub scale: f32,
+
+    /// Determines how much audio energy increases speed, scale and brightness, between 0 and 1;
+    /// `DEFAULT_REACTIVITY` is used if not a number.
+    pub reactivity: f32,
+}
+
+impl PlasmaVizConfig {
+    /// Convert settings in map of strings to visualization config.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        settings.insert("speed".to_string(), self.speed.to_string());
+        settings.insert("scale".to_string(), self.scale.to_string());
+        settings.insert("reactivity".to_string(), self.reactivity.to_string());
+        settings
+    }
+
+    /// Create visualization config from map of strings.
+    pub fn from_map(name: String, settings: HashMap<String, String>) -> Self {
+        Self {
+            pretty_name: name,
+            speed: settings
+                .get(&"speed".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            scale: settings
+                .get(&"scale".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            reactivity: settings
+                .get(&"reactivity".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+/// Visualization sampling a 2D gradient noise field along the strip and over time.
+/// Noise values are mapped to a gradient of the theme colors; audio energy makes the
+/// plasma move faster, form smaller blobs and shine brighter.
+pub struct PlasmaViz {
+    /// Visualization config.
+    pub config: PlasmaVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    /// Position of the strip in the noise field along the time axis.
+    offset: f32,
+
+    /// Audio energy smoothed over time between 0 and 1.
+    energy: f32,
+}
+
+#[typetag::serde]
+impl Viz for PlasmaViz {
+    fn get_name(&self) -> &str {
+        "plasma_viz"
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        // smooth energy for a calm look; smoothing is independent of the update rate
+        let smoothing = ENERGY_SMOOTHING.powf(time.frames());
+        self.energy = input.energy().clamp(0.0, 1.0) * (1.0 - smoothing) + self.energy * smoothing;
+
+        let reactivity = finite_or(self.config.reactivity, DEFAULT_REACTIVITY).clamp(0.0, 1.0);
+        let modulation = reactivity * self.energy;
+        let speed = finite_or(self.config.speed, DEFAULT_SPEED) * (1.0 + modulation);
+        let scale = finite_or(self.config.scale, DEFAULT_SCALE) * (1.0 + modulation);
+        let brightness = 1.0 - reactivity + modulation;
+
+        self.offset = (self.offset + speed * time.delta).rem_euclid(NOISE_PERIOD as f32);
+
+        // scale from the center of the strip so the plasma doesn't drift to one end
+        let center = self.total_pixels as f32 / 2.0;
+        (0..self.total_pixels)
+            .map(|i| {
+                let noise = Self::noise((i as f32 - center) * scale, self.offset);
+                let mut pixel = Self::palette_pixel((noise + 1.0) / 2.0, colors);
+                pixel.brightness = brightness;
+                pixel
+            })
+            .collect()
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.to_map()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        let new_settings = PlasmaVizConfig::from_map(self.get_pretty_name().to_string(), settings);
+        self.config = new_settings;
+    }
+}
+
+unsafe impl Send for PlasmaViz {}
+unsafe impl Sync for PlasmaViz {}
+
+impl PlasmaViz {
+    pub fn new(config: PlasmaVizConfig) -> Self {
+        PlasmaViz {
+            config,
+            total_pixels: 0,
+            offset: 0.0,
+            energy: 0.0,
+        }
+    }
+
+    /// Returns the 2D gradient noise value at the provided point between -1 and 1.
+    ///
+    /// The noise field repeats every `NOISE_PERIOD` along the y axis.
+    ///
+    fn noise(x: f32, y: f32) -> f32 {
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let (dx, dy) = (x - x0, y - y0);
+        let (xi, yi) = (x0 as i32, y0 as i32);
+
+        let dot = |cx: i32, cy: i32, ox: f32, oy: f32| {
+            let (gx, gy) = Self::gradient(cx, cy.rem_euclid(NOISE_PERIOD));
+            gx * ox + gy * oy
+        };
+        let n00 = dot(xi, yi, dx, dy);
+        let n10 = dot(xi + 1, yi, dx - 1.0, dy);
+        let n01 = dot(xi, yi + 1, dx, dy - 1.0);
+        let n11 = dot(xi + 1, yi + 1, dx - 1.0, dy - 1.0);
+
+        let (u, v) = (Self::fade(dx), Self::fade(dy));
+        let nx0 = n00 + u * (n10 - n00);
+        let nx1 = n01 + u * (n11 - n01);
+
+        // values of 2D gradient noise are within +-sqrt(0.5)
+        ((nx0 + v * (nx1 - nx0)) * std::f32::consts::SQRT_2).clamp(-1.0, 1.0)
+    }
+
+    /// Returns a pseudo random unit vector for the provided grid point.
+    fn gradient(x: i32, y: i32) -> (f32, f32) {
+        let mut hash = (x as u32).wrapping_mul(0x27d4_eb2d) ^ (y as u32).wrapping_mul(0x1656_67b1);
+        hash ^= hash >> 15;
+        hash = hash.wrapping_mul(0x85eb_ca6b);
+        hash ^= hash >> 13;
+
+        let angle = hash as f32 / u32::MAX as f32 * std::f32::consts::TAU;
+        (angle.cos(), angle.sin())
+    }
+
+    /// Smoothstep curve used to interpolate between grid points.
+    fn fade(t: f32) -> f32 {
+        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+    }
+
+    /// Returns a pixel with the color at the provided position between 0 and 1 of a gradient
+    /// running through all theme colors.
+    fn palette_pixel(position: f32, colors: &[Color]) -> PixelViz {
+        if colors.len() < 2 {
+            return PixelViz::default();
+        }
+
+        let scaled = position.clamp(0.0, 1.0) * (colors.len() - 1) as f32;
+        let index = (scaled.floor() as usize).min(colors.len() - 2);
+        let fraction = scaled - index as f32;
+        let (from, to) = (colors[index], colors[index + 1]);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+
+        PixelViz::with_color(
+            Color {
+                r: mix(from.r, to.r),
+                g: mix(from.g, to.g),
+                b: mix(from.b, to.b),
+            },
+            colors,
+        )
+    }
+}
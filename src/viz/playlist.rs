@@ -0,0 +1,264 @@
+use rand::seq::SliceRandom;
+
+use crate::features::AudioFeatures;
+use crate::settings::{PlaylistAdvance, PlaylistSettings, Settings};
+use crate::theme::Theme;
+use crate::viz::FrameTime;
+use crate::viz::Viz;
+
+/// Loudness in LUFS below which audio is considered silent
+const SILENCE_LOUDNESS: f32 = -50.0;
+
+/// Minimum duration of silence in seconds that separates two songs
+const SILENCE_GAP: f32 = 1.0;
+
+/// Time constant in seconds of the short-term loudness average
+const SHORT_TERM_TIME: f32 = 1.5;
+
+/// Time constant in seconds of the long-term loudness average
+const LONG_TERM_TIME: f32 = 20.0;
+
+/// Minimum difference in dB between short-term and long-term loudness that indicates a new song
+const ENERGY_SHIFT: f32 = 10.0;
+
+/// Minimum time in seconds after a song change before an energy shift is considered a new song
+const MIN_SONG_DURATION: f32 = 30.0;
+
+/// Detects the start of a new song in the audio features.
+///
+/// A new song starts when sound follows a gap of silence, or when the short-term loudness
+/// shifts considerably compared to the long-term loudness of the current song.
+pub struct SongChangeDetector {
+    /// Time in seconds audio has been silent
+    silence: f32,
+
+    /// Short-term average loudness in LUFS; not set until the first sound
+    short_term: Option<f32>,
+
+    /// Long-term average loudness in LUFS
+    long_term: f32,
+
+    /// Time in seconds since the current song started
+    song_duration: f32,
+}
+
+impl SongChangeDetector {
+    pub fn new() -> Self {
+        SongChangeDetector {
+            silence: 0.0,
+            short_term: None,
+            long_term: 0.0,
+            song_duration: 0.0,
+        }
+    }
+
+    /// Returns `true` if a new song starts with the provided audio features.
+    pub fn update(&mut self, features: &AudioFeatures, time: &FrameTime) -> bool {
+        self.song_duration += time.delta;
+
+        if features.loudness < SILENCE_LOUDNESS {
+            self.silence += time.delta;
+            return false;
+        }
+
+        let short_term = match self.short_term {
+            Some(short_term) if self.silence < SILENCE_GAP => short_term,
+            previous => {
+                // first sound, or sound after a gap of silence, starts a new song;
+                // the very first sound is not a change though
+                self.silence = 0.0;
+                self.short_term = Some(features.loudness);
+                self.long_term = features.loudness;
+                self.song_duration = 0.0;
+                return previous.is_some();
+            }
+        };
+        self.silence = 0.0;
+
+        // exponential moving averages of the loudness
+        let short_term =
+            short_term + (features.loudness - short_term) * (time.delta / SHORT_TERM_TIME).min(1.0);
+        self.long_term +=
+            (features.loudness - self.long_term) * (time.delta / LONG_TERM_TIME).min(1.0);
+        self.short_term = Some(short_term);
+
+        if self.song_duration >= MIN_SONG_DURATION
+            && (short_term - self.long_term).abs() >= ENERGY_SHIFT
+        {
+            // loudness of the new song is the reference for the next change
+            self.long_term = short_term;
+            self.song_duration = 0.0;
+            return true;
+        }
+
+        false
+    }
+}
+
+impl Default for SongChangeDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Visualization and theme of a playlist entry.
+pub struct PlaylistItem {
+    /// Visualization to show
+    pub viz: Box<dyn Viz>,
+
+    /// Theme to activate; the active theme is kept if not set
+    pub theme: Option<Theme>,
+}
+
+impl Clone for PlaylistItem {
+    fn clone(&self) -> Self {
+        PlaylistItem {
+            viz: dyn_clone::clone_box(&*self.viz),
+            theme: self.theme.clone(),
+        }
+    }
+}
+
+/// Rotates through visualizations and themes.
+///
+/// Entries are shown in the configured or in random order and the playlist advances
+/// depending on the duration of an entry, detected song changes or the number of beats.
+pub struct Playlist {
+    /// Settings the playlist has been created from
+    settings: PlaylistSettings,
+
+    /// Visualizations and themes of the entries; not set for skipped entries
+    items: Vec<Option<PlaylistItem>>,
+
+    /// Order in which entries are shown; skipped entries are left out
+    order: Vec<usize>,
+
+    /// Position of the current entry in `order`
+    position: usize,
+
+    /// Time in seconds the current entry has been shown
+    elapsed: f32,
+
+    /// Number of beats since the current entry has been shown
+    beats: u32,
+
+    /// Whether there was an onset in the previous update; onsets can span multiple updates
+    was_onset: bool,
+
+    /// Detects new songs if the playlist advances on song changes
+    song_change: SongChangeDetector,
+}
+
+impl Playlist {
+    /// Creates a playlist from its settings.
+    ///
+    /// Entries of visualizations that aren't available, e.g. disabled flashing ones, are skipped.
+    /// Returns `None` if there are no entries left or if a visualization or theme doesn't exist.
+    ///
+    pub fn from_settings(playlist: &PlaylistSettings, settings: &Settings) -> Option<Self> {
+        let items = playlist
+            .entries
+            .iter()
+            .map(|entry| {
+                if !settings.is_available(&entry.viz) {
+                    eprintln!(
+                        "Skipping playlist entry of unavailable visualization {}",
+                        entry.viz
+                    );
+                    return Some(None);
+                }
+
+                let theme = match &entry.theme {
+                    Some(name) => Some(settings.theme(name)?),
+                    None => None,
+                };
+                Some(Some(PlaylistItem {
+                    viz: settings.visualization(&entry.viz)?,
+                    theme,
+                }))
+            })
+            .collect::<Option<Vec<Option<PlaylistItem>>>>()?;
+
+        let mut order: Vec<usize> = (0..items.len()).filter(|i| items[*i].is_some()).collect();
+        if order.is_empty() {
+            return None;
+        }
+        if playlist.shuffle {
+            order.shuffle(&mut rand::thread_rng());
+        }
+
+        Some(Playlist {
+            settings: playlist.clone(),
+            items,
+            order,
+            position: 0,
+            elapsed: 0.0,
+            beats: 0,
+            was_onset: false,
+            song_change: SongChangeDetector::new(),
+        })
+    }
+
+    /// Returns the index of the current entry in the playlist settings.
+    pub fn current_entry(&self) -> usize {
+        self.order[self.position]
+    }
+
+    /// Returns the visualization and theme of the current entry.
+    pub fn current(&self) -> PlaylistItem {
+        self.items[self.current_entry()]
+            .clone()
+            .expect("skipped entries are not in the order")
+    }
+
+    /// Updates the playlist state based on the provided audio features.
+    ///
+    /// Returns the next entry if the playlist advances.
+    ///
+    pub fn update(&mut self, features: &AudioFeatures, time: &FrameTime) -> Option<PlaylistItem> {
+        self.elapsed += time.delta;
+        if features.onset && !self.was_onset {
+            self.beats += 1;
+        }
+        self.was_onset = features.onset;
+
+        // song changes are tracked continuously, also when the entry duration ends first
+        let is_new_song = self.settings.advance == PlaylistAdvance::SongChange
+            && self.song_change.update(features, time);
+
+        let entry = &self.settings.entries[self.current_entry()];
+        let advance = self.elapsed >= entry.duration
+            || match self.settings.advance {
+                PlaylistAdvance::Timer => false,
+                PlaylistAdvance::SongChange => is_new_song,
+                PlaylistAdvance::Beats => self.beats >= entry.beats,
+            };
+
+        if advance {
+            Some(self.advance())
+        } else {
+            None
+        }
+    }
+
+    /// Advances to the next entry and returns it.
+    pub fn advance(&mut self) -> PlaylistItem {
+        self.position += 1;
+        if self.position == self.order.len() {
+            self.position = 0;
+
+            if self.settings.shuffle {
+                // reshuffle, but don't show the last entry twice in a row
+                let last = self.order[self.order.len() - 1];
+                self.order.shuffle(&mut rand::thread_rng());
+                if self.order.len() > 1 && self.order[0] == last {
+                    self.order.swap(0, 1);
+                }
+            }
+        }
+
+        self.elapsed = 0.0;
+        self.beats = 0;
+        self.current()
+    }
+}
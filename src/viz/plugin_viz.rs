@@ -0,0 +1,465 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use crate::features::AudioFeatures;
+use crate::settings::PluginSettings;
+use crate::theme::Color;
+use crate::viz::script_viz::pretty_name;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// File extension of plugin modules
+const PLUGIN_EXTENSION: &str = "wasm";
+
+/// Prefix of the identifiers of plugin visualizations; avoids clashes with built-in visualizations
+const NAME_PREFIX: &str = "plugin_";
+
+/// Size in bytes of a pixel returned by `update`:
+/// color index (u32), brightness, red, green and blue multiplier (f32)
+const PIXEL_SIZE: usize = 20;
+
+/// Maximum size in bytes of the settings schema
+const MAX_SCHEMA_SIZE: usize = 64 * 1024;
+
+/// Number of consecutive failed updates after which a plugin is disabled
+const MAX_FAILURES: u32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct PluginVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Unique identifier of the visualization.
+    pub name: String,
+
+    /// Path of the plugin module.
+    pub path: PathBuf,
+
+    /// Settings passed to the plugin.
+    pub settings: HashMap<String, String>,
+}
+
+#[derive(Clone)]
+/// Compiled plugin module and the limits it runs with.
+struct Plugin {
+    /// WASM runtime the module has been compiled with
+    engine: Engine,
+
+    /// Compiled plugin module
+    module: Arc<Module>,
+
+    /// Maximum fuel the plugin can consume per call
+    max_fuel: u64,
+
+    /// Maximum size of the plugin memory in bytes
+    max_memory: usize,
+}
+
+/// Running instance of a plugin module.
+struct PluginInstance {
+    /// Instance state, including the plugin memory
+    store: Store<StoreLimits>,
+
+    /// Exported plugin memory
+    memory: Memory,
+
+    /// Exported function allocating plugin memory for data passed by the host
+    alloc: TypedFunc<i32, i32>,
+
+    /// Exported function updating the plugin and returning a pointer to its pixels
+    update: TypedFunc<(i32, i32, f32), i32>,
+
+    /// Optional exported function returning a pointer to the settings schema
+    settings_schema: Option<TypedFunc<(), i32>>,
+
+    /// Pointer to the memory bands are written to and the number of bands it fits
+    bands: Option<(i32, usize)>,
+
+    /// Maximum fuel the plugin can consume per call
+    max_fuel: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+/// Visualization running a WebAssembly plugin module.
+///
+/// Each plugin visualization runs its own module instance. Clones start with a new instance,
+/// which means the plugin state is reset, e.g. when the visualization is set for both outputs.
+pub struct PluginViz {
+    /// Visualization config.
+    pub config: PluginVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    #[serde(skip)]
+    /// Compiled plugin module.
+    plugin: Option<Plugin>,
+
+    #[serde(skip)]
+    /// Plugin instance; instantiated on the next update if not set.
+    instance: Option<PluginInstance>,
+
+    /// Last error of the plugin; used to report each error only once.
+    last_error: Option<String>,
+
+    #[serde(skip)]
+    /// Number of consecutive failed updates; the plugin is disabled after `MAX_FAILURES`.
+    failures: u32,
+}
+
+impl Clone for PluginViz {
+    fn clone(&self) -> Self {
+        PluginViz {
+            config: self.config.clone(),
+            total_pixels: self.total_pixels,
+            plugin: self.plugin.clone(),
+            instance: None,
+            last_error: self.last_error.clone(),
+            failures: self.failures,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Viz for PluginViz {
+    fn get_name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        if self.instance.is_none() && self.failures < MAX_FAILURES {
+            if let Some(plugin) = &self.plugin {
+                match plugin.instantiate(self.total_pixels, &self.config.settings) {
+                    Ok(instance) => self.instance = Some(instance),
+                    Err(e) => self.report_failure(e.to_string()),
+                }
+            }
+        }
+
+        let total_pixels = self.total_pixels;
+        let result = self
+            .instance
+            .as_mut()
+            .map(|instance| instance.update(&input.bands, time.delta, total_pixels));
+
+        let mut pixels = match result {
+            Some(Ok(plugin_pixels)) => {
+                self.last_error = None;
+                self.failures = 0;
+                plugin_pixels
+                    .chunks_exact(PIXEL_SIZE)
+                    .map(|p| to_pixel_viz(p, colors.len()))
+                    .collect::<Vec<PixelViz>>()
+            }
+            Some(Err(e)) => {
+                // the plugin state might be broken after a trap; start over with a new instance
+                self.report_failure(e.to_string());
+                self.instance = None;
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        // pixels are turned off if the plugin isn't running
+        let mut off = PixelViz::default();
+        off.off();
+        pixels.resize(self.total_pixels, off);
+        pixels
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+        self.instance = None;
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.settings.clone()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        // new settings might fix a disabled plugin
+        self.config.settings = settings;
+        self.instance = None;
+        self.failures = 0;
+    }
+}
+
+// the wasmi store, module and functions are thread-safe; fails to compile if that changes
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PluginViz>();
+};
+
+impl PluginViz {
+    /// Compiles the plugin module at the provided path.
+    ///
+    /// The module is instantiated once to check its exports and read its settings schema.
+    ///
+    pub fn new(path: &Path, settings: &PluginSettings) -> anyhow::Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &fs::read(path)?)
+            .map_err(|e| anyhow!("Failed to compile {:?}: {}", path, e))?;
+        let plugin = Plugin {
+            engine,
+            module: Arc::new(module),
+            max_fuel: settings.max_fuel,
+            max_memory: settings.max_memory,
+        };
+
+        let mut instance = plugin
+            .instantiate(0, &HashMap::new())
+            .map_err(|e| anyhow!("Failed to instantiate {:?}: {}", path, e))?;
+        let default_settings = instance
+            .settings_schema()
+            .map_err(|e| anyhow!("Invalid settings schema of {:?}: {}", path, e))?;
+
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Ok(PluginViz {
+            config: PluginVizConfig {
+                pretty_name: pretty_name(&stem),
+                name: format!("{}{}", NAME_PREFIX, stem),
+                path: path.to_path_buf(),
+                settings: default_settings,
+            },
+            total_pixels: 0,
+            plugin: Some(plugin),
+            instance: None,
+            last_error: None,
+            failures: 0,
+        })
+    }
+
+    /// Loads all plugin modules in the plugin directory.
+    ///
+    /// Modules that fail to compile or don't implement the plugin ABI are skipped.
+    /// No plugins are loaded if the directory doesn't exist.
+    ///
+    pub fn load_dir(settings: &PluginSettings) -> Vec<PluginViz> {
+        let mut paths = match fs::read_dir(&settings.dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension() == Some(OsStr::new(PLUGIN_EXTENSION)))
+                .collect::<Vec<PathBuf>>(),
+            Err(_) => return Vec::new(),
+        };
+        paths.sort();
+
+        paths
+            .iter()
+            .filter_map(|path| match PluginViz::new(path, settings) {
+                Ok(viz) => Some(viz),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Prints plugin errors unless the same error has been printed before.
+    fn report_error(&mut self, error: String) {
+        if self.last_error.as_ref() != Some(&error) {
+            eprintln!("{:?}: {}", self.config.path, error);
+            self.last_error = Some(error);
+        }
+    }
+
+    /// Reports a failed update and disables the plugin after `MAX_FAILURES` consecutive failures.
+    ///
+    /// Each failure can consume the maximum fuel of several calls, since the plugin is instantiated
+    /// and initialized again; disabling it keeps a broken plugin from slowing down every frame.
+    ///
+    fn report_failure(&mut self, error: String) {
+        self.report_error(error);
+        self.failures += 1;
+        if self.failures == MAX_FAILURES {
+            eprintln!(
+                "{:?}: disabled after {} consecutive failures",
+                self.config.path, MAX_FAILURES
+            );
+        }
+    }
+}
+
+impl Plugin {
+    /// Instantiates the module, passes the settings and initializes it with the number of pixels.
+    ///
+    /// Plugins can't import any host functions and their memory is limited.
+    ///
+    fn instantiate(
+        &self,
+        total_pixels: usize,
+        settings: &HashMap<String, String>,
+    ) -> anyhow::Result<PluginInstance> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.max_fuel).map_err(wasmi::Error::from)?;
+
+        let linker = Linker::<StoreLimits>::new(&self.engine);
+        let instance = linker
+            .instantiate(&mut store, &self.module)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| anyhow!("Plugin doesn't export memory"))?;
+        let init = instance.get_typed_func::<i32, ()>(&store, "init")?;
+        let update_settings = instance
+            .get_typed_func::<(i32, i32), ()>(&store, "update_settings")
+            .ok();
+
+        let mut plugin_instance = PluginInstance {
+            alloc: instance.get_typed_func::<i32, i32>(&store, "alloc")?,
+            update: instance.get_typed_func::<(i32, i32, f32), i32>(&store, "update")?,
+            settings_schema: instance
+                .get_typed_func::<(), i32>(&store, "settings_schema")
+                .ok(),
+            store,
+            memory,
+            bands: None,
+            max_fuel: self.max_fuel,
+        };
+
+        if let Some(update_settings) = update_settings {
+            let settings = serde_json::to_vec(settings)?;
+            let ptr = plugin_instance.write(&settings)?;
+            plugin_instance.refuel()?;
+            update_settings.call(&mut plugin_instance.store, (ptr, settings.len() as i32))?;
+        }
+
+        plugin_instance.refuel()?;
+        init.call(&mut plugin_instance.store, total_pixels as i32)?;
+        Ok(plugin_instance)
+    }
+}
+
+impl PluginInstance {
+    /// Passes the bands to the plugin and returns the memory of the pixels the plugin returns.
+    fn update(
+        &mut self,
+        bands: &[f32],
+        delta: f32,
+        total_pixels: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        // band memory only needs to be allocated if the number of bands changes
+        let bands_ptr = match self.bands {
+            Some((ptr, len)) if len == bands.len() => ptr,
+            _ => {
+                self.refuel()?;
+                let ptr = self.alloc.call(&mut self.store, (bands.len() * 4) as i32)?;
+                self.bands = Some((ptr, bands.len()));
+                ptr
+            }
+        };
+        let band_bytes = bands
+            .iter()
+            .flat_map(|b| b.to_le_bytes())
+            .collect::<Vec<u8>>();
+        self.memory
+            .write(&mut self.store, bands_ptr as u32 as usize, &band_bytes)
+            .map_err(wasmi::Error::from)?;
+
+        self.refuel()?;
+        let pixels_ptr = self
+            .update
+            .call(&mut self.store, (bands_ptr, bands.len() as i32, delta))?;
+
+        let mut pixels = vec![0; total_pixels * PIXEL_SIZE];
+        self.memory
+            .read(&self.store, pixels_ptr as u32 as usize, &mut pixels)
+            .map_err(wasmi::Error::from)?;
+        Ok(pixels)
+    }
+
+    /// Resets the fuel of the plugin before a call.
+    fn refuel(&mut self) -> Result<(), wasmi::Error> {
+        self.store.set_fuel(self.max_fuel)?;
+        Ok(())
+    }
+
+    /// Copies data to memory allocated by the plugin and returns a pointer to it.
+    fn write(&mut self, data: &[u8]) -> anyhow::Result<i32> {
+        self.refuel()?;
+        let ptr = self.alloc.call(&mut self.store, data.len() as i32)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, data)
+            .map_err(wasmi::Error::from)?;
+        Ok(ptr)
+    }
+
+    /// Returns the default settings of the optional `settings_schema` export.
+    ///
+    /// `settings_schema` returns a pointer to a null-terminated JSON object
+    /// mapping setting names to their default values.
+    ///
+    fn settings_schema(&mut self) -> anyhow::Result<HashMap<String, String>> {
+        let settings_schema = match self.settings_schema {
+            Some(settings_schema) => settings_schema,
+            None => return Ok(HashMap::new()),
+        };
+        self.refuel()?;
+        let ptr = settings_schema.call(&mut self.store, ())? as u32 as usize;
+
+        let data = self.memory.data(&self.store);
+        let schema = data
+            .get(ptr..)
+            .ok_or_else(|| anyhow!("Schema pointer out of bounds"))?;
+        let len = schema
+            .iter()
+            .take(MAX_SCHEMA_SIZE)
+            .position(|b| *b == 0)
+            .ok_or_else(|| anyhow!("Schema isn't null-terminated"))?;
+        let schema: HashMap<String, Value> = serde_json::from_slice(&schema[..len])?;
+
+        Ok(schema
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::String(s) => (key, s),
+                v => (key, v.to_string()),
+            })
+            .collect())
+    }
+}
+
+/// Converts a pixel returned by a plugin to a `PixelViz`.
+fn to_pixel_viz(pixel: &[u8], total_colors: usize) -> PixelViz {
+    let value = |i: usize| [pixel[i], pixel[i + 1], pixel[i + 2], pixel[i + 3]];
+    let float = |i: usize| f32::from_le_bytes(value(i));
+
+    PixelViz {
+        color_index: u32::from_le_bytes(value(0)) as usize % total_colors.max(1),
+        brightness: float(4).clamp(0.0, 1.0),
+        red_mul: float(8).max(0.0),
+        green_mul: float(12).max(0.0),
+        blue_mul: float(16).max(0.0),
+    }
+}
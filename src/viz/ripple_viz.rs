@@ -0,0 +1,294 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::features::AudioFeatures;
+use crate::theme::Color;
+use crate::viz::viz::finite_or;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// Distance in pixels from the wave front at which a wave no longer lights pixels
+const WAVE_WIDTH: f32 = 3.0;
+
+/// Wave speed used if the configured one isn't a positive number
+const DEFAULT_WAVE_SPEED: f32 = 30.0;
+
+/// Damping used if the configured one isn't a number
+const DEFAULT_DAMPING: f32 = 0.97;
+
+/// Amplitude below which waves are removed
+const MIN_AMPLITUDE: f32 = 0.01;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines where new waves are spawned.
+pub enum RipplePosition {
+    /// Random pixel of the strip
+    Random,
+
+    /// Pixel corresponding to the dominant band; low bands at the start, high bands at the end of the strip
+    Band,
+}
+
+impl Default for RipplePosition {
+    fn default() -> Self {
+        RipplePosition::Random
+    }
+}
+
+impl fmt::Display for RipplePosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RipplePosition::Random => write!(f, "random"),
+            RipplePosition::Band => write!(f, "band"),
+        }
+    }
+}
+
+impl FromStr for RipplePosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(RipplePosition::Random),
+            "band" => Ok(RipplePosition::Band),
+            _ => Err(format!("Unknown ripple position {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct RippleVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Number of pixels waves spread per second; `DEFAULT_WAVE_SPEED` is used if not a positive
+    /// number, so waves always leave the strip.
+    pub wave_speed: f32,
+
+    /// Factor of how much the amplitude of waves is reduced per frame at `REFERENCE_FPS` between
+    /// 0 and 1; `DEFAULT_DAMPING` is used if not a number.
+    pub damping: f32,
+
+    /// Maximum number of waves at the same time; the oldest wave is removed for new waves.
+    pub max_waves: usize,
+
+    /// Determines where new waves are spawned.
+    pub position: RipplePosition,
+}
+
+impl RippleVizConfig {
+    /// Convert settings in map of strings to visualization config.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        settings.insert("wave_speed".to_string(), self.wave_speed.to_string());
+        settings.insert("damping".to_string(), self.damping.to_string());
+        settings.insert("max_waves".to_string(), self.max_waves.to_string());
+        settings.insert("position".to_string(), self.position.to_string());
+        settings
+    }
+
+    /// Create visualization config from map of strings.
+    pub fn from_map(name: String, settings: HashMap<String, String>) -> Self {
+        Self {
+            pretty_name: name,
+            wave_speed: settings
+                .get(&"wave_speed".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            damping: settings
+                .get(&"damping".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            max_waves: settings
+                .get(&"max_waves".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<usize>()
+                .unwrap(),
+            position: settings
+                .get(&"position".to_string())
+                .unwrap_or(&"random".to_string())
+                .parse::<RipplePosition>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+/// State of a single wave spreading from its origin in both directions.
+struct Wave {
+    /// Pixel the wave has been spawned at.
+    origin: f32,
+
+    /// Distance in pixels the wave front has spread from its origin.
+    radius: f32,
+
+    /// Brightness of the wave front between 0 and 1.
+    amplitude: f32,
+
+    /// Index of the theme color of the wave.
+    color_index: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+/// Visualization spawning a wave on each detected onset. Waves spread outward and fade;
+/// overlapping waves add up their brightness and blend their colors.
+pub struct RippleViz {
+    /// Visualization config.
+    pub config: RippleVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    /// Waves from the oldest to the newest.
+    waves: Vec<Wave>,
+}
+
+#[typetag::serde]
+impl Viz for RippleViz {
+    fn get_name(&self) -> &str {
+        "ripple_viz"
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        // spread and damp existing waves; damping is independent of the update rate
+        let damping_factor = self.damping().powf(time.frames());
+        let wave_speed = self.wave_speed();
+        let max_radius = self.total_pixels as f32 + WAVE_WIDTH;
+        for wave in self.waves.iter_mut() {
+            wave.radius += wave_speed * time.delta;
+            wave.amplitude *= damping_factor;
+        }
+        self.waves
+            .retain(|w| w.amplitude >= MIN_AMPLITUDE && w.radius <= max_radius);
+
+        if input.onset && self.total_pixels > 0 && self.config.max_waves > 0 {
+            if self.waves.len() >= self.config.max_waves {
+                let excess = self.waves.len() + 1 - self.config.max_waves;
+                self.waves.drain(..excess);
+            }
+            self.waves.push(self.spawn_wave(input));
+        }
+
+        (0..self.total_pixels)
+            .map(|i| self.pixel(i as f32, colors))
+            .collect()
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+        self.waves.clear();
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.to_map()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        let new_settings = RippleVizConfig::from_map(self.get_pretty_name().to_string(), settings);
+        self.config = new_settings;
+        self.waves.clear();
+    }
+}
+
+unsafe impl Send for RippleViz {}
+unsafe impl Sync for RippleViz {}
+
+impl RippleViz {
+    pub fn new(config: RippleVizConfig) -> Self {
+        RippleViz {
+            config,
+            total_pixels: 0,
+            waves: Vec::new(),
+        }
+    }
+
+    /// Returns the number of pixels waves spread per second.
+    fn wave_speed(&self) -> f32 {
+        if self.config.wave_speed.is_finite() && self.config.wave_speed > 0.0 {
+            self.config.wave_speed
+        } else {
+            DEFAULT_WAVE_SPEED
+        }
+    }
+
+    /// Returns the factor of how much the amplitude of waves is reduced per frame.
+    fn damping(&self) -> f32 {
+        finite_or(self.config.damping, DEFAULT_DAMPING).clamp(0.0, 1.0)
+    }
+
+    /// Creates a new wave colored by the dominant band with the magnitude of the dominant band
+    /// as amplitude.
+    fn spawn_wave(&self, input: &AudioFeatures) -> Wave {
+        let dominant_band = input.dominant_band();
+        let magnitude = input.bands.get(dominant_band).copied().unwrap_or(0.0);
+        let origin = match self.config.position {
+            RipplePosition::Random => rand::thread_rng().gen_range(0..self.total_pixels),
+            RipplePosition::Band => {
+                let total_bands = input.bands.len().max(1);
+                (2 * dominant_band + 1) * self.total_pixels / (2 * total_bands)
+            }
+        };
+
+        Wave {
+            origin: origin as f32,
+            radius: 0.0,
+            amplitude: (magnitude / 100.0).clamp(0.0, 1.0),
+            color_index: dominant_band,
+        }
+    }
+
+    /// Returns the sum of all waves at the provided pixel.
+    ///
+    /// Brightness is the sum of the wave brightnesses; the color is the average of the wave
+    /// colors weighted by their brightness.
+    ///
+    fn pixel(&self, position: f32, colors: &[Color]) -> PixelViz {
+        let mut brightness = 0.0;
+        let mut rgb = [0.0_f32; 3];
+        for wave in &self.waves {
+            let distance = ((position - wave.origin).abs() - wave.radius).abs();
+            let wave_brightness = wave.amplitude * (1.0 - distance / WAVE_WIDTH).max(0.0);
+            if wave_brightness <= 0.0 || colors.is_empty() {
+                continue;
+            }
+
+            let color = colors[wave.color_index % colors.len()];
+            rgb[0] += color.r as f32 * wave_brightness;
+            rgb[1] += color.g as f32 * wave_brightness;
+            rgb[2] += color.b as f32 * wave_brightness;
+            brightness += wave_brightness;
+        }
+
+        if brightness <= 0.0 {
+            let mut pixel = PixelViz::default();
+            pixel.off();
+            return pixel;
+        }
+
+        let color = Color {
+            r: (rgb[0] / brightness).round() as u8,
+            g: (rgb[1] / brightness).round() as u8,
+            b: (rgb[2] / brightness).round() as u8,
+        };
+        let mut pixel = PixelViz::with_color(color, colors);
+        pixel.brightness = brightness.min(1.0);
+        pixel
+    }
+}
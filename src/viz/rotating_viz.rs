@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 
+use crate::features::AudioFeatures;
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
-use chrono::prelude::*;
 
 use rand::Rng;
 use std::collections::HashMap;
@@ -17,7 +18,7 @@ pub struct RotatingVizConfig {
     /// Number of pixels pixels are moved per second.
     pub speed: f32,
 
-    /// Factor of how much brightness is reduced.
+    /// Factor of how much brightness is reduced per frame at `REFERENCE_FPS`.
     pub falloff: f32,
 }
 
@@ -58,8 +59,8 @@ pub struct RotatingViz {
     /// Total number of pixels.
     total_pixels: usize,
 
-    /// Elapsed time for each pixel until it blends to different color.
-    elapsed_time: DateTime<Utc>,
+    /// Distance in pixels moved since the last rotation.
+    offset: f32,
 
     /// Color of each pixel.
     pixels: Vec<Option<PixelViz>>,
@@ -78,37 +79,30 @@ impl Viz for RotatingViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, input: &Vec<f32>, _colors: &Vec<Color>) -> Vec<PixelViz> {
-        let total_bands = input.len();
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        _colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let bands = &input.bands;
+        let total_bands = bands.len();
         let mut rng = rand::thread_rng();
 
-        let now = Utc::now();
-        let elapsed: f32 = (now - self.elapsed_time).num_milliseconds() as f32;
-
-        // rotate pixels
-        if self.pixels.len() > 0 {
-            let first = self.pixels[0].clone();
-            let first_falloff = self.falloffs[0];
-
-            for pixel_index in 1..self.total_pixels {
-                if elapsed > 1000.0 / self.config.speed {
-                    // rotate one position
-                    self.pixels[pixel_index - 1] = self.pixels[pixel_index].clone();
-                    self.falloffs[pixel_index - 1] = self.falloffs[pixel_index];
-
-                    if pixel_index == self.total_pixels - 1 {
-                        self.pixels[pixel_index] = first.clone();
-                        self.falloffs[pixel_index] = first_falloff;
-                    }
-                    self.elapsed_time = now;
-                }
-            }
+        // rotate pixels by the full positions moved since the last update
+        self.offset += self.config.speed * time.delta;
+        if !self.pixels.is_empty() && self.offset >= 1.0 {
+            let positions = self.offset.floor();
+            self.offset -= positions;
+            let positions = positions as usize % self.pixels.len();
+            self.pixels.rotate_left(positions);
+            self.falloffs.rotate_left(positions);
         }
 
         for band in 0..total_bands {
             // renew active pixels
             let active_pixels =
-                ((input[band] / 100.0) * (self.total_pixels as f32 / total_bands as f32)) as i64;
+                ((bands[band] / 100.0) * (self.total_pixels as f32 / total_bands as f32)) as i64;
             let prev_active_pixels = self
                 .pixels
                 .iter()
@@ -204,17 +198,17 @@ impl Viz for RotatingViz {
             for p in self.pixels.iter_mut() {
                 if let Some(ref mut pixel) = p {
                     if pixel.color_index == band {
-                        pixel.brightness = input[band] as f32 / 100.0;
+                        pixel.brightness = bands[band] as f32 / 100.0;
                     }
                 }
             }
         }
 
-        // apply falloffs
+        // apply falloffs; falloffs count the frames at `REFERENCE_FPS` since pixels got turned off
         for (i, falloff) in self.falloffs.iter_mut().enumerate() {
             if let Some(ref mut pixel) = self.pixels[i] {
                 pixel.brightness *= 1.0 - (*falloff * self.config.falloff).min(1.0);
-                *falloff += 1.0;
+                *falloff += time.frames();
             }
 
             if *falloff >= 10.0 {
@@ -271,7 +265,7 @@ impl RotatingViz {
         RotatingViz {
             config,
             total_pixels: 0,
-            elapsed_time: Utc::now(),
+            offset: 0.0,
             falloffs: Vec::new(),
             pixels: Vec::new(),
         }
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::viz::{Clock, FrameTime, FrameTimer};
+
+/// Number of past frames frame rate and frame time statistics are based on
+const FRAME_HISTORY_LEN: usize = 120;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+/// Frame rate statistics of the visualization runner.
+pub struct FrameStats {
+    /// Target number of frames per second
+    pub target_fps: f32,
+
+    /// Achieved number of frames per second over the recent frames
+    pub fps: f32,
+
+    /// Median time in milliseconds needed to compute and show a frame
+    pub frame_time_p50: f32,
+
+    /// 95th percentile of the frame time in milliseconds
+    pub frame_time_p95: f32,
+
+    /// 99th percentile of the frame time in milliseconds
+    pub frame_time_p99: f32,
+
+    /// Number of frames skipped since frames took longer than the target frame duration
+    pub dropped_frames: u64,
+
+    /// Number of frames shown
+    pub total_frames: u64,
+}
+
+/// Paces visualization updates to a target frame rate.
+///
+/// Frames are scheduled at fixed deadlines, so time spent computing a frame is not added
+/// to the frame duration. If a frame takes so long that deadlines are missed, the missed
+/// frames are counted as dropped and the schedule restarts from the current time.
+pub struct FrameScheduler {
+    /// Clock used for sleeping and measuring frame times
+    clock: Arc<dyn Clock>,
+
+    /// Determines the frame time passed to visualizations
+    frame_timer: FrameTimer,
+
+    /// Target number of frames per second
+    target_fps: f32,
+
+    /// Time between frames
+    frame_duration: Duration,
+
+    /// Time the next frame is due
+    next_frame: Option<Duration>,
+
+    /// Time the current frame started
+    frame_start: Duration,
+
+    /// Time needed to compute and show the recent frames
+    frame_times: VecDeque<Duration>,
+
+    /// Time between the start of the recent frames
+    frame_intervals: VecDeque<Duration>,
+
+    /// Number of dropped frames
+    dropped_frames: u64,
+
+    /// Number of frames shown
+    total_frames: u64,
+}
+
+impl FrameScheduler {
+    pub fn new(target_fps: f32, clock: Arc<dyn Clock>) -> Self {
+        FrameScheduler {
+            clock: Arc::clone(&clock),
+            frame_timer: FrameTimer::new(clock),
+            target_fps,
+            frame_duration: Duration::from_secs_f32(1.0 / target_fps),
+            next_frame: None,
+            frame_start: Duration::ZERO,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+            frame_intervals: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+            dropped_frames: 0,
+            total_frames: 0,
+        }
+    }
+
+    /// Blocks until the next frame is due and returns its frame time.
+    pub fn wait(&mut self) -> FrameTime {
+        let now = self.clock.now();
+        let next_frame = *self.next_frame.get_or_insert(now);
+
+        let start = if now >= next_frame + self.frame_duration {
+            // at least one deadline has been missed
+            let missed = (now - next_frame).as_secs_f64() / self.frame_duration.as_secs_f64();
+            self.dropped_frames += missed as u64;
+            now
+        } else {
+            self.clock.sleep_until(next_frame);
+            next_frame
+        };
+        self.next_frame = Some(start + self.frame_duration);
+
+        let time = self.frame_timer.tick();
+        if self.total_frames > 0 {
+            Self::record(
+                &mut self.frame_intervals,
+                time.now.saturating_sub(self.frame_start),
+            );
+        }
+        self.frame_start = time.now;
+        time
+    }
+
+    /// Marks the current frame as shown.
+    pub fn end_frame(&mut self) {
+        let frame_time = self.clock.now().saturating_sub(self.frame_start);
+        Self::record(&mut self.frame_times, frame_time);
+        self.total_frames += 1;
+    }
+
+    /// Returns the frame rate statistics of the recent frames.
+    pub fn stats(&self) -> FrameStats {
+        let total_interval: Duration = self.frame_intervals.iter().sum();
+        let fps = if total_interval > Duration::ZERO {
+            self.frame_intervals.len() as f32 / total_interval.as_secs_f32()
+        } else {
+            0.0
+        };
+
+        let mut frame_times: Vec<f32> = self
+            .frame_times
+            .iter()
+            .map(|t| t.as_secs_f32() * 1000.0)
+            .collect();
+        frame_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        FrameStats {
+            target_fps: self.target_fps,
+            fps,
+            frame_time_p50: Self::percentile(&frame_times, 0.5),
+            frame_time_p95: Self::percentile(&frame_times, 0.95),
+            frame_time_p99: Self::percentile(&frame_times, 0.99),
+            dropped_frames: self.dropped_frames,
+            total_frames: self.total_frames,
+        }
+    }
+
+    /// Adds a value to a history and removes the oldest value if the history is full.
+    fn record(history: &mut VecDeque<Duration>, value: Duration) {
+        if history.len() == FRAME_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(value);
+    }
+
+    /// Returns the nearest-rank percentile of sorted values; 0 if there are no values.
+    fn percentile(sorted: &[f32], percentile: f32) -> f32 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        let rank = (percentile * sorted.len() as f32).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
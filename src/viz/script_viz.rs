@@ -0,0 +1,474 @@
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use crate::features::AudioFeatures;
+use crate::settings::ScriptSettings;
+use crate::theme::Color;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// File extension of visualization scripts
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Prefix of the identifiers of script visualizations; avoids clashes with built-in visualizations
+const NAME_PREFIX: &str = "script_";
+
+/// Interval in seconds in which script files are checked for changes
+const RELOAD_INTERVAL: f32 = 1.0;
+
+/// Name of the script function returning the default settings
+const DEFAULT_SETTINGS_FN: &str = "default_settings";
+
+/// Maximum number of elements of script arrays; needs to fit the pixels of linked outputs
+const MAX_ARRAY_SIZE: usize = 4096;
+
+/// Maximum number of messages a script can print per second; further messages are dropped
+const MAX_PRINTS_PER_SECOND: u32 = 10;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct ScriptVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Unique identifier of the visualization.
+    pub name: String,
+
+    /// Path of the script file.
+    pub path: PathBuf,
+
+    /// Settings passed to the script.
+    pub settings: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+/// Visualization running a user-defined Rhai script.
+///
+/// The script is evaluated for each update and returns an array with a map for each pixel.
+/// Scripts are checked for changes periodically and reloaded if they have been modified.
+pub struct ScriptViz {
+    /// Visualization config.
+    pub config: ScriptVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    #[serde(skip, default = "default_engine")]
+    /// Sandboxed script engine; shared between the visualizations of all outputs.
+    engine: Arc<Engine>,
+
+    #[serde(skip)]
+    /// Compiled script.
+    ast: AST,
+
+    #[serde(skip)]
+    /// State kept by the script between updates.
+    state: Map,
+
+    #[serde(skip)]
+    /// Modification time of the script file when it has been compiled.
+    modified: Option<SystemTime>,
+
+    /// Elapsed time in seconds since the script file has been checked for changes.
+    elapsed: f32,
+
+    /// Last error of the script; used to report each error only once.
+    last_error: Option<String>,
+}
+
+#[typetag::serde]
+impl Viz for ScriptViz {
+    fn get_name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        self.elapsed += time.delta;
+        if self.elapsed > RELOAD_INTERVAL {
+            self.elapsed = 0.0;
+            self.reload();
+        }
+
+        let mut scope = Scope::new();
+        scope.push_constant(
+            "bands",
+            input
+                .bands
+                .iter()
+                .map(|b| Dynamic::from(*b as f64))
+                .collect::<Array>(),
+        );
+        scope.push_constant("energy", input.energy() as f64);
+        scope.push_constant("onset", input.onset);
+        scope.push_constant("time", time.now.as_secs_f64());
+        scope.push_constant("delta", time.delta as f64);
+        scope.push_constant("pixels", self.total_pixels as i64);
+        scope.push_constant("colors", colors.len() as i64);
+        scope.push_constant("settings", self.settings_map());
+        scope.push("state", std::mem::take(&mut self.state));
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Array>(&mut scope, &self.ast);
+        self.state = scope.get_value::<Map>("state").unwrap_or_default();
+
+        let mut pixels = match result {
+            Ok(script_pixels) => {
+                self.last_error = None;
+                script_pixels
+                    .into_iter()
+                    .take(self.total_pixels)
+                    .map(|p| to_pixel_viz(p, colors.len()))
+                    .collect::<Vec<PixelViz>>()
+            }
+            Err(e) => {
+                self.report_error(e.to_string());
+                Vec::new()
+            }
+        };
+
+        // pixels the script doesn't return are turned off
+        let mut off = PixelViz::default();
+        off.off();
+        pixels.resize(self.total_pixels, off);
+        pixels
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.settings.clone()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        self.config.settings = settings;
+        self.state = Map::new();
+    }
+}
+
+// the rhai engine, scripts and values are thread-safe with the `sync` feature; fails to compile
+// if that changes
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ScriptViz>();
+};
+
+impl ScriptViz {
+    /// Compiles the script at the provided path.
+    ///
+    /// Default settings are returned by the `default_settings` function of the script, if it is defined.
+    ///
+    pub fn new(path: &Path, settings: &ScriptSettings) -> anyhow::Result<Self> {
+        let engine = Arc::new(script_engine(settings, path));
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow::anyhow!("Failed to compile {:?}: {}", path, e))?;
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut viz = ScriptViz {
+            config: ScriptVizConfig {
+                pretty_name: pretty_name(&stem),
+                name: script_name(path),
+                path: path.to_path_buf(),
+                settings: HashMap::new(),
+            },
+            total_pixels: 0,
+            engine,
+            ast,
+            state: Map::new(),
+            modified: modified(path),
+            elapsed: 0.0,
+            last_error: None,
+        };
+        viz.config.settings = viz.default_settings();
+        Ok(viz)
+    }
+
+    /// Loads the script with the provided visualization identifier from the script directory.
+    ///
+    /// Returns `None` if the identifier doesn't belong to a script, the script file doesn't exist
+    /// or fails to compile.
+    ///
+    pub fn load(name: &str, settings: &ScriptSettings) -> Option<ScriptViz> {
+        let path = ScriptViz::path(name, settings)?;
+        if !path.is_file() {
+            return None;
+        }
+
+        ScriptViz::new(&path, settings)
+            .map_err(|e| eprintln!("{}", e))
+            .ok()
+    }
+
+    /// Returns the path of the script file with the provided visualization identifier.
+    ///
+    /// Returns `None` if the identifier doesn't belong to a script; the file might not exist.
+    ///
+    pub fn path(name: &str, settings: &ScriptSettings) -> Option<PathBuf> {
+        let stem = name.strip_prefix(NAME_PREFIX)?;
+        Some(Path::new(&settings.dir).join(format!("{}.{}", stem, SCRIPT_EXTENSION)))
+    }
+
+    /// Loads all scripts in the script directory.
+    ///
+    /// Scripts that fail to compile are skipped. No scripts are loaded if the directory doesn't exist.
+    ///
+    pub fn load_dir(settings: &ScriptSettings) -> Vec<ScriptViz> {
+        ScriptViz::load_new(settings, &[])
+    }
+
+    /// Loads the scripts in the script directory except the ones with the provided identifiers.
+    ///
+    /// Only new scripts are compiled, so the directory can be rescanned cheaply.
+    ///
+    pub fn load_new(settings: &ScriptSettings, loaded: &[String]) -> Vec<ScriptViz> {
+        let mut paths = match fs::read_dir(&settings.dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension() == Some(OsStr::new(SCRIPT_EXTENSION)))
+                .filter(|p| !loaded.contains(&script_name(p)))
+                .collect::<Vec<PathBuf>>(),
+            Err(_) => return Vec::new(),
+        };
+        paths.sort();
+
+        paths
+            .iter()
+            .filter_map(|path| match ScriptViz::new(path, settings) {
+                Ok(viz) => Some(viz),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Recompiles the script if the script file has been modified.
+    ///
+    /// The previous script keeps running if the modified script fails to compile.
+    ///
+    fn reload(&mut self) {
+        let modified = modified(&self.config.path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        match self.engine.compile_file(self.config.path.clone()) {
+            Ok(ast) => {
+                self.ast = ast;
+                self.state = Map::new();
+                self.last_error = None;
+
+                // keep current settings; add settings the modified script introduces
+                for (key, value) in self.default_settings() {
+                    self.config.settings.entry(key).or_insert(value);
+                }
+            }
+            Err(e) => self.report_error(e.to_string()),
+        }
+    }
+
+    /// Returns the settings returned by the `default_settings` function of the script.
+    fn default_settings(&self) -> HashMap<String, String> {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|f| f.name == DEFAULT_SETTINGS_FN)
+        {
+            return HashMap::new();
+        }
+
+        // only call the function; the rest of the script expects the variables of an update
+        let options = CallFnOptions::new().eval_ast(false);
+        match self.engine.call_fn_with_options::<Map>(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            DEFAULT_SETTINGS_FN,
+            (),
+        ) {
+            Ok(settings) => settings
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            Err(e) => {
+                eprintln!("{:?}: {}", self.config.path, e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Converts the settings to a script map; numeric and boolean values are converted.
+    fn settings_map(&self) -> Map {
+        self.config
+            .settings
+            .iter()
+            .map(|(key, value)| {
+                let value = if let Ok(v) = value.parse::<f64>() {
+                    Dynamic::from(v)
+                } else if let Ok(v) = value.parse::<bool>() {
+                    Dynamic::from(v)
+                } else {
+                    Dynamic::from(value.clone())
+                };
+                (key.as_str().into(), value)
+            })
+            .collect()
+    }
+
+    /// Prints script errors unless the same error has been printed before.
+    fn report_error(&mut self, error: String) {
+        if self.last_error.as_ref() != Some(&error) {
+            eprintln!("{:?}: {}", self.config.path, error);
+            self.last_error = Some(error);
+        }
+    }
+}
+
+/// Prints the messages of a script to stderr, at most `MAX_PRINTS_PER_SECOND` per second.
+struct ScriptLog {
+    /// Path of the script file
+    path: PathBuf,
+
+    /// Start of the current second and the number of messages printed in it
+    window: Mutex<(Instant, u32)>,
+}
+
+impl ScriptLog {
+    fn new(path: &Path) -> Self {
+        ScriptLog {
+            path: path.to_path_buf(),
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Prints the message unless the script has printed too many messages in the current second.
+    fn print(&self, message: &str) {
+        let mut window = self.window.lock().unwrap();
+        if window.0.elapsed().as_secs_f32() >= 1.0 {
+            *window = (Instant::now(), 0);
+        }
+        window.1 += 1;
+
+        if window.1 <= MAX_PRINTS_PER_SECOND {
+            eprintln!("{:?}: {}", self.path, message);
+        } else if window.1 == MAX_PRINTS_PER_SECOND + 1 {
+            eprintln!("{:?}: too many messages, dropping them", self.path);
+        }
+    }
+}
+
+/// Creates a script engine without access to modules and with limits on the script execution.
+///
+/// `print` and `debug` of the script at `path` are printed to stderr with a rate limit.
+///
+fn script_engine(settings: &ScriptSettings, path: &Path) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    let log = Arc::new(ScriptLog::new(path));
+    let debug_log = log.clone();
+    engine.on_print(move |text| log.print(text));
+    engine.on_debug(move |text, _, pos| debug_log.print(&format!("{} {}", pos, text)));
+    engine.set_max_operations(settings.max_operations);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1024);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(1024);
+    engine
+}
+
+/// Engine of deserialized script visualizations.
+fn default_engine() -> Arc<Engine> {
+    Arc::new(script_engine(&ScriptSettings::default(), Path::new("")))
+}
+
+/// Returns the visualization identifier of the script at the provided path.
+fn script_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}{}", NAME_PREFIX, stem)
+}
+
+/// Returns the modification time of a file.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Converts a file name like `pulse_wave` to a screen friendly name like `Pulse Wave`.
+pub(super) fn pretty_name(stem: &str) -> String {
+    stem.split(['_', '-'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Converts a pixel returned by a script to a `PixelViz`.
+///
+/// Pixels are maps with the optional keys `color`, `brightness`, `red`, `green` and `blue`;
+/// other values turn the pixel off.
+///
+fn to_pixel_viz(pixel: Dynamic, total_colors: usize) -> PixelViz {
+    let mut pixel_viz = PixelViz::default();
+    let map = match pixel.try_cast::<Map>() {
+        Some(map) => map,
+        None => {
+            pixel_viz.off();
+            return pixel_viz;
+        }
+    };
+
+    let float = |key: &str, default: f32| {
+        map.get(key)
+            .and_then(|v| {
+                v.as_float()
+                    .ok()
+                    .or_else(|| v.as_int().ok().map(|i| i as f64))
+            })
+            .map(|v| v as f32)
+            .unwrap_or(default)
+    };
+
+    if let Some(color) = map.get("color").and_then(|v| v.as_int().ok()) {
+        pixel_viz.color_index = color.rem_euclid(total_colors.max(1) as i64) as usize;
+    }
+    pixel_viz.brightness = float("brightness", 1.0).clamp(0.0, 1.0);
+    pixel_viz.red_mul = float("red", 1.0).max(0.0);
+    pixel_viz.green_mul = float("green", 1.0).max(0.0);
+    pixel_viz.blue_mul = float("blue", 1.0).max(0.0);
+    pixel_viz
+}
@@ -1,7 +1,8 @@
+use crate::features::{AudioFeatures, BandSource};
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
-use chrono::prelude::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -17,6 +18,10 @@ pub struct SolidBeatVizConfig {
 
     /// Speed of fading the pixels to a different color.
     pub fade_duration: i64,
+
+    #[serde(default)]
+    /// Band magnitudes brightness is based on.
+    pub band_source: BandSource,
 }
 
 impl SolidBeatVizConfig {
@@ -25,6 +30,7 @@ impl SolidBeatVizConfig {
         let mut settings = HashMap::new();
         settings.insert("fade_colors".to_string(), self.fade_colors.to_string());
         settings.insert("fade_duration".to_string(), self.fade_duration.to_string());
+        settings.insert("band_source".to_string(), self.band_source.to_string());
         settings
     }
 
@@ -42,6 +48,11 @@ impl SolidBeatVizConfig {
                 .unwrap_or(&"0".to_string())
                 .parse::<i64>()
                 .unwrap(),
+            band_source: settings
+                .get(&"band_source".to_string())
+                .unwrap_or(&"all".to_string())
+                .parse::<BandSource>()
+                .unwrap(),
         }
     }
 }
@@ -57,8 +68,8 @@ pub struct SolidBeatViz {
     /// Total number of pixels.
     total_pixels: usize,
 
-    /// Time since the last fade happened.
-    elapsed_time: DateTime<Utc>,
+    /// Time since the last fade happened in seconds.
+    elapsed: f32,
 
     /// Current color pixels are displayed in.
     color_index: usize,
@@ -74,29 +85,30 @@ impl Viz for SolidBeatViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, input: &Vec<f32>, colors: &Vec<Color>) -> Vec<PixelViz> {
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
         // determine the total frequency magnitude and compute brightness based on it
-        let total_bands = input.len();
-        let max_magnitude = 100.0 * total_bands as f32;
-        let magnitude: f32 = input.iter().sum();
         let mut viz = PixelViz::default();
-        viz.brightness = magnitude / max_magnitude;
+        viz.brightness = input.source_energy(self.config.band_source);
         viz.color_index = self.color_index;
 
         if self.config.fade_colors {
             // if colors are configured to be fading then wait until fade threshold is reached
             let next_color_index = (self.color_index + 1) % colors.len();
-            let now = Utc::now();
-            let elapsed = (now - self.elapsed_time).num_seconds();
+            self.elapsed += time.delta;
 
-            if elapsed > self.config.fade_duration {
+            if self.elapsed > self.config.fade_duration as f32 {
                 self.color_index = next_color_index;
-                self.elapsed_time = now;
+                self.elapsed = 0.0;
             } else {
                 // fade
                 let current_color = colors[self.color_index % colors.len()];
                 let next_color = colors[next_color_index % colors.len()];
-                let elapsed_perc: f32 = elapsed as f32 / self.config.fade_duration as f32;
+                let elapsed_perc: f32 = self.elapsed / self.config.fade_duration as f32;
                 viz.red_mul =
                     (((next_color.r as f32 / current_color.r as f32) - 1.0) * elapsed_perc) + 1.0;
                 viz.green_mul =
@@ -132,7 +144,7 @@ impl SolidBeatViz {
         SolidBeatViz {
             config,
             total_pixels: 0,
-            elapsed_time: Utc::now(),
+            elapsed: 0.0,
             color_index: 0,
         }
     }
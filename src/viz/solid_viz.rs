@@ -1,4 +1,6 @@
+use crate::features::AudioFeatures;
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
 use serde::{Deserialize, Serialize};
@@ -43,7 +45,12 @@ impl Viz for SolidViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, _input: &Vec<f32>, _colors: &Vec<Color>) -> Vec<PixelViz> {
+    fn update(
+        &mut self,
+        _input: &AudioFeatures,
+        _colors: &Vec<Color>,
+        _time: &FrameTime,
+    ) -> Vec<PixelViz> {
         vec![PixelViz::default(); self.total_pixels]
     }
 
@@ -1,11 +1,11 @@
-use chrono::prelude::*;
-
 use rand::seq::SliceRandom;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::features::AudioFeatures;
 use crate::theme::Color;
+use crate::viz::FrameTime;
 use crate::viz::PixelViz;
 use crate::viz::Viz;
 
@@ -17,7 +17,7 @@ pub struct SparkleVizConfig {
     /// Determines how frequently pixels should be ignited.
     pub speed: f32,
 
-    /// Factor of how much brightness is reduced.
+    /// Factor of how much brightness is reduced per frame at `REFERENCE_FPS`.
     pub falloff: f32,
 
     /// Maximum number of pixels ignited at the same time representing the same frequency.
@@ -70,8 +70,8 @@ pub struct SparkleViz {
     /// Falloff factors applied to each pixel.
     falloffs: Vec<f32>,
 
-    /// Elapsed time since last time pixels got ignited.
-    elapsed_time: DateTime<Utc>,
+    /// Elapsed time in seconds since last time pixels got ignited.
+    elapsed: f32,
 
     /// Pixel colors.
     pixels: Vec<Option<PixelViz>>,
@@ -87,18 +87,23 @@ impl Viz for SparkleViz {
         &self.config.pretty_name
     }
 
-    fn update(&mut self, input: &Vec<f32>, _colors: &Vec<Color>) -> Vec<PixelViz> {
-        let total_bands = input.len();
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        _colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let bands = &input.bands;
+        let total_bands = bands.len();
         let _rng = rand::thread_rng();
 
-        let now = Utc::now();
-        let elapsed: f32 = (now - self.elapsed_time).num_milliseconds() as f32;
+        self.elapsed += time.delta;
 
-        if elapsed > 1000.0 / self.config.speed {
-            self.elapsed_time = now;
+        if self.elapsed > 1.0 / self.config.speed {
+            self.elapsed = 0.0;
             for band in 0..total_bands {
                 // total number of pixels to ignite
-                let total_ignite = ((input[band] / 100.0) * self.config.max_ignite) as usize;
+                let total_ignite = ((bands[band] / 100.0) * self.config.max_ignite) as usize;
 
                 // pixels that are currently off
                 let off_pixels = self
@@ -121,18 +126,19 @@ impl Viz for SparkleViz {
                         red_mul: 1.0,
                         green_mul: 1.0,
                         blue_mul: 1.0,
-                        brightness: input[band] / 100.0,
+                        brightness: bands[band] / 100.0,
                     });
                     self.falloffs[pixel] = 1.0;
                 }
             }
         }
 
-        // apply falloffs
+        // apply falloffs; brightness decreases by the same amount independent of the update rate
+        let falloff_factor = self.config.falloff.powf(time.frames());
         for (i, falloff) in self.falloffs.iter_mut().enumerate() {
             if let Some(ref mut pixel) = self.pixels[i] {
                 if *falloff >= 1.0 {
-                    pixel.brightness *= self.config.falloff;
+                    pixel.brightness *= falloff_factor;
                 }
                 if pixel.brightness <= 0.1 {
                     self.pixels[i] = None;
@@ -188,7 +194,7 @@ impl SparkleViz {
         SparkleViz {
             config,
             total_pixels: 0,
-            elapsed_time: Utc::now(),
+            elapsed: 0.0,
             falloffs: Vec::new(),
             pixels: Vec::new(),
         }
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::features::AudioFeatures;
+use crate::theme::Color;
+use crate::viz::viz::finite_or;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// Peak hold time in seconds used if the configured one isn't a number
+const DEFAULT_PEAK_HOLD: f32 = 0.5;
+
+/// Peak fall speed used if the configured one isn't a number
+const DEFAULT_PEAK_FALL_SPEED: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines in which direction the segments of the spectrum light up.
+pub enum SpectrumOrientation {
+    /// Segments light up from their start to their end
+    BottomUp,
+
+    /// Segments light up from their center to both ends
+    CenterOut,
+}
+
+impl Default for SpectrumOrientation {
+    fn default() -> Self {
+        SpectrumOrientation::BottomUp
+    }
+}
+
+impl fmt::Display for SpectrumOrientation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpectrumOrientation::BottomUp => write!(f, "bottom_up"),
+            SpectrumOrientation::CenterOut => write!(f, "center_out"),
+        }
+    }
+}
+
+impl FromStr for SpectrumOrientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bottom_up" => Ok(SpectrumOrientation::BottomUp),
+            "center_out" => Ok(SpectrumOrientation::CenterOut),
+            _ => Err(format!("Unknown spectrum orientation {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct SpectrumVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Number of segments the strip is divided into; neighboring bands are merged if there are
+    /// less segments than bands. Each band gets a segment if 0.
+    pub bands_per_strip: usize,
+
+    /// Determines in which direction segments light up.
+    pub orientation: SpectrumOrientation,
+
+    /// Whether pixels are colored by their position in the segment instead of by their band.
+    pub gradient: bool,
+
+    /// Time in seconds peaks are held before they start falling; at least 0,
+    /// `DEFAULT_PEAK_HOLD` is used if not a number.
+    pub peak_hold: f32,
+
+    /// Fraction of the segment length peaks fall per second; at least 0,
+    /// `DEFAULT_PEAK_FALL_SPEED` is used if not a number.
+    pub peak_fall_speed: f32,
+}
+
+impl SpectrumVizConfig {
+    /// Convert settings in map of strings to visualization config.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        settings.insert(
+            "bands_per_strip".to_string(),
+            self.bands_per_strip.to_string(),
+        );
+        settings.insert("orientation".to_string(), self.orientation.to_string());
+        settings.insert("gradient".to_string(), self.gradient.to_string());
+        settings.insert("peak_hold".to_string(), self.peak_hold.to_string());
+        settings.insert(
+            "peak_fall_speed".to_string(),
+            self.peak_fall_speed.to_string(),
+        );
+        settings
+    }
+
+    /// Create visualization config from map of strings.
+    pub fn from_map(name: String, settings: HashMap<String, String>) -> Self {
+        Self {
+            pretty_name: name,
+            bands_per_strip: settings
+                .get(&"bands_per_strip".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<usize>()
+                .unwrap(),
+            orientation: settings
+                .get(&"orientation".to_string())
+                .unwrap_or(&"bottom_up".to_string())
+                .parse::<SpectrumOrientation>()
+                .unwrap(),
+            gradient: settings
+                .get(&"gradient".to_string())
+                .unwrap_or(&"false".to_string())
+                .parse::<bool>()
+                .unwrap(),
+            peak_hold: settings
+                .get(&"peak_hold".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            peak_fall_speed: settings
+                .get(&"peak_fall_speed".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+/// Visualization dividing the strip into segments that light up proportionally to the
+/// magnitude of their frequency bands, like a spectrum analyzer or VU meter.
+/// The maximum of each segment is held as a peak dot that slowly falls back.
+pub struct SpectrumViz {
+    /// Visualization config.
+    pub config: SpectrumVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    /// Peak level of each segment between 0 and 1.
+    peaks: Vec<f32>,
+
+    /// Remaining time in seconds each peak is held.
+    peak_hold_remaining: Vec<f32>,
+}
+
+#[typetag::serde]
+impl Viz for SpectrumViz {
+    fn get_name(&self) -> &str {
+        "spectrum_viz"
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let levels = self.segment_levels(&input.bands);
+        let total_segments = levels.len();
+        let mut pixels = vec![PixelViz::default(); self.total_pixels];
+        pixels.iter_mut().for_each(|p| p.off());
+
+        if total_segments == 0 || self.total_pixels < total_segments {
+            return pixels;
+        }
+
+        if self.peaks.len() != total_segments {
+            self.peaks = vec![0.0; total_segments];
+            self.peak_hold_remaining = vec![0.0; total_segments];
+        }
+
+        let total_colors = colors.len().max(1);
+        let segment_length = self.total_pixels / total_segments;
+
+        // distance between the positions of neighboring pixels
+        let pixel_step = match self.config.orientation {
+            SpectrumOrientation::BottomUp => 1.0 / segment_length as f32,
+            SpectrumOrientation::CenterOut => 2.0 / segment_length as f32,
+        };
+
+        for (segment, level) in levels.iter().enumerate() {
+            self.update_peak(segment, *level, time);
+            let peak = self.peaks[segment];
+
+            for i in 0..segment_length {
+                let position = self.position(i, segment_length);
+                let pixel = &mut pixels[segment * segment_length + i];
+                let is_peak = peak > 0.0 && position < peak && position >= peak - pixel_step;
+
+                if position < *level || is_peak {
+                    pixel.brightness = 1.0;
+                    pixel.color_index = if self.config.gradient {
+                        (position * total_colors as f32) as usize
+                    } else {
+                        segment
+                    };
+                }
+            }
+        }
+
+        pixels
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.to_map()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        let new_settings =
+            SpectrumVizConfig::from_map(self.get_pretty_name().to_string(), settings);
+        self.config = new_settings;
+        self.peaks = Vec::new();
+        self.peak_hold_remaining = Vec::new();
+    }
+}
+
+unsafe impl Send for SpectrumViz {}
+unsafe impl Sync for SpectrumViz {}
+
+impl SpectrumViz {
+    pub fn new(config: SpectrumVizConfig) -> Self {
+        SpectrumViz {
+            config,
+            total_pixels: 0,
+            peaks: Vec::new(),
+            peak_hold_remaining: Vec::new(),
+        }
+    }
+
+    /// Returns the level of each segment between 0 and 1.
+    ///
+    /// Bands are merged into segments if there are less segments than bands; the level of a
+    /// segment is the maximum magnitude of its bands.
+    ///
+    fn segment_levels(&self, bands: &[f32]) -> Vec<f32> {
+        let total_bands = bands.len();
+        let total_segments = match self.config.bands_per_strip {
+            0 => total_bands,
+            n => n.min(total_bands),
+        };
+
+        (0..total_segments)
+            .map(|segment| {
+                let start = segment * total_bands / total_segments;
+                let end = (segment + 1) * total_bands / total_segments;
+                bands[start..end]
+                    .iter()
+                    .fold(0.0_f32, |max, b| max.max(*b))
+                    .clamp(0.0, 100.0)
+                    / 100.0
+            })
+            .collect()
+    }
+
+    /// Holds the peak of a segment if its level is at least as high, otherwise lets it fall
+    /// after the hold time is over.
+    fn update_peak(&mut self, segment: usize, level: f32, time: &FrameTime) {
+        if level >= self.peaks[segment] {
+            self.peaks[segment] = level;
+            self.peak_hold_remaining[segment] = self.peak_hold();
+        } else if self.peak_hold_remaining[segment] > 0.0 {
+            self.peak_hold_remaining[segment] -= time.delta;
+        } else {
+            let fallen = self.peaks[segment] - self.peak_fall_speed() * time.delta;
+            self.peaks[segment] = fallen.max(level);
+        }
+    }
+
+    /// Returns the time in seconds peaks are held.
+    fn peak_hold(&self) -> f32 {
+        finite_or(self.config.peak_hold, DEFAULT_PEAK_HOLD).max(0.0)
+    }
+
+    /// Returns the fraction of the segment length peaks fall per second.
+    fn peak_fall_speed(&self) -> f32 {
+        finite_or(self.config.peak_fall_speed, DEFAULT_PEAK_FALL_SPEED).max(0.0)
+    }
+
+    /// Returns the position of a pixel in its segment between 0 and 1 in the direction
+    /// the segment lights up.
+    fn position(&self, pixel: usize, segment_length: usize) -> f32 {
+        let center = pixel as f32 + 0.5;
+        match self.config.orientation {
+            SpectrumOrientation::BottomUp => center / segment_length as f32,
+            SpectrumOrientation::CenterOut => {
+                let half = segment_length as f32 / 2.0;
+                (center - half).abs() / half
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::features::AudioFeatures;
+use crate::theme::Color;
+use crate::viz::viz::finite_or;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// Hard limit of flashes per second; higher flash rates can trigger photosensitive seizures
+pub const MAX_FLASH_RATE: f32 = 3.0;
+
+/// Hard limit of the brightness change between flashes and the dark phase in between,
+/// and of the change of the perceived luminance relative to white; pixels never go from
+/// full brightness to off
+pub const MAX_BRIGHTNESS_CHANGE: f32 = 0.8;
+
+/// Flash rate used if the configured flash rate isn't a positive number
+const DEFAULT_FLASH_RATE: f32 = 2.0;
+
+/// Duty cycle used if the configured duty cycle isn't a number
+const DEFAULT_DUTY_CYCLE: f32 = 0.5;
+
+/// Brightness change used if the configured brightness change isn't a number
+const DEFAULT_BRIGHTNESS_CHANGE: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines the color of flashes.
+pub enum StrobeColorMode {
+    /// Theme color of the band with the largest magnitude
+    DominantBand,
+
+    /// Next theme color on each flash
+    Cycle,
+}
+
+impl Default for StrobeColorMode {
+    fn default() -> Self {
+        StrobeColorMode::DominantBand
+    }
+}
+
+impl fmt::Display for StrobeColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StrobeColorMode::DominantBand => write!(f, "dominant_band"),
+            StrobeColorMode::Cycle => write!(f, "cycle"),
+        }
+    }
+}
+
+impl FromStr for StrobeColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dominant_band" => Ok(StrobeColorMode::DominantBand),
+            "cycle" => Ok(StrobeColorMode::Cycle),
+            _ => Err(format!("Unknown strobe color mode {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct StrobeVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Maximum number of flashes per second; limited to `MAX_FLASH_RATE`.
+    /// `DEFAULT_FLASH_RATE` is used if not a positive number.
+    pub flash_rate: f32,
+
+    /// Fraction of the minimum time between two flashes pixels are lit, between 0 and 1.
+    pub duty_cycle: f32,
+
+    /// Brightness change between flashes and the dark phase in between, between 0 and 1;
+    /// limited to `MAX_BRIGHTNESS_CHANGE`.
+    pub brightness_change: f32,
+
+    #[serde(default)]
+    /// Determines the color of flashes.
+    pub color_mode: StrobeColorMode,
+}
+
+impl StrobeVizConfig {
+    /// Convert settings in map of strings to visualization config.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        settings.insert("flash_rate".to_string(), self.flash_rate.to_string());
+        settings.insert("duty_cycle".to_string(), self.duty_cycle.to_string());
+        settings.insert(
+            "brightness_change".to_string(),
+            self.brightness_change.to_string(),
+        );
+        settings.insert("color_mode".to_string(), self.color_mode.to_string());
+        settings
+    }
+
+    /// Create visualization config from map of strings.
+    pub fn from_map(name: String, settings: HashMap<String, String>) -> Self {
+        Self {
+            pretty_name: name,
+            flash_rate: settings
+                .get(&"flash_rate".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            duty_cycle: settings
+                .get(&"duty_cycle".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            brightness_change: settings
+                .get(&"brightness_change".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            color_mode: settings
+                .get(&"color_mode".to_string())
+                .unwrap_or(&"dominant_band".to_string())
+                .parse::<StrobeColorMode>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// Last flash of all strobe visualizations sharing it.
+struct Flash {
+    /// Frame time of the last flash; not set before the first flash.
+    time: Option<Duration>,
+
+    /// Theme color of the last flash.
+    color_index: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+/// Visualization flashing all pixels on beats.
+/// Flash rate and luminance change are limited to photosensitivity-safe values,
+/// independent of the config; onsets are ignored until the next flash is allowed.
+///
+/// Clones share their flashes, so the visualizations of both outputs flash together and
+/// the flash rate is limited across all of them.
+pub struct StrobeViz {
+    /// Visualization config.
+    pub config: StrobeVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    #[serde(skip)]
+    /// Last flash; shared between clones.
+    flash: Arc<Mutex<Flash>>,
+
+    #[serde(skip)]
+    /// Perceived luminance of the pixels during the current flash and during the dark phase
+    /// after it; not set before the first update.
+    levels: Option<(f32, f32)>,
+
+    #[serde(skip)]
+    /// Frame time of the flash `levels` have been set for.
+    levels_flash: Option<Duration>,
+}
+
+#[typetag::serde]
+impl Viz for StrobeViz {
+    fn get_name(&self) -> &str {
+        "strobe_viz"
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        let period = self.flash_period();
+        let mut flash = self.flash.lock().unwrap();
+
+        // a clock running backwards, e.g. after a restart, allows the next flash
+        let since_flash = |flash: &Flash| {
+            flash
+                .time
+                .and_then(|flash_time| time.now.checked_sub(flash_time))
+                .map_or(f32::MAX, |elapsed| elapsed.as_secs_f32())
+        };
+        if input.onset && since_flash(&flash) >= period {
+            flash.time = Some(time.now);
+            flash.color_index = match self.config.color_mode {
+                StrobeColorMode::DominantBand => input.dominant_band(),
+                StrobeColorMode::Cycle => (flash.color_index + 1) % colors.len().max(1),
+            };
+        }
+
+        let luminance = colors
+            .get(flash.color_index % colors.len().max(1))
+            .map_or(0.0, |c| c.luminance());
+        if self.levels.is_none() || self.levels_flash != flash.time {
+            self.levels = Some(self.flash_levels(luminance));
+            self.levels_flash = flash.time;
+        }
+
+        // brightness is derived from the luminance, which is kept if theme colors change
+        let (flash_level, dark_level) = self.levels.unwrap_or_default();
+        let level = if since_flash(&flash) < self.duty_cycle() * period {
+            flash_level
+        } else {
+            dark_level
+        };
+        let brightness = if luminance > 0.0 {
+            (level / luminance).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let pixel = PixelViz {
+            color_index: flash.color_index,
+            brightness,
+            ..PixelViz::default()
+        };
+        vec![pixel; self.total_pixels]
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.to_map()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        let new_settings = StrobeVizConfig::from_map(self.get_pretty_name().to_string(), settings);
+        self.config = new_settings;
+    }
+}
+
+impl StrobeViz {
+    pub fn new(config: StrobeVizConfig) -> Self {
+        StrobeViz {
+            config,
+            total_pixels: 0,
+            flash: Arc::new(Mutex::new(Flash::default())),
+            levels: None,
+            levels_flash: None,
+        }
+    }
+
+    /// Returns the perceived luminance of the pixels during a new flash and the dark phase after it.
+    ///
+    /// The luminance of the flash changes by at most `MAX_BRIGHTNESS_CHANGE` from the dark phase
+    /// of the previous flash, e.g. if a dark theme color is followed by a bright one.
+    ///
+    /// # Arguments
+    /// * `luminance`: perceived luminance of the color of the flash at full brightness
+    ///
+    fn flash_levels(&self, luminance: f32) -> (f32, f32) {
+        let flash_level = match self.levels {
+            Some((_, dark_level)) => luminance.min(dark_level + MAX_BRIGHTNESS_CHANGE),
+            None => luminance,
+        };
+        (flash_level, flash_level * (1.0 - self.brightness_change()))
+    }
+
+    /// Returns the minimum time in seconds between two flashes.
+    fn flash_period(&self) -> f32 {
+        let flash_rate = if self.config.flash_rate.is_finite() && self.config.flash_rate > 0.0 {
+            self.config.flash_rate.min(MAX_FLASH_RATE)
+        } else {
+            DEFAULT_FLASH_RATE
+        };
+        1.0 / flash_rate
+    }
+
+    /// Returns the fraction of the minimum time between two flashes pixels are lit.
+    fn duty_cycle(&self) -> f32 {
+        finite_or(self.config.duty_cycle, DEFAULT_DUTY_CYCLE).clamp(0.0, 1.0)
+    }
+
+    /// Returns the brightness change between flashes and the dark phase in between.
+    fn brightness_change(&self) -> f32 {
+        finite_or(self.config.brightness_change, DEFAULT_BRIGHTNESS_CHANGE)
+            .clamp(0.0, MAX_BRIGHTNESS_CHANGE)
+    }
+}
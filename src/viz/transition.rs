@@ -0,0 +1,120 @@
+use crate::settings::{TransitionCurve, TransitionSettings};
+use crate::viz::FrameTime;
+use crate::viz::Layer;
+use crate::viz::OutputThemes;
+
+/// Width of the soft edge of wipes relative to half of the strip
+const WIPE_EDGE: f32 = 0.1;
+
+/// Transition from the previously shown layers and theme to the current ones.
+///
+/// The previous layers keep getting updated during the transition, so that both the previous
+/// and the current visualization are animated while they are blended.
+pub struct Transition {
+    /// Layers shown before the transition started
+    pub layers: Vec<Layer>,
+
+    /// Themes used in layers without a theme before the transition started
+    pub themes: OutputThemes,
+
+    /// Transition that was running when the transition started
+    pub previous: Option<Box<Transition>>,
+
+    /// Duration and curve of the transition
+    settings: TransitionSettings,
+
+    /// Time in seconds since the transition started
+    elapsed: f32,
+}
+
+impl Transition {
+    pub fn new(layers: Vec<Layer>, themes: OutputThemes, settings: TransitionSettings) -> Self {
+        Transition {
+            layers,
+            themes,
+            previous: None,
+            settings,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the transition by the frame time.
+    pub fn update(&mut self, time: &FrameTime) {
+        self.elapsed += time.delta;
+    }
+
+    /// Returns whether only the current layers are shown.
+    pub fn is_done(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// Returns the progress of the transition between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        if self.settings.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.settings.duration).clamp(0.0, 1.0)
+    }
+
+    /// Returns the RGB values of the left and right output shown when the transition started.
+    ///
+    /// If another transition was running at that time, it keeps running and its blended
+    /// values are returned, so starting a transition during another one doesn't jump.
+    ///
+    /// # Arguments
+    /// * `time`: frame time the previous transition is advanced by
+    /// * `composite`: returns the RGB values of the provided layers and themes
+    ///
+    pub fn previous_frames<F>(
+        &mut self,
+        time: &FrameTime,
+        composite: &mut F,
+    ) -> (Vec<[f32; 3]>, Vec<[f32; 3]>)
+    where
+        F: FnMut(&mut [Layer], &OutputThemes) -> (Vec<[f32; 3]>, Vec<[f32; 3]>),
+    {
+        let (mut left_frame, mut right_frame) = composite(&mut self.layers, &self.themes);
+        if let Some(previous) = self.previous.as_mut() {
+            previous.update(time);
+            let (left_previous, right_previous) = previous.previous_frames(time, composite);
+            previous.blend(&left_previous, &mut left_frame);
+            previous.blend(&right_previous, &mut right_frame);
+
+            if previous.is_done() {
+                self.previous = None;
+            }
+        }
+        (left_frame, right_frame)
+    }
+
+    /// Returns the share of the current layers in the color of a pixel between 0 and 1.
+    ///
+    /// # Arguments
+    /// * `index`: index of the pixel
+    /// * `total_pixels`: number of pixels of the output
+    ///
+    pub fn mix(&self, index: usize, total_pixels: usize) -> f32 {
+        let progress = self.progress();
+        match self.settings.curve {
+            TransitionCurve::Linear => progress,
+            TransitionCurve::Ease => progress * progress * (3.0 - 2.0 * progress),
+            TransitionCurve::Wipe => {
+                // distance of the pixel center to the strip center; 0 at the center, 1 at the ends
+                let half = total_pixels as f32 / 2.0;
+                let distance = ((index as f32 + 0.5 - half) / half).abs();
+                ((progress * (1.0 + WIPE_EDGE) - distance) / WIPE_EDGE).clamp(0.0, 1.0)
+            }
+        }
+    }
+
+    /// Blends the RGB values of the previous layers into the RGB values of the current layers.
+    pub fn blend(&self, previous: &[[f32; 3]], frame: &mut [[f32; 3]]) {
+        let total_pixels = frame.len();
+        for (i, (pixel, previous)) in frame.iter_mut().zip(previous.iter()).enumerate() {
+            let mix = self.mix(i, total_pixels);
+            for (value, previous) in pixel.iter_mut().zip(previous.iter()) {
+                *value = previous + (*value - previous) * mix;
+            }
+        }
+    }
+}
@@ -5,10 +5,17 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use crate::settings::{OutputSettings, TransformerSettings};
+use crate::features::AudioFeatures;
+use crate::settings::{
+    LayerSettings, OutputSettings, Settings, Speaker, TransformerSettings, TransitionSettings,
+};
 use crate::theme::Color;
 use crate::theme::Theme;
 use crate::transform::AudioTransformer;
+use crate::viz::{
+    composite, split_linked, Clock, FrameScheduler, FrameStats, FrameTime, Layer, Playlist,
+    PlaylistItem, Transition,
+};
 
 #[typetag::serde]
 /// Abstract type implemented by all visualizations.
@@ -19,8 +26,16 @@ pub trait Viz: DynClone + Sync + Send {
     /// Returns a descriptive visualization name.
     fn get_pretty_name(&self) -> &str;
 
-    /// Updates the visualization state based on the provided input.
-    fn update(&mut self, input: &Vec<f32>, colors: &Vec<Color>) -> Vec<PixelViz>;
+    /// Updates the visualization state based on the provided audio features.
+    ///
+    /// Animations need to be based on the provided frame time instead of the number of updates,
+    /// since the update rate depends on the hardware.
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz>;
 
     /// Sets the number of total available pizels.
     fn set_total_pixels(&mut self, pixels: usize);
@@ -56,6 +71,25 @@ impl PixelViz {
     pub fn off(&mut self) {
         self.brightness = 0.0;
     }
+
+    /// Creates a pixel showing the provided color instead of a theme color.
+    ///
+    /// The color multipliers are relative to the first theme color;
+    /// theme color values are never 0, so any color can be reached.
+    ///
+    pub fn with_color(color: Color, colors: &[Color]) -> PixelViz {
+        let base = colors
+            .first()
+            .copied()
+            .unwrap_or(Color { r: 1, g: 1, b: 1 });
+        PixelViz {
+            color_index: 0,
+            red_mul: color.r as f32 / base.r.max(1) as f32,
+            green_mul: color.g as f32 / base.g.max(1) as f32,
+            blue_mul: color.b as f32 / base.b.max(1) as f32,
+            brightness: 1.0,
+        }
+    }
 }
 
 impl Default for PixelViz {
@@ -71,13 +105,59 @@ impl Default for PixelViz {
     }
 }
 
+/// Returns the provided value, or the default if the value is NaN or infinite.
+///
+/// Used by visualizations to fall back to defaults for invalid numeric settings.
+///
+pub(super) fn finite_or(value: f32, default: f32) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        default
+    }
+}
+
+#[derive(Clone)]
+/// Themes of the left and right speaker.
+pub struct OutputThemes {
+    /// Theme of the left speaker
+    pub left: Theme,
+
+    /// Theme of the right speaker
+    pub right: Theme,
+}
+
+impl OutputThemes {
+    /// Uses the same theme for both speakers.
+    pub fn new(theme: Theme) -> Self {
+        OutputThemes {
+            left: theme.clone(),
+            right: theme,
+        }
+    }
+
+    /// Returns the theme of the provided speaker.
+    pub fn get(&self, speaker: Speaker) -> &Theme {
+        match speaker {
+            Speaker::Left => &self.left,
+            Speaker::Right => &self.right,
+        }
+    }
+
+    /// Sets the theme of the provided speaker; of both speakers if no speaker is provided.
+    pub fn set(&mut self, speaker: Option<Speaker>, theme: Theme) {
+        match speaker {
+            Some(Speaker::Left) => self.left = theme,
+            Some(Speaker::Right) => self.right = theme,
+            None => *self = OutputThemes::new(theme),
+        }
+    }
+}
+
 /// Executes and updates the visualization for all output channels.
 pub struct VizRunner {
-    /// Left visualization
-    pub viz_left: Arc<Mutex<Box<dyn Viz>>>,
-
-    /// Right visualization
-    pub viz_right: Arc<Mutex<Box<dyn Viz>>>,
+    /// Visualization layers from bottom to top; the bottom layer is the active visualization
+    pub layers: Arc<Mutex<Vec<Layer>>>,
 
     /// Output settings
     pub output_settings: OutputSettings,
@@ -85,75 +165,212 @@ pub struct VizRunner {
     /// Whether the visualization is running and is getting updated
     pub is_stopped: Arc<AtomicBool>,
 
-    /// Theme to use in visualization
-    pub theme: Arc<Mutex<Theme>>,
+    /// Themes to use in visualization layers without a theme
+    pub themes: Arc<Mutex<OutputThemes>>,
 
     /// Audio transformer; used for visualization input
     pub transformer: Arc<Mutex<AudioTransformer>>,
+
+    /// Clock the frame time passed to visualizations is based on
+    pub clock: Arc<dyn Clock>,
+
+    /// Frame rate statistics; updated after each frame
+    pub stats: Arc<Mutex<FrameStats>>,
+
+    /// Playlist changing the visualization of the bottom layer and the theme; not set if disabled
+    pub playlist: Arc<Mutex<Option<Playlist>>>,
+
+    /// Running transition from the previously shown layers and theme
+    pub transition: Arc<Mutex<Option<Transition>>>,
+
+    /// Transition used when switching visualization or theme without providing a transition
+    pub transition_settings: TransitionSettings,
 }
 
 impl VizRunner {
     pub fn start(&self) {
         // make values available in thread
         let stopped = self.is_stopped.clone();
-        let left_viz = Arc::clone(&self.viz_left);
-        let right_viz = Arc::clone(&self.viz_right);
+        let layers = Arc::clone(&self.layers);
         let output = self.output_settings.clone();
-        let theme = Arc::clone(&self.theme);
+        let themes = Arc::clone(&self.themes);
         let transformer = Arc::clone(&self.transformer);
+        let stats = Arc::clone(&self.stats);
+        let playlist = Arc::clone(&self.playlist);
+        let transition = Arc::clone(&self.transition);
+        let transition_settings = self.transition_settings;
+        let mut scheduler = FrameScheduler::new(output.fps, Arc::clone(&self.clock));
 
         let _handle = Some(thread::spawn(move || {
             // init outputs from settings
             let mut left_output = output.left.to_led();
             let mut right_output = output.right.to_led();
 
+            // LEDs only need to be turned off once when stopped
+            let mut is_cleared = false;
+
             loop {
+                // frames are also scheduled while stopped to avoid a large delta when resuming
+                let time = scheduler.wait();
+
                 if !stopped.load(Ordering::Relaxed) {
-                    let colors = theme.lock().unwrap().colors.clone();
-                    // update visualizations for left and right channel
-                    let left_pixel_viz = left_viz.lock().unwrap().update(
-                        &transformer.lock().unwrap().left_bands.lock().unwrap(),
-                        &colors,
-                    );
-                    let right_pixel_viz = right_viz.lock().unwrap().update(
-                        &transformer.lock().unwrap().right_bands.lock().unwrap(),
-                        &colors,
+                    is_cleared = false;
+
+                    let left_features = transformer
+                        .lock()
+                        .unwrap()
+                        .left_features
+                        .lock()
+                        .unwrap()
+                        .clone();
+                    let right_features = transformer
+                        .lock()
+                        .unwrap()
+                        .right_features
+                        .lock()
+                        .unwrap()
+                        .clone();
+
+                    // switch to the next playlist entry if it is due
+                    if let Some(playlist) = playlist.lock().unwrap().as_mut() {
+                        if let Some(item) = playlist.update(&left_features, &time) {
+                            Self::show_playlist_item(
+                                item,
+                                &layers,
+                                &themes,
+                                &transition,
+                                transition_settings,
+                                &output,
+                            );
+                        }
+                    }
+
+                    let themes = themes.lock().unwrap().clone();
+                    let (mut left_frame, mut right_frame) = Self::composite_layers(
+                        &mut layers.lock().unwrap(),
+                        &themes,
+                        &left_features,
+                        &right_features,
+                        &time,
+                        &output,
                     );
 
-                    // show pixel visualizations and apply multipliers
-                    for (i, pixel_viz) in left_pixel_viz.iter().enumerate() {
-                        let color = colors[pixel_viz.color_index % colors.len()];
-                        left_output.set_pixel(
-                            i,
-                            ((color.r as f32) * pixel_viz.red_mul) as u8,
-                            ((color.g as f32) * pixel_viz.green_mul) as u8,
-                            ((color.b as f32) * pixel_viz.blue_mul) as u8,
-                            pixel_viz.brightness,
-                        )
+                    // fade from the previous layers and theme while a transition is running
+                    let mut running_transition = transition.lock().unwrap();
+                    if let Some(t) = running_transition.as_mut() {
+                        t.update(&time);
+                        let (left_previous, right_previous) = t.previous_frames(
+                            &time,
+                            &mut |layers: &mut [Layer], themes: &OutputThemes| {
+                                Self::composite_layers(
+                                    layers,
+                                    themes,
+                                    &left_features,
+                                    &right_features,
+                                    &time,
+                                    &output,
+                                )
+                            },
+                        );
+                        t.blend(&left_previous, &mut left_frame);
+                        t.blend(&right_previous, &mut right_frame);
+
+                        if t.is_done() {
+                            *running_transition = None;
+                        }
+                    }
+                    drop(running_transition);
+
+                    // linked outputs are split after blending, so wipes run across the whole strip
+                    let (left_frame, right_frame) = if output.linked.enabled {
+                        split_linked(&left_frame, &output)
+                    } else {
+                        (left_frame, right_frame)
+                    };
+
+                    // show composited pixels
+                    for (i, [r, g, b]) in left_frame.iter().enumerate() {
+                        left_output.set_pixel(i, *r as u8, *g as u8, *b as u8, 1.0);
                     }
 
-                    for (i, pixel_viz) in right_pixel_viz.iter().enumerate() {
-                        let color = colors[pixel_viz.color_index % colors.len()];
-                        right_output.set_pixel(
-                            i,
-                            ((color.r as f32) * pixel_viz.red_mul) as u8,
-                            ((color.g as f32) * pixel_viz.green_mul) as u8,
-                            ((color.b as f32) * pixel_viz.blue_mul) as u8,
-                            pixel_viz.brightness,
-                        )
+                    for (i, [r, g, b]) in right_frame.iter().enumerate() {
+                        right_output.set_pixel(i, *r as u8, *g as u8, *b as u8, 1.0);
                     }
-                } else {
+
+                    left_output.show();
+                    right_output.show();
+                } else if !is_cleared {
                     // turn off LEDs of speakers
                     left_output.clear();
                     right_output.clear();
+                    left_output.show();
+                    right_output.show();
+                    is_cleared = true;
                 }
 
-                left_output.show();
-                right_output.show();
+                scheduler.end_frame();
+                *stats.lock().unwrap() = scheduler.stats();
             }
         }));
     }
 
+    /// Updates the visualizations of all layers for left and right channel
+    /// and composites them from bottom to top.
+    ///
+    /// If the outputs are linked, only the left visualizations are updated and the RGB values
+    /// of the left output cover the whole linked strip; they still need to be split.
+    /// Returns the RGB values of the left and right output.
+    ///
+    fn composite_layers(
+        layers: &mut [Layer],
+        themes: &OutputThemes,
+        left_features: &AudioFeatures,
+        right_features: &AudioFeatures,
+        time: &FrameTime,
+        output: &OutputSettings,
+    ) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+        let is_linked = output.linked.enabled;
+        let mut left_frame = vec![[0.0; 3]; output.total_pixels(Speaker::Left)];
+        let mut right_frame = vec![[0.0; 3]; output.total_pixels(Speaker::Right)];
+
+        for layer in layers.iter_mut() {
+            // theme colors might depend on the audio features of each channel
+            let left_colors = layer
+                .theme
+                .as_ref()
+                .unwrap_or(&themes.left)
+                .colors_for(left_features);
+            let left_pixel_viz = layer.viz_left.update(left_features, &left_colors, time);
+            composite(
+                &mut left_frame,
+                &left_pixel_viz,
+                &left_colors,
+                layer.opacity,
+                layer.blend_mode,
+            );
+
+            if is_linked {
+                continue;
+            }
+
+            let right_colors = layer
+                .theme
+                .as_ref()
+                .unwrap_or(&themes.right)
+                .colors_for(right_features);
+            let right_pixel_viz = layer.viz_right.update(right_features, &right_colors, time);
+            composite(
+                &mut right_frame,
+                &right_pixel_viz,
+                &right_colors,
+                layer.opacity,
+                layer.blend_mode,
+            );
+        }
+
+        (left_frame, right_frame)
+    }
+
     /// Restart the transformer, which will also restart the viz.
     pub fn update_transformer_settings(&mut self, settings: TransformerSettings) {
         self.transformer.lock().unwrap().update_settings(settings);
@@ -161,11 +378,20 @@ impl VizRunner {
 
     /// Update the settings for the current visualization and restart.
     pub fn update_viz_settings(&mut self, settings: HashMap<String, String>) {
-        self.viz_right
-            .lock()
-            .unwrap()
-            .update_settings(settings.clone());
-        self.viz_left.lock().unwrap().update_settings(settings);
+        if let Some(layer) = self.layers.lock().unwrap().first_mut() {
+            layer.update_viz_settings(&settings);
+        }
+    }
+
+    /// Update the settings for the current visualization of the provided speaker.
+    pub fn update_output_viz_settings(
+        &mut self,
+        speaker: Speaker,
+        settings: HashMap<String, String>,
+    ) {
+        if let Some(layer) = self.layers.lock().unwrap().first_mut() {
+            layer.update_output_viz_settings(speaker, &settings);
+        }
     }
 
     /// Stops the visualization from updating and running.
@@ -178,17 +404,282 @@ impl VizRunner {
         self.is_stopped.clone().load(Ordering::Relaxed)
     }
 
-    /// Sets the provided theme for the visualization.
-    pub fn set_theme(&mut self, theme: Theme) {
-        *self.theme.lock().unwrap() = theme;
+    /// Sets the provided theme for both speakers.
+    ///
+    /// The default transition is used if no transition is provided.
+    ///
+    pub fn set_theme(&mut self, theme: Theme, transition: Option<TransitionSettings>) {
+        self.switch_theme(None, theme, transition);
+    }
+
+    /// Sets the provided theme for a single speaker.
+    ///
+    /// The default transition is used if no transition is provided.
+    ///
+    pub fn set_output_theme(
+        &mut self,
+        speaker: Speaker,
+        theme: Theme,
+        transition: Option<TransitionSettings>,
+    ) {
+        self.switch_theme(Some(speaker), theme, transition);
     }
 
-    pub fn set_visualization(&mut self, viz: Box<dyn Viz>) {
-        let mut left_viz = dyn_clone::clone_box(&*viz);
-        left_viz.set_total_pixels(self.output_settings.left.total_leds as usize);
-        *self.viz_left.lock().unwrap() = left_viz;
-        let mut right_viz = dyn_clone::clone_box(&*viz);
-        right_viz.set_total_pixels(self.output_settings.right.total_leds as usize);
-        *self.viz_right.lock().unwrap() = right_viz;
+    /// Sets the visualization of the bottom layer for both speakers.
+    ///
+    /// The default transition is used if no transition is provided.
+    ///
+    pub fn set_visualization(&mut self, viz: Box<dyn Viz>, transition: Option<TransitionSettings>) {
+        self.switch_visualization(None, viz, transition);
+    }
+
+    /// Sets the visualization of the bottom layer for a single speaker.
+    ///
+    /// The default transition is used if no transition is provided.
+    ///
+    pub fn set_output_visualization(
+        &mut self,
+        speaker: Speaker,
+        viz: Box<dyn Viz>,
+        transition: Option<TransitionSettings>,
+    ) {
+        self.switch_visualization(Some(speaker), viz, transition);
+    }
+
+    /// Sets the theme of the provided speaker; of both speakers if no speaker is provided.
+    fn switch_theme(
+        &mut self,
+        speaker: Option<Speaker>,
+        theme: Theme,
+        transition: Option<TransitionSettings>,
+    ) {
+        let layers = self.layers.lock().unwrap();
+        let mut themes = self.themes.lock().unwrap();
+        Self::start_transition(
+            &layers,
+            &themes,
+            &self.transition,
+            transition.unwrap_or(self.transition_settings),
+        );
+        themes.set(speaker, theme);
+    }
+
+    /// Sets the bottom visualization of the provided speaker; of both speakers if not provided.
+    fn switch_visualization(
+        &mut self,
+        speaker: Option<Speaker>,
+        viz: Box<dyn Viz>,
+        transition: Option<TransitionSettings>,
+    ) {
+        let mut layers = self.layers.lock().unwrap();
+        Self::start_transition(
+            &layers,
+            &self.themes.lock().unwrap(),
+            &self.transition,
+            transition.unwrap_or(self.transition_settings),
+        );
+        Self::set_bottom_visualization(&mut layers, speaker, viz, &self.output_settings);
+    }
+
+    /// Starts a transition from the provided layers and themes to the ones that are set next.
+    ///
+    /// A running transition is kept as previous transition, so the new transition fades from
+    /// the blended frames. Switches instantly and ends a running transition if the duration is 0.
+    ///
+    fn start_transition(
+        layers: &[Layer],
+        themes: &OutputThemes,
+        transition: &Mutex<Option<Transition>>,
+        settings: TransitionSettings,
+    ) {
+        let mut transition = transition.lock().unwrap();
+        *transition = if settings.duration > 0.0 {
+            let mut next = Transition::new(layers.to_vec(), themes.clone(), settings);
+            next.previous = transition.take().map(Box::new);
+            Some(next)
+        } else {
+            None
+        };
+    }
+
+    /// Replaces the visualization of the bottom layer, keeping its theme, opacity and blend mode.
+    ///
+    /// Only the visualization of the provided speaker is replaced if a speaker is provided.
+    ///
+    fn set_bottom_visualization(
+        layers: &mut Vec<Layer>,
+        speaker: Option<Speaker>,
+        viz: Box<dyn Viz>,
+        output: &OutputSettings,
+    ) {
+        match (layers.first_mut(), speaker) {
+            (Some(layer), Some(speaker)) => layer.set_viz(speaker, viz, output),
+            (Some(layer), None) => {
+                *layer = Layer::new(
+                    viz,
+                    output,
+                    layer.theme.take(),
+                    layer.opacity,
+                    layer.blend_mode,
+                )
+            }
+            (None, _) => layers.push(Layer::new(viz, output, None, 1.0, Default::default())),
+        }
+    }
+
+    /// Shows the visualization and theme of a playlist entry.
+    fn show_playlist_item(
+        item: PlaylistItem,
+        layers: &Mutex<Vec<Layer>>,
+        themes: &Mutex<OutputThemes>,
+        transition: &Mutex<Option<Transition>>,
+        transition_settings: TransitionSettings,
+        output: &OutputSettings,
+    ) {
+        let mut layers = layers.lock().unwrap();
+        let mut themes = themes.lock().unwrap();
+        Self::start_transition(&layers, &themes, transition, transition_settings);
+        Self::set_bottom_visualization(&mut layers, None, item.viz, output);
+        if let Some(item_theme) = item.theme {
+            themes.set(None, item_theme);
+        }
+    }
+
+    /// Returns the identifier of the visualization of the bottom layer of the left speaker.
+    pub fn current_visualization(&self) -> String {
+        self.current_output_visualization(Speaker::Left)
+    }
+
+    /// Returns the identifier of the visualization of the bottom layer of the provided speaker.
+    pub fn current_output_visualization(&self, speaker: Speaker) -> String {
+        self.layers
+            .lock()
+            .unwrap()
+            .first()
+            .map(|l| l.viz(speaker).get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Returns the name of the theme of the provided speaker.
+    pub fn current_theme(&self, speaker: Speaker) -> String {
+        self.themes.lock().unwrap().get(speaker).name.clone()
+    }
+
+    /// Returns the settings of all layers from bottom to top.
+    pub fn layer_settings(&self) -> Vec<LayerSettings> {
+        self.layers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|l| l.to_settings())
+            .collect()
+    }
+
+    /// Adds a layer on top of the layer stack.
+    pub fn add_layer(&mut self, layer: Layer) {
+        self.layers.lock().unwrap().push(layer);
+    }
+
+    /// Updates the layer at the provided index.
+    ///
+    /// The layer keeps its visualization state if the visualization doesn't change.
+    /// Returns `false` if the layer, visualization or theme doesn't exist.
+    ///
+    pub fn update_layer(
+        &mut self,
+        index: usize,
+        layer_settings: &LayerSettings,
+        settings: &Settings,
+    ) -> bool {
+        let mut layers = self.layers.lock().unwrap();
+        let layer = match layers.get_mut(index) {
+            Some(layer) => layer,
+            None => return false,
+        };
+
+        if layer.viz_left.get_name() != layer_settings.viz {
+            return match Layer::from_settings(layer_settings, settings) {
+                Some(new_layer) => {
+                    *layer = new_layer;
+                    true
+                }
+                None => false,
+            };
+        }
+
+        let theme = match &layer_settings.theme {
+            Some(name) => match settings.theme(name) {
+                Some(theme) => Some(theme),
+                None => return false,
+            },
+            None => None,
+        };
+        if let Some(viz_settings) = &layer_settings.settings {
+            layer.merge_viz_settings(viz_settings);
+        }
+        layer.theme = theme;
+        layer.opacity = layer_settings.opacity;
+        layer.blend_mode = layer_settings.blend_mode;
+        true
+    }
+
+    /// Removes the layer at the provided index.
+    ///
+    /// Returns `false` if the layer doesn't exist or is the only layer.
+    ///
+    pub fn remove_layer(&mut self, index: usize) -> bool {
+        let mut layers = self.layers.lock().unwrap();
+        if index >= layers.len() || layers.len() == 1 {
+            return false;
+        }
+        layers.remove(index);
+        true
+    }
+
+    /// Replaces the playlist and shows its current entry; the playlist is stopped if `None`.
+    pub fn set_playlist(&mut self, playlist: Option<Playlist>) {
+        let mut current = self.playlist.lock().unwrap();
+        if let Some(playlist) = &playlist {
+            Self::show_playlist_item(
+                playlist.current(),
+                &self.layers,
+                &self.themes,
+                &self.transition,
+                self.transition_settings,
+                &self.output_settings,
+            );
+        }
+        *current = playlist;
+    }
+
+    /// Returns the index of the current playlist entry; `None` if no playlist is running.
+    pub fn current_playlist_entry(&self) -> Option<usize> {
+        self.playlist
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|p| p.current_entry())
+    }
+
+    /// Skips to the next playlist entry.
+    ///
+    /// Returns `false` if no playlist is running.
+    ///
+    pub fn next_playlist_entry(&mut self) -> bool {
+        let mut playlist = self.playlist.lock().unwrap();
+        match playlist.as_mut() {
+            Some(playlist) => {
+                Self::show_playlist_item(
+                    playlist.advance(),
+                    &self.layers,
+                    &self.themes,
+                    &self.transition,
+                    self.transition_settings,
+                    &self.output_settings,
+                );
+                true
+            }
+            None => false,
+        }
     }
 }
@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::features::AudioFeatures;
+use crate::theme::Color;
+use crate::viz::viz::finite_or;
+use crate::viz::FrameTime;
+use crate::viz::PixelViz;
+use crate::viz::Viz;
+
+/// Scroll speed used if the configured one isn't a number
+const DEFAULT_SCROLL_SPEED: f32 = 60.0;
+
+/// Maximum spectral centroid used if the configured one isn't a number
+const DEFAULT_MAX_CENTROID: f32 = 8000.0;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// Determines which feature the color of new pixels is based on.
+pub enum WaterfallColorSource {
+    /// Band with the largest magnitude; each band has its own theme color
+    DominantBand,
+
+    /// Spectral centroid; low frequencies map to the first, high frequencies to the last theme color
+    SpectralCentroid,
+}
+
+impl Default for WaterfallColorSource {
+    fn default() -> Self {
+        WaterfallColorSource::DominantBand
+    }
+}
+
+impl fmt::Display for WaterfallColorSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaterfallColorSource::DominantBand => write!(f, "dominant_band"),
+            WaterfallColorSource::SpectralCentroid => write!(f, "spectral_centroid"),
+        }
+    }
+}
+
+impl FromStr for WaterfallColorSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dominant_band" => Ok(WaterfallColorSource::DominantBand),
+            "spectral_centroid" => Ok(WaterfallColorSource::SpectralCentroid),
+            _ => Err(format!("Unknown waterfall color source {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// Visualization Config.
+pub struct WaterfallVizConfig {
+    /// Screen friendly name of visualization.
+    pub pretty_name: String,
+
+    /// Determines which feature the color of new pixels is based on.
+    pub color_source: WaterfallColorSource,
+
+    /// Number of pixels the strip scrolls per second; at least 0,
+    /// `DEFAULT_SCROLL_SPEED` is used if not a number.
+    pub scroll_speed: f32,
+
+    /// Spectral centroid in Hz mapped to the last theme color;
+    /// `DEFAULT_MAX_CENTROID` is used if not a number.
+    pub max_centroid: f32,
+}
+
+impl WaterfallVizConfig {
+    /// Convert settings in map of strings to visualization config.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut settings = HashMap::new();
+        settings.insert("color_source".to_string(), self.color_source.to_string());
+        settings.insert("scroll_speed".to_string(), self.scroll_speed.to_string());
+        settings.insert("max_centroid".to_string(), self.max_centroid.to_string());
+        settings
+    }
+
+    /// Create visualization config from map of strings.
+    pub fn from_map(name: String, settings: HashMap<String, String>) -> Self {
+        Self {
+            pretty_name: name,
+            color_source: settings
+                .get(&"color_source".to_string())
+                .unwrap_or(&"dominant_band".to_string())
+                .parse::<WaterfallColorSource>()
+                .unwrap(),
+            scroll_speed: settings
+                .get(&"scroll_speed".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+            max_centroid: settings
+                .get(&"max_centroid".to_string())
+                .unwrap_or(&"0".to_string())
+                .parse::<f32>()
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+/// Visualization scrolling along the strip like a spectrogram. New pixels are added at
+/// the start of the strip, so a song leaves a visible history of its frequencies.
+pub struct WaterfallViz {
+    /// Visualization config.
+    pub config: WaterfallVizConfig,
+
+    /// Total number of pixels.
+    total_pixels: usize,
+
+    /// Pixels from the newest to the oldest.
+    history: VecDeque<PixelViz>,
+
+    /// Fraction of a pixel the strip has not been scrolled yet.
+    scroll_remainder: f32,
+}
+
+#[typetag::serde]
+impl Viz for WaterfallViz {
+    fn get_name(&self) -> &str {
+        "waterfall_viz"
+    }
+
+    fn get_pretty_name(&self) -> &str {
+        &self.config.pretty_name
+    }
+
+    fn update(
+        &mut self,
+        input: &AudioFeatures,
+        colors: &Vec<Color>,
+        time: &FrameTime,
+    ) -> Vec<PixelViz> {
+        self.scroll_remainder += self.scroll_speed() * time.delta;
+        let shift = self.scroll_remainder.floor();
+        self.scroll_remainder -= shift;
+
+        // pixels scrolled off the strip don't need to be added
+        let pixel = self.new_pixel(input, colors);
+        for _ in 0..(shift as usize).min(self.total_pixels) {
+            self.history.push_front(pixel.clone());
+        }
+        self.history.truncate(self.total_pixels);
+
+        let mut pixels: Vec<PixelViz> = self.history.iter().cloned().collect();
+        pixels.resize_with(self.total_pixels, || {
+            let mut pixel = PixelViz::default();
+            pixel.off();
+            pixel
+        });
+        pixels
+    }
+
+    fn set_total_pixels(&mut self, pixels: usize) {
+        self.total_pixels = pixels;
+        self.history.truncate(pixels);
+    }
+
+    fn get_settings(&self) -> HashMap<String, String> {
+        self.config.to_map()
+    }
+
+    fn update_settings(&mut self, settings: HashMap<String, String>) {
+        let new_settings =
+            WaterfallVizConfig::from_map(self.get_pretty_name().to_string(), settings);
+        self.config = new_settings;
+        self.history.clear();
+        self.scroll_remainder = 0.0;
+    }
+}
+
+unsafe impl Send for WaterfallViz {}
+unsafe impl Sync for WaterfallViz {}
+
+impl WaterfallViz {
+    pub fn new(config: WaterfallVizConfig) -> Self {
+        WaterfallViz {
+            config,
+            total_pixels: 0,
+            history: VecDeque::new(),
+            scroll_remainder: 0.0,
+        }
+    }
+
+    /// Returns the number of pixels the strip scrolls per second.
+    fn scroll_speed(&self) -> f32 {
+        finite_or(self.config.scroll_speed, DEFAULT_SCROLL_SPEED).max(0.0)
+    }
+
+    /// Returns the pixel added at the start of the strip for the current frame.
+    ///
+    /// The brightness of the pixel is the magnitude of the dominant band.
+    ///
+    fn new_pixel(&self, input: &AudioFeatures, colors: &[Color]) -> PixelViz {
+        let dominant_band = input.dominant_band();
+        let magnitude = input.bands.get(dominant_band).copied().unwrap_or(0.0);
+        let color_index = match self.config.color_source {
+            WaterfallColorSource::DominantBand => dominant_band,
+            WaterfallColorSource::SpectralCentroid => {
+                let max_index = colors.len().max(1) - 1;
+                let max_centroid = finite_or(self.config.max_centroid, DEFAULT_MAX_CENTROID);
+                let position = if max_centroid > 0.0 {
+                    (input.spectral_centroid / max_centroid).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                (position * max_index as f32).round() as usize
+            }
+        };
+
+        PixelViz {
+            color_index,
+            brightness: (magnitude / 100.0).clamp(0.0, 1.0),
+            ..PixelViz::default()
+        }
+    }
+}
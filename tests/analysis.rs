@@ -0,0 +1,93 @@
+use led_speakers::analysis::{analyze, hop_len, write_csv, write_json, AnalysisFrame};
+use led_speakers::settings::{Analysis, TransformerSettings};
+
+use std::f32::consts::PI;
+
+const RATE: u32 = 44100;
+
+fn settings() -> TransformerSettings {
+    TransformerSettings {
+        sink: "".to_string(),
+        fft_len: 3000,
+        total_bands: 6,
+        lower_cutoff: 50.0,
+        upper_cutoff: 10000.0,
+        monstercat: 50.0,
+        decay: 10.0,
+        buffer_size: 65535,
+        analysis: Analysis::Fft,
+        hpss: false,
+    }
+}
+
+/// Interleaved stereo samples of a sine wave lasting `duration` seconds.
+fn sine(frequency: f32, duration: f32) -> Vec<i16> {
+    let len = (RATE as f32 * duration) as usize;
+    (0..len)
+        .flat_map(|i| {
+            let sample = (2.0 * PI * frequency * i as f32 / RATE as f32).sin() * 16000.0;
+            vec![sample as i16, sample as i16]
+        })
+        .collect()
+}
+
+#[test]
+fn test_hop_len() {
+    assert_eq!(hop_len(44100), 366);
+    assert_eq!(hop_len(48000), 400);
+    assert_eq!(hop_len(0), 0);
+}
+
+#[test]
+fn test_analyze() {
+    let samples = sine(440.0, 1.0);
+    let frames = analyze(&samples, RATE, settings());
+
+    // first frame once fft_len sample pairs are available, then one frame per hop
+    let hop = hop_len(RATE);
+    let first_end = (3000 * 2usize).div_ceil(hop) * hop;
+    assert_eq!(frames.len(), (samples.len() - first_end) / hop + 1);
+    assert_eq!(frames[0].frame, 0);
+    assert_eq!(frames[0].time, (first_end / 2) as f32 / RATE as f32);
+    assert!((frames[1].time - frames[0].time - (hop / 2) as f32 / RATE as f32).abs() < 1e-6);
+
+    let last = frames.last().unwrap();
+    assert!((last.left.dominant_frequency - 440.0).abs() < 15.0);
+    assert!((last.right.dominant_frequency - 440.0).abs() < 15.0);
+    assert!(last.left.stereo.correlation > 0.99);
+}
+
+#[test]
+fn test_analyze_short_input() {
+    let frames = analyze(&sine(440.0, 0.01), RATE, settings());
+    assert!(frames.is_empty());
+}
+
+#[test]
+fn test_write_csv() {
+    let frames = analyze(&sine(440.0, 0.1), RATE, settings());
+    let mut output = Vec::new();
+    write_csv(&frames, &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), frames.len() * 2 + 1);
+
+    let header: Vec<&str> = lines[0].split(',').collect();
+    assert_eq!(header[..3], ["frame", "time", "channel"]);
+    assert_eq!(header.last(), Some(&"band_5"));
+    assert_eq!(lines[1].split(',').count(), header.len());
+    assert!(lines[1].starts_with("0,"));
+    assert!(lines[2].contains(",right,"));
+}
+
+#[test]
+fn test_write_json() {
+    let frames = analyze(&sine(440.0, 0.1), RATE, settings());
+    let mut output = Vec::new();
+    write_json(&frames, &mut output).unwrap();
+
+    let parsed: Vec<AnalysisFrame> = serde_json::from_slice(&output).unwrap();
+    assert_eq!(parsed.len(), frames.len());
+    assert_eq!(parsed[0].left.bands, frames[0].left.bands);
+}
@@ -0,0 +1,143 @@
+use led_speakers::analysis::analyze;
+use led_speakers::features::DrumEvent;
+use led_speakers::settings::{Analysis, TransformerSettings};
+
+use std::f32::consts::PI;
+
+const RATE: u32 = 44100;
+
+fn settings() -> TransformerSettings {
+    TransformerSettings {
+        sink: "".to_string(),
+        fft_len: 3000,
+        total_bands: 6,
+        lower_cutoff: 50.0,
+        upper_cutoff: 10000.0,
+        monstercat: 50.0,
+        decay: 10.0,
+        buffer_size: 65535,
+        analysis: Analysis::Fft,
+        hpss: false,
+    }
+}
+
+/// White noise; uses a fixed linear congruential generator to be reproducible.
+fn noise(len: usize) -> Vec<f32> {
+    let mut state: u32 = 12345;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// Bass drum: sine sweeping down from 120 Hz to 50 Hz with a fast decay.
+fn kick(len: usize) -> Vec<f32> {
+    let mut phase = 0.0;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / RATE as f32;
+            let frequency = 50.0 + 70.0 * (-t * 40.0).exp();
+            phase += 2.0 * PI * frequency / RATE as f32;
+            phase.sin() * (-t * 12.0).exp()
+        })
+        .collect()
+}
+
+/// Snare drum: 200 Hz body and low-passed noise.
+fn snare(len: usize) -> Vec<f32> {
+    let mut lowpassed = 0.0;
+    noise(len)
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            let t = i as f32 / RATE as f32;
+            lowpassed += 0.3 * (n - lowpassed);
+            let body = 0.4 * (2.0 * PI * 200.0 * t).sin() * (-t * 30.0).exp();
+            body + 0.8 * lowpassed * (-t * 20.0).exp()
+        })
+        .collect()
+}
+
+/// Hi-hat: high-passed noise with a very fast decay.
+fn hihat(len: usize) -> Vec<f32> {
+    let noise = noise(len + 1);
+    noise
+        .windows(2)
+        .enumerate()
+        .map(|(i, n)| {
+            let t = i as f32 / RATE as f32;
+            0.5 * (n[1] - n[0]) * (-t * 60.0).exp()
+        })
+        .collect()
+}
+
+/// Interleaved stereo samples of the drum hit preceded and followed by silence.
+fn hit(drum: fn(usize) -> Vec<f32>) -> Vec<i16> {
+    let silence = RATE as usize / 5;
+    let mut samples = vec![0.0; silence];
+    samples.extend(drum(RATE as usize / 2));
+    samples.extend(vec![0.0; silence]);
+    samples
+        .iter()
+        .flat_map(|s| {
+            let sample = (s * 16000.0) as i16;
+            vec![sample, sample]
+        })
+        .collect()
+}
+
+/// All drum events classified in the left channel.
+fn drums(samples: &[i16]) -> Vec<DrumEvent> {
+    analyze(samples, RATE, settings())
+        .iter()
+        .flat_map(|frame| frame.left.drums.clone())
+        .collect()
+}
+
+#[test]
+fn test_kick() {
+    assert_eq!(drums(&hit(kick)), vec![DrumEvent::Kick]);
+}
+
+#[test]
+fn test_snare() {
+    assert_eq!(drums(&hit(snare)), vec![DrumEvent::Snare]);
+}
+
+#[test]
+fn test_hihat() {
+    assert_eq!(drums(&hit(hihat)), vec![DrumEvent::HiHat]);
+}
+
+#[test]
+fn test_kick_and_hihat() {
+    fn kick_and_hihat(len: usize) -> Vec<f32> {
+        kick(len)
+            .iter()
+            .zip(hihat(len).iter())
+            .map(|(k, h)| k + h)
+            .collect()
+    }
+    let events = drums(&hit(kick_and_hihat));
+    assert!(events.contains(&DrumEvent::Kick), "{:?}", events);
+    assert!(events.contains(&DrumEvent::HiHat), "{:?}", events);
+    assert!(!events.contains(&DrumEvent::Snare), "{:?}", events);
+}
+
+#[test]
+fn test_no_drums_in_tone() {
+    let samples: Vec<i16> = (0..RATE as usize)
+        .flat_map(|i| {
+            let sample = (2.0 * PI * 440.0 * i as f32 / RATE as f32).sin() * 16000.0;
+            vec![sample as i16, sample as i16]
+        })
+        .collect();
+    // the start of the tone is an onset, but the sustained tone must not be classified
+    let frames = analyze(&samples, RATE, settings());
+    assert!(frames
+        .iter()
+        .filter(|frame| frame.time > 0.2)
+        .all(|frame| frame.left.drums.is_empty()));
+}
@@ -0,0 +1,234 @@
+use led_speakers::transform::{AudioTransformer, TransformedAudio};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+use std::env;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::PathBuf;
+
+const RATE: u32 = 44100;
+const FFT_LEN: usize = 3000;
+const TOTAL_BANDS: usize = 6;
+const LOWER_CUTOFF: f32 = 50.0;
+const UPPER_CUTOFF: f32 = 10000.0;
+const MONSTERCAT: f32 = 50.0;
+const DECAY: f32 = 10.0;
+
+/// Number of frames each signal is fed through the transformation;
+/// scaling is based on the maximum magnitudes of the previous `FFT_LEN` frames
+const FRAMES: usize = FFT_LEN;
+
+/// Every n-th frame is recorded in the snapshot
+const SNAPSHOT_INTERVAL: usize = 250;
+
+/// Maximum difference between snapshot and computed band magnitudes
+const SNAPSHOT_TOLERANCE: f32 = 0.01;
+
+/// Samples of a sine wave with the provided frequency.
+fn tone(frequency: f32) -> Vec<f32> {
+    (0..FFT_LEN)
+        .map(|i| (2.0 * PI * frequency * i as f32 / RATE as f32).sin())
+        .collect()
+}
+
+/// Samples of two sine waves with the provided frequencies.
+fn two_tone(a: f32, b: f32) -> Vec<f32> {
+    tone(a)
+        .iter()
+        .zip(tone(b).iter())
+        .map(|(a, b)| (a + b) / 2.0)
+        .collect()
+}
+
+/// Full-scale white noise; uses a fixed linear congruential generator to be reproducible.
+fn noise() -> Vec<f32> {
+    let mut state: u32 = 12345;
+    (0..FFT_LEN)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// FFT magnitudes of the provided samples.
+fn fft_magnitudes(samples: &[f32]) -> Vec<f32> {
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(FFT_LEN);
+    let mut buffer: Vec<Complex<f32>> = samples.iter().map(|s| Complex::new(*s, 0.0)).collect();
+    fft.process(&mut buffer);
+    buffer.iter().map(|c| c.norm_sqr().sqrt()).collect()
+}
+
+fn cutoff_frequencies() -> (Vec<usize>, Vec<usize>) {
+    AudioTransformer::cutoff_frequencies(TOTAL_BANDS, LOWER_CUTOFF, UPPER_CUTOFF, RATE, FFT_LEN)
+}
+
+/// Feeds the samples repeatedly through `frequency_magnitudes` and returns the bands of each frame.
+fn transform(samples: &[f32]) -> Vec<Vec<f32>> {
+    let magnitudes = fft_magnitudes(samples);
+    let (lower_cutoff_freq, upper_cutoff_freq) = cutoff_frequencies();
+    let mut transformed_audio = TransformedAudio::new(TOTAL_BANDS, FFT_LEN);
+    let mut bands = vec![0.0; TOTAL_BANDS];
+
+    (0..FRAMES)
+        .map(|_| {
+            AudioTransformer::frequency_magnitudes(
+                &magnitudes,
+                &mut bands,
+                &mut transformed_audio,
+                &lower_cutoff_freq,
+                &upper_cutoff_freq,
+                MONSTERCAT,
+                DECAY,
+            );
+            bands.clone()
+        })
+        .collect()
+}
+
+/// Index of the band the FFT bin of the provided frequency is merged into.
+fn expected_band(frequency: f32) -> usize {
+    let bin = (frequency * FFT_LEN as f32 / RATE as f32).round() as usize;
+    let (lower_cutoff_freq, upper_cutoff_freq) = cutoff_frequencies();
+    (0..TOTAL_BANDS)
+        .find(|n| lower_cutoff_freq[*n] <= bin && bin <= upper_cutoff_freq[*n])
+        .unwrap()
+}
+
+/// Index of the band with the largest magnitude.
+fn loudest_band(bands: &[f32]) -> usize {
+    (0..bands.len())
+        .max_by(|a, b| bands[*a].partial_cmp(&bands[*b]).unwrap())
+        .unwrap()
+}
+
+/// Asserts that all band magnitudes are within the documented range of 0 to 100.
+fn assert_in_range(frames: &[Vec<f32>]) {
+    for bands in frames {
+        for band in bands {
+            assert!(
+                band.is_finite() && *band >= 0.0 && *band <= 100.0,
+                "band magnitude {} out of range in {:?}",
+                band,
+                bands
+            );
+        }
+    }
+}
+
+/// Compares the recorded frames with the snapshot file `tests/snapshots/<name>.txt`.
+///
+/// Snapshot files are written if they don't exist yet or if `UPDATE_SNAPSHOTS` is set.
+fn assert_snapshot(name: &str, frames: &[Vec<f32>]) {
+    let snapshot = frames
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| (i + 1) % SNAPSHOT_INTERVAL == 0 || *i == 0)
+        .map(|(i, bands)| {
+            let bands: Vec<String> = bands.iter().map(|b| format!("{:.3}", b)).collect();
+            format!("{}: {}", i, bands.join(" "))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+        + "\n";
+
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("txt");
+
+    if env::var("UPDATE_SNAPSHOTS").is_ok() || !path.exists() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, snapshot).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap();
+    let parse = |s: &str| -> Vec<f32> {
+        s.split_whitespace()
+            .skip(1)
+            .map(|b| b.parse::<f32>().unwrap())
+            .collect()
+    };
+    assert_eq!(
+        expected.lines().count(),
+        snapshot.lines().count(),
+        "snapshot {} has a different number of frames",
+        name
+    );
+    for (expected_line, line) in expected.lines().zip(snapshot.lines()) {
+        let matches = parse(expected_line)
+            .iter()
+            .zip(parse(line).iter())
+            .all(|(a, b)| (a - b).abs() <= SNAPSHOT_TOLERANCE);
+        assert!(
+            matches,
+            "snapshot {} changed\nexpected: {}\n  actual: {}\nrun with UPDATE_SNAPSHOTS=1 if this is intended",
+            name,
+            expected_line,
+            line
+        );
+    }
+}
+
+#[test]
+fn golden_silence() {
+    let frames = transform(&vec![0.0; FFT_LEN]);
+    assert_in_range(&frames);
+    for bands in &frames {
+        assert_eq!(bands, &vec![0.0; TOTAL_BANDS]);
+    }
+    assert_snapshot("silence", &frames);
+}
+
+#[test]
+fn golden_single_tones() {
+    for frequency in [100.0, 300.0, 900.0, 2000.0, 5000.0, 12000.0].iter() {
+        let frames = transform(&tone(*frequency));
+        assert_in_range(&frames);
+
+        let bands = frames.last().unwrap();
+        assert_eq!(
+            loudest_band(bands),
+            expected_band(*frequency),
+            "unexpected band for {} Hz: {:?}",
+            frequency,
+            bands
+        );
+        assert_snapshot(&format!("tone_{}", frequency), &frames);
+    }
+}
+
+#[test]
+fn golden_two_tone() {
+    let (low, high) = (100.0, 2000.0);
+    let frames = transform(&two_tone(low, high));
+    assert_in_range(&frames);
+
+    // both tones are louder than all other bands
+    let bands = frames.last().unwrap();
+    let mut sorted: Vec<usize> = (0..TOTAL_BANDS).collect();
+    sorted.sort_by(|a, b| bands[*b].partial_cmp(&bands[*a]).unwrap());
+    let mut loudest = sorted[..2].to_vec();
+    loudest.sort_unstable();
+    assert_eq!(
+        loudest,
+        vec![expected_band(low), expected_band(high)],
+        "{:?}",
+        bands
+    );
+    assert_snapshot("two_tone", &frames);
+}
+
+#[test]
+fn golden_noise() {
+    let frames = transform(&noise());
+    assert_in_range(&frames);
+
+    // noise is spread across the whole spectrum
+    let bands = frames.last().unwrap();
+    assert!(bands.iter().all(|b| *b > 0.0), "{:?}", bands);
+    assert_snapshot("noise", &frames);
+}
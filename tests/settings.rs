@@ -1,5 +1,10 @@
-use led_speakers::settings::Settings;
-use led_speakers::theme::{Color, Theme};
+use led_speakers::settings::{
+    persist_setting, OutputSettings, PlaylistAdvance, PlaylistSettings, Settings, MAX_FPS,
+    MIN_ENTRY_DURATION,
+};
+use led_speakers::theme::{Color, Theme, ThemeMode};
+
+use std::fs;
 
 #[test]
 fn settings_from_json() {
@@ -27,7 +32,8 @@ fn settings_from_json() {
         settings.themes[0],
         Theme {
             name: "test".to_string(),
-            colors: vec![Color { r: 0, g: 0, b: 255 }, Color { r: 1, g: 1, b: 1 }]
+            colors: vec![Color { r: 0, g: 0, b: 255 }, Color { r: 1, g: 1, b: 1 }],
+            mode: ThemeMode::Bands,
         }
     );
     assert_eq!(
@@ -39,3 +45,136 @@ fn settings_from_json() {
         "rotating_viz".to_string()
     );
 }
+
+#[test]
+fn playlist_settings_defaults() {
+    let playlist: PlaylistSettings =
+        serde_json::from_str(r#"{"entries": [{"viz": "solid_viz"}]}"#).unwrap();
+
+    assert!(!playlist.enabled);
+    assert!(!playlist.shuffle);
+    assert_eq!(playlist.advance, PlaylistAdvance::Timer);
+    assert_eq!(playlist.entries[0].theme, None);
+    assert_eq!(playlist.entries[0].duration, 60.0);
+    assert_eq!(playlist.entries[0].beats, 64);
+}
+
+#[test]
+fn playlist_settings_reject_invalid_entries() {
+    let playlist = |entry: &str| -> serde_json::Result<PlaylistSettings> {
+        serde_json::from_str(&format!(r#"{{"entries": [{}]}}"#, entry))
+    };
+
+    assert!(playlist(r#"{"viz": "solid_viz", "duration": 0}"#).is_err());
+    assert!(playlist(r#"{"viz": "solid_viz", "duration": -10}"#).is_err());
+    assert!(playlist(r#"{"viz": "solid_viz", "duration": 0.5}"#).is_err());
+    assert!(playlist(r#"{"viz": "solid_viz", "beats": 0}"#).is_err());
+
+    let valid = playlist(r#"{"viz": "solid_viz", "duration": 1, "beats": 1}"#).unwrap();
+    assert_eq!(valid.entries[0].duration, MIN_ENTRY_DURATION);
+    assert_eq!(valid.entries[0].beats, 1);
+}
+
+#[test]
+fn output_settings_limit_fps() {
+    let output = |fps: &str| -> OutputSettings {
+        serde_json::from_str(&format!(
+            r#"{{
+                "left": {{"spi": "/dev/spidev0.0", "total_leds": 10}},
+                "right": {{"spi": "/dev/spidev0.1", "total_leds": 10}},
+                "fps": {}
+            }}"#,
+            fps
+        ))
+        .unwrap()
+    };
+
+    assert_eq!(output("30").fps, 30.0);
+    assert_eq!(output("10000").fps, MAX_FPS);
+    assert_eq!(output("0").fps, 60.0);
+    assert_eq!(output("-5").fps, 60.0);
+}
+
+#[test]
+fn persist_setting_keeps_config() {
+    let path =
+        std::env::temp_dir().join(format!("led_speakers_config_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    fs::write(
+        path,
+        "{\n    \"server_port\": \"8000\",\n    \"layers\": []\n}\n",
+    )
+    .unwrap();
+
+    let playlist = PlaylistSettings {
+        enabled: true,
+        ..PlaylistSettings::default()
+    };
+    persist_setting(path, "playlist", &playlist).unwrap();
+    persist_setting(path, "server_port", &"8080").unwrap();
+    let config = fs::read_to_string(path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // existing keys keep their order and new keys are appended
+    let expected = r#"{
+    "server_port": "8080",
+    "layers": [],
+    "playlist": {
+        "enabled": true,
+        "entries": [],
+        "shuffle": false,
+        "advance": "timer"
+    }
+}
+"#;
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn safety_settings_disable_strobe() {
+    let mut config: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("config.json").unwrap()).unwrap();
+
+    let settings: Settings = serde_json::from_value(config.clone()).unwrap();
+    assert!(settings.is_available("strobe_viz"));
+    assert!(settings.visualization("strobe_viz").is_some());
+
+    config["safety"]["strobe_enabled"] = serde_json::Value::Bool(false);
+    let settings: Settings = serde_json::from_value(config).unwrap();
+    assert!(!settings.is_available("strobe_viz"));
+    assert!(settings.visualization("strobe_viz").is_none());
+    assert!(settings.visualization("rotating_viz").is_some());
+    assert!(settings
+        .available_visualizations()
+        .all(|v| v.get_name() != "strobe_viz"));
+}
+
+#[test]
+fn settings_rescan_scripts() {
+    let mut settings: Settings =
+        serde_json::from_str(&fs::read_to_string("config.json").unwrap()).unwrap();
+    let dir = std::env::temp_dir().join(format!("led_speakers_rescan_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    settings.scripts.dir = dir.to_str().unwrap().to_string();
+    let names = |settings: &Settings| -> Vec<String> {
+        settings
+            .available_visualizations()
+            .map(|v| v.get_name().to_string())
+            .filter(|name| name.starts_with("script_"))
+            .collect()
+    };
+
+    fs::write(dir.join("kept.rhai"), "[]").unwrap();
+    fs::write(dir.join("deleted.rhai"), "[]").unwrap();
+    settings.load_scripts();
+    assert_eq!(names(&settings), vec!["script_deleted", "script_kept"]);
+
+    // loaded scripts aren't compiled again, deleted scripts are removed and new scripts are added
+    fs::write(dir.join("kept.rhai"), "let result = [").unwrap();
+    fs::remove_file(dir.join("deleted.rhai")).unwrap();
+    fs::write(dir.join("added.rhai"), "[]").unwrap();
+    settings.load_scripts();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(names(&settings), vec!["script_kept", "script_added"]);
+}
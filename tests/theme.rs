@@ -1,4 +1,5 @@
-use led_speakers::theme::{Color, Theme};
+use led_speakers::features::{AudioFeatures, Key};
+use led_speakers::theme::{Color, Theme, ThemeMode};
 
 #[test]
 fn theme_from_json() {
@@ -25,3 +26,69 @@ fn color_from_vec() {
     assert_eq!(color.g, 2);
     assert_eq!(color.b, 3);
 }
+
+#[test]
+fn theme_harmonic_colors() {
+    let colors = (0..12)
+        .map(|i| Color { r: i, g: 1, b: 1 })
+        .collect::<Vec<Color>>();
+    let theme = Theme {
+        name: "test".to_string(),
+        colors: colors.clone(),
+        mode: ThemeMode::Harmonic,
+    };
+
+    let mut features = AudioFeatures::new(1);
+    features.key = Key {
+        root: 9,
+        minor: true,
+        confidence: 1.0,
+    };
+    let harmonic_colors = theme.colors_for(&features);
+    assert_eq!(harmonic_colors[0], colors[9]);
+    assert_eq!(harmonic_colors[3], colors[0]);
+
+    let theme = Theme {
+        mode: ThemeMode::Bands,
+        ..theme
+    };
+    assert_eq!(theme.colors_for(&features), colors);
+}
+
+#[test]
+fn theme_harmonic_colors_follow_chroma() {
+    let colors = (0..6)
+        .map(|i| Color { r: i, g: 1, b: 1 })
+        .collect::<Vec<Color>>();
+    let theme = Theme {
+        name: "test".to_string(),
+        colors: colors.clone(),
+        mode: ThemeMode::Harmonic,
+    };
+
+    // C major chord in the key of C
+    let mut features = AudioFeatures::new(1);
+    features.chroma = vec![0.0; 12];
+    features.chroma[0] = 1.0;
+    features.chroma[4] = 0.8;
+    features.chroma[7] = 0.6;
+    let harmonic_colors = theme.colors_for(&features);
+    assert_eq!(harmonic_colors[0], colors[0]);
+    assert_eq!(harmonic_colors[1], colors[2]);
+    assert_eq!(harmonic_colors[2], colors[3]);
+
+    // G major chord changes the color without a key change
+    features.chroma = vec![0.0; 12];
+    features.chroma[7] = 1.0;
+    features.chroma[11] = 0.8;
+    features.chroma[2] = 0.6;
+    let harmonic_colors = theme.colors_for(&features);
+    assert_eq!(harmonic_colors[0], colors[3]);
+    assert_eq!(harmonic_colors[1], colors[5]);
+    assert_eq!(harmonic_colors[2], colors[1]);
+
+    // every theme color is used exactly once
+    let mut sorted = harmonic_colors.clone();
+    sorted.sort_by_key(|c| c.r);
+    assert_eq!(sorted, colors);
+}
@@ -1,4 +1,11 @@
-use led_speakers::transform::{AudioTransformer, TransformedAudio};
+use led_speakers::settings::{Analysis, TransformerSettings};
+use led_speakers::transform::{
+    AudioTransformer, ConstantQKernel, FrameTransformer, Hpss, TransformedAudio,
+};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+use std::f32::consts::PI;
 
 #[test]
 fn test_cutoff_frequencies() {
@@ -24,11 +31,11 @@ fn test_magnitudes() {
     let upper_cutoff_freq = vec![2, 4];
     let lower_cutoff_freq = vec![0, 3];
     let input = vec![1000.0, 1000.0, 1000.0, 1000.0, 1000.0];
-    AudioTransformer::magnitudes(&mut bands, input, &lower_cutoff_freq, &upper_cutoff_freq);
+    AudioTransformer::magnitudes(&mut bands, &input, &lower_cutoff_freq, &upper_cutoff_freq);
     assert_eq!(bands, vec![223.6068, 281.51044]);
 
     let input = vec![1000.0, 1000.0, 0.0, 0.0, 0.0];
-    AudioTransformer::magnitudes(&mut bands, input, &lower_cutoff_freq, &upper_cutoff_freq);
+    AudioTransformer::magnitudes(&mut bands, &input, &lower_cutoff_freq, &upper_cutoff_freq);
     assert_eq!(bands, vec![182.57419, 0.0]);
 }
 
@@ -86,3 +93,462 @@ fn test_falloff() {
     assert_eq!(transformed_audio.bands, vec![90.0, 90.0, 1.0]);
     assert_eq!(transformed_audio.falloff, vec![2.0, 2.0, 1.0]);
 }
+
+#[test]
+fn test_rms_and_peak() {
+    let samples = vec![0.5, -0.5, 0.5, -0.5];
+    assert_eq!(AudioTransformer::rms(&samples), 0.5);
+    assert_eq!(AudioTransformer::peak(&samples), 0.5);
+
+    let samples = vec![0.0, -1.0, 0.25];
+    assert_eq!(AudioTransformer::peak(&samples), 1.0);
+    assert_eq!(AudioTransformer::rms(&vec![]), 0.0);
+}
+
+#[test]
+fn test_spectral_centroid() {
+    let rate = 48000;
+    let fft_len = 8;
+    let spectrum = vec![0.0, 1.0, 0.0, 1.0];
+    assert_eq!(
+        AudioTransformer::spectral_centroid(&spectrum, rate, fft_len),
+        12000.0
+    );
+    assert_eq!(
+        AudioTransformer::spectral_centroid(&vec![0.0; 4], rate, fft_len),
+        0.0
+    );
+}
+
+#[test]
+fn test_dominant_frequency() {
+    let rate = 48000;
+    let fft_len = 8;
+    let spectrum = vec![0.0, 0.2, 0.8, 0.1];
+    assert_eq!(
+        AudioTransformer::dominant_frequency(&spectrum, rate, fft_len),
+        12000.0
+    );
+}
+
+#[test]
+fn test_spectral_flux() {
+    let mut transformed_audio = TransformedAudio::new(1, 6);
+
+    let flux = AudioTransformer::spectral_flux(&vec![1.0, 0.0, 0.5], &mut transformed_audio);
+    assert_eq!(flux, 1.5);
+    assert_eq!(transformed_audio.spectrum, vec![1.0, 0.0, 0.5]);
+
+    // decreasing magnitudes do not contribute to flux
+    let flux = AudioTransformer::spectral_flux(&vec![0.0, 0.25, 0.5], &mut transformed_audio);
+    assert_eq!(flux, 0.25);
+}
+
+#[test]
+fn test_onset() {
+    let mut transformed_audio = TransformedAudio::new(1, 6);
+
+    // silence does not trigger onsets
+    for _ in 0..10 {
+        assert!(!AudioTransformer::onset(0.0, &mut transformed_audio));
+    }
+
+    // sudden increase in flux triggers a single onset
+    assert!(AudioTransformer::onset(1.0, &mut transformed_audio));
+    assert!(!AudioTransformer::onset(1.0, &mut transformed_audio));
+    assert_eq!(transformed_audio.flux_history[0], 1.0);
+    assert_eq!(transformed_audio.flux_history[1], 1.0);
+}
+
+#[test]
+fn test_features() {
+    let rate = 48000;
+    let fft_len = 64;
+    let total_bands = 2;
+    let mut transformed_audio = TransformedAudio::new(total_bands, fft_len);
+
+    // sine wave matching the frequency of FFT bin 8
+    let samples: Vec<f32> = (0..fft_len)
+        .map(|i| (2.0 * PI * 8.0 * i as f32 / fft_len as f32).sin())
+        .collect();
+    let mut magnitudes = vec![0.0; fft_len];
+    magnitudes[8] = fft_len as f32 / 2.0;
+    magnitudes[fft_len - 8] = fft_len as f32 / 2.0;
+    let mut spectrum = vec![0.0; fft_len / 2];
+    AudioTransformer::normalize_spectrum(&magnitudes, &mut spectrum, fft_len);
+    assert_eq!(spectrum[8], 1.0);
+
+    let features = AudioTransformer::features(
+        &samples,
+        &spectrum,
+        fft_len,
+        vec![100.0, 0.0],
+        &mut transformed_audio,
+        rate,
+    );
+    assert_eq!(features.bands, vec![100.0, 0.0]);
+    assert_eq!(features.dominant_frequency, 6000.0);
+    assert_eq!(features.spectral_centroid, 6000.0);
+    assert_eq!(features.spectral_flux, 1.0);
+    assert!(features.onset);
+    assert!((features.rms - 0.5f32.sqrt()).abs() < 0.001);
+    assert!((features.peak - 1.0).abs() < 0.001);
+}
+
+#[test]
+fn test_stereo_features() {
+    let lower_cutoff_freq = vec![0, 2];
+    let upper_cutoff_freq = vec![1, 4];
+    let left_samples = vec![0.5, -0.5, 0.5, -0.5];
+    let left_fft = vec![
+        Complex::new(0.0, 0.0),
+        Complex::new(4.0, 0.0),
+        Complex::new(0.0, 0.0),
+        Complex::new(0.0, 0.0),
+        Complex::new(0.0, 0.0),
+        Complex::new(0.0, 0.0),
+        Complex::new(0.0, 0.0),
+        Complex::new(0.0, 0.0),
+    ];
+
+    // mono signal
+    let stereo = AudioTransformer::stereo_features(
+        &left_samples,
+        &left_samples,
+        &left_fft,
+        &left_fft,
+        8,
+        &lower_cutoff_freq,
+        &upper_cutoff_freq,
+    );
+    assert_eq!(stereo.balance, 0.0);
+    assert_eq!(stereo.correlation, 1.0);
+    assert_eq!(stereo.mid_bands, vec![1.0, 0.0]);
+    assert_eq!(stereo.side_bands, vec![0.0, 0.0]);
+
+    // out of phase signal
+    let right_samples: Vec<f32> = left_samples.iter().map(|s| -s).collect();
+    let right_fft: Vec<Complex<f32>> = left_fft.iter().map(|c| -c).collect();
+    let stereo = AudioTransformer::stereo_features(
+        &left_samples,
+        &right_samples,
+        &left_fft,
+        &right_fft,
+        8,
+        &lower_cutoff_freq,
+        &upper_cutoff_freq,
+    );
+    assert_eq!(stereo.correlation, -1.0);
+    assert_eq!(stereo.mid_bands, vec![0.0, 0.0]);
+    assert_eq!(stereo.side_bands, vec![1.0, 0.0]);
+
+    // signal panned to the left
+    let silence = vec![0.0; 4];
+    let silence_fft = vec![Complex::new(0.0, 0.0); 8];
+    let stereo = AudioTransformer::stereo_features(
+        &left_samples,
+        &silence,
+        &left_fft,
+        &silence_fft,
+        8,
+        &lower_cutoff_freq,
+        &upper_cutoff_freq,
+    );
+    assert_eq!(stereo.balance, -1.0);
+    assert_eq!(stereo.correlation, 0.0);
+}
+
+#[test]
+fn test_chroma() {
+    let rate = 48000;
+    let fft_len = 4800;
+
+    // bin 44 corresponds to 440 Hz which is an A
+    let mut spectrum = vec![0.0; fft_len / 2];
+    spectrum[44] = 1.0;
+    spectrum[26] = 0.5;
+    let chroma = AudioTransformer::chroma(&spectrum, rate, fft_len);
+    assert_eq!(chroma.len(), 12);
+    assert_eq!(chroma[9], 1.0);
+    assert_eq!(chroma[0], 0.25);
+
+    // frequencies outside of the chroma range are ignored
+    let mut spectrum = vec![0.0; fft_len / 2];
+    spectrum[2] = 1.0;
+    assert_eq!(AudioTransformer::chroma(&spectrum, rate, fft_len), vec![0.0; 12]);
+}
+
+#[test]
+fn test_estimate_key() {
+    let mut transformed_audio = TransformedAudio::new(1, 2);
+
+    // silence does not result in a key
+    let key = AudioTransformer::estimate_key(&vec![0.0; 12], &mut transformed_audio);
+    assert_eq!(key.confidence, 0.0);
+
+    // C major triad
+    let mut chroma = vec![0.0; 12];
+    chroma[0] = 1.0;
+    chroma[4] = 0.8;
+    chroma[7] = 0.9;
+    let key = AudioTransformer::estimate_key(&chroma, &mut transformed_audio);
+    assert_eq!(key.root, 0);
+    assert!(!key.minor);
+
+    // A minor triad
+    let mut transformed_audio = TransformedAudio::new(1, 2);
+    let mut chroma = vec![0.0; 12];
+    chroma[9] = 1.0;
+    chroma[0] = 0.8;
+    chroma[4] = 0.9;
+    let key = AudioTransformer::estimate_key(&chroma, &mut transformed_audio);
+    assert_eq!(key.root, 9);
+    assert!(key.minor);
+}
+
+#[test]
+fn test_frame_transformer() {
+    let rate = 48000;
+    let fft_len = 480;
+    let settings = TransformerSettings {
+        sink: "".to_string(),
+        fft_len,
+        total_bands: 4,
+        lower_cutoff: 50.0,
+        upper_cutoff: 10000.0,
+        monstercat: 50.0,
+        decay: 10.0,
+        buffer_size: 0,
+        analysis: Analysis::Fft,
+        hpss: false,
+    };
+    let mut frame_transformer = FrameTransformer::new(settings, rate);
+
+    // 1000 Hz sine on the left channel, silence on the right channel
+    let mut samples: Vec<i16> = vec![0; fft_len * 2];
+    for i in 0..fft_len {
+        let sample = (2.0 * PI * 1000.0 * i as f32 / rate as f32).sin();
+        samples[i * 2 + 1] = (sample * i16::max_value() as f32) as i16;
+    }
+
+    let (left, right) = frame_transformer.process(&samples);
+    assert_eq!(left.dominant_frequency, 1000.0);
+    assert_eq!(right.rms, 0.0);
+    assert_eq!(left.stereo.balance, -1.0);
+    assert_eq!(left.stereo, right.stereo);
+
+    // magnitudes of the real-input FFT match the magnitudes of a complex FFT
+    let mut complex_input: Vec<Complex<f32>> = frame_transformer
+        .left
+        .samples
+        .iter()
+        .map(|s| Complex::new(*s, 0.0))
+        .collect();
+    FftPlanner::new()
+        .plan_fft_forward(fft_len)
+        .process(&mut complex_input);
+    for (magnitude, c) in frame_transformer
+        .left
+        .magnitudes
+        .iter()
+        .zip(complex_input.iter())
+    {
+        assert!((magnitude - c.norm()).abs() < 0.01);
+    }
+}
+
+#[test]
+fn test_constant_q_kernel() {
+    let rate = 44100;
+    let fft_len = 4096;
+    let kernel = ConstantQKernel::new(4, 100.0, 1600.0, rate, fft_len);
+    assert_eq!(kernel.kernels.len(), 4);
+
+    // kernels are sparse and higher bands cover more FFT bins
+    for (lower, upper) in kernel.kernels.iter().zip(kernel.kernels.iter().skip(1)) {
+        assert!(!lower.is_empty());
+        assert!(lower.len() <= upper.len());
+        assert!(lower.last().unwrap().0 < upper.last().unwrap().0);
+    }
+    assert!(kernel.kernels[3].len() < fft_len / 8);
+}
+
+#[test]
+fn test_constant_q_magnitudes() {
+    let rate = 44100;
+    let fft_len = 4096;
+    let total_bands = 4;
+    let kernel = ConstantQKernel::new(total_bands, 100.0, 1600.0, rate, fft_len);
+    let fft = FftPlanner::new().plan_fft_forward(fft_len);
+
+    // a tone at the center frequency of each band has the largest magnitude in that band
+    for (n, frequency) in [141.0, 283.0, 566.0, 1131.0].iter().enumerate() {
+        let mut input: Vec<Complex<f32>> = (0..fft_len)
+            .map(|i| Complex::new((2.0 * PI * frequency * i as f32 / rate as f32).sin(), 0.0))
+            .collect();
+        fft.process(&mut input);
+
+        let mut bands = vec![0.0; total_bands];
+        AudioTransformer::constant_q_magnitudes(&mut bands, &input, &kernel);
+        let loudest = (0..total_bands)
+            .max_by(|a, b| bands[*a].partial_cmp(&bands[*b]).unwrap())
+            .unwrap();
+        assert_eq!(loudest, n, "{} Hz: {:?}", frequency, bands);
+    }
+
+    let mut bands = vec![0.0; total_bands];
+    let silence = vec![Complex::new(0.0, 0.0); fft_len];
+    AudioTransformer::constant_q_magnitudes(&mut bands, &silence, &kernel);
+    assert_eq!(bands, vec![0.0; total_bands]);
+}
+
+#[test]
+fn test_frame_transformer_constant_q() {
+    let rate = 44100;
+    let fft_len = 3000;
+    let settings = TransformerSettings {
+        sink: "".to_string(),
+        fft_len,
+        total_bands: 6,
+        lower_cutoff: 50.0,
+        upper_cutoff: 10000.0,
+        monstercat: 50.0,
+        decay: 10.0,
+        buffer_size: 0,
+        analysis: Analysis::ConstantQ,
+        hpss: false,
+    };
+    let mut frame_transformer = FrameTransformer::new(settings, rate);
+
+    let mut samples: Vec<i16> = vec![0; fft_len * 2];
+    for i in 0..fft_len {
+        let sample = (2.0 * PI * 1000.0 * i as f32 / rate as f32).sin();
+        samples[i * 2 + 1] = (sample * i16::MAX as f32) as i16;
+    }
+
+    let (left, right) = frame_transformer.process(&samples);
+    assert_eq!(left.bands.len(), 6);
+    assert!(left.bands.iter().all(|b| *b >= 0.0 && *b <= 100.0));
+    assert_eq!(right.bands, vec![0.0; 6]);
+}
+
+#[test]
+fn test_a_weighting() {
+    assert!((AudioTransformer::a_weighting(1000.0) - 1.0).abs() < 0.01);
+
+    // -19.1 dB at 100 Hz, +1.2 dB at 2.5 kHz, -2.5 dB at 10 kHz
+    let db = |frequency: f32| 20.0 * AudioTransformer::a_weighting(frequency).log10();
+    assert!((db(100.0) + 19.1).abs() < 0.1);
+    assert!((db(2500.0) - 1.3).abs() < 0.1);
+    assert!((db(10000.0) + 2.5).abs() < 0.1);
+    assert_eq!(AudioTransformer::a_weighting(0.0), 0.0);
+}
+
+#[test]
+fn test_loudness() {
+    let rate = 48000;
+    let fft_len = 480;
+
+    // silence
+    let mut transformed_audio = TransformedAudio::new(2, fft_len);
+    let spectrum = vec![0.0; fft_len / 2];
+    let loudness = AudioTransformer::loudness(&spectrum, rate, fft_len, &mut transformed_audio);
+    assert_eq!(loudness, -70.0);
+
+    // full scale 1 kHz sine; loudness builds up over the momentary window
+    let mut spectrum = vec![0.0; fft_len / 2];
+    spectrum[10] = 1.0;
+    let first = AudioTransformer::loudness(&spectrum, rate, fft_len, &mut transformed_audio);
+    let mut loudness = first;
+    for _ in 0..200 {
+        loudness = AudioTransformer::loudness(&spectrum, rate, fft_len, &mut transformed_audio);
+    }
+    assert!(first < loudness);
+    assert!((loudness + 3.01).abs() < 0.1);
+
+    // low frequencies are perceived as less loud
+    let mut transformed_audio = TransformedAudio::new(2, fft_len);
+    let mut spectrum = vec![0.0; fft_len / 2];
+    spectrum[1] = 1.0;
+    for _ in 0..200 {
+        loudness = AudioTransformer::loudness(&spectrum, rate, fft_len, &mut transformed_audio);
+    }
+    assert!((loudness + 3.01 + 19.1).abs() < 0.2);
+}
+
+#[test]
+fn test_hpss() {
+    let fft_len = 64;
+    let output_len = fft_len / 2 + 1;
+    let mut hpss = Hpss::new(2, fft_len);
+
+    // sustained tone in FFT bin 8
+    let mut fft_output = vec![Complex::new(0.0, 0.0); output_len];
+    fft_output[8] = Complex::new(10.0, 0.0);
+    let magnitudes: Vec<f32> = (0..fft_len)
+        .map(|i| fft_output[i.min(fft_len - i)].norm())
+        .collect();
+    for _ in 0..100 {
+        hpss.separate(&fft_output, &magnitudes);
+    }
+    assert!((hpss.harmonic_magnitudes[8] - 10.0).abs() < 0.01);
+    assert!(hpss.percussive_magnitudes[8] < 0.01);
+    assert_eq!(hpss.harmonic_magnitudes[fft_len - 8], hpss.harmonic_magnitudes[8]);
+    assert_eq!(hpss.harmonic_output[8], Complex::new(hpss.harmonic_magnitudes[8], 0.0));
+
+    // broadband click on top of the tone
+    let click_output: Vec<Complex<f32>> = fft_output
+        .iter()
+        .map(|c| c + Complex::new(5.0, 0.0))
+        .collect();
+    let click_magnitudes: Vec<f32> = (0..fft_len)
+        .map(|i| click_output[i.min(fft_len - i)].norm())
+        .collect();
+    hpss.separate(&click_output, &click_magnitudes);
+    assert!((hpss.percussive_magnitudes[20] - 5.0).abs() < 0.01);
+    assert!(hpss.harmonic_magnitudes[20] < 0.01);
+    assert!(hpss.harmonic_magnitudes[8] > hpss.percussive_magnitudes[8]);
+
+    // masks split the magnitudes
+    for bin in 0..fft_len {
+        let total = hpss.percussive_magnitudes[bin] + hpss.harmonic_magnitudes[bin];
+        assert!((total - click_magnitudes[bin]).abs() < 0.01);
+    }
+}
+
+#[test]
+fn test_frame_transformer_hpss() {
+    let rate = 44100;
+    let fft_len = 1024;
+    let settings = TransformerSettings {
+        sink: "".to_string(),
+        fft_len,
+        total_bands: 4,
+        lower_cutoff: 50.0,
+        upper_cutoff: 10000.0,
+        monstercat: 50.0,
+        decay: 10.0,
+        buffer_size: 0,
+        analysis: Analysis::Fft,
+        hpss: true,
+    };
+    let mut frame_transformer = FrameTransformer::new(settings.clone(), rate);
+
+    let samples: Vec<i16> = vec![0; fft_len * 2];
+    let (left, right) = frame_transformer.process(&samples);
+    assert_eq!(left.percussive_bands.len(), 4);
+    assert_eq!(left.harmonic_bands.len(), 4);
+    assert_eq!(right.percussive_bands, vec![0.0; 4]);
+
+    // without separation, percussive and harmonic bands are not published
+    let mut frame_transformer = FrameTransformer::new(
+        TransformerSettings {
+            hpss: false,
+            ..settings
+        },
+        rate,
+    );
+    let (left, _) = frame_transformer.process(&samples);
+    assert!(left.percussive_bands.is_empty());
+    assert!(left.harmonic_bands.is_empty());
+}
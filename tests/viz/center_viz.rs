@@ -0,0 +1,35 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::theme::Color;
+use led_speakers::viz::{CenterViz, CenterVizConfig, FrameTime, Viz};
+
+#[test]
+fn center_viz_silence() {
+    let mut viz = CenterViz::new(CenterVizConfig {
+        pretty_name: "Center Viz".to_string(),
+    });
+    viz.set_total_pixels(20);
+
+    let colors = vec![Color { r: 255, g: 0, b: 0 }];
+    let pixels = viz.update(&AudioFeatures::new(2), &colors, &FrameTime::default());
+    assert_eq!(pixels.len(), 20);
+    assert!(pixels[1..].iter().all(|p| p.brightness == 0.0));
+}
+
+#[test]
+fn center_viz_full_magnitude() {
+    let mut viz = CenterViz::new(CenterVizConfig {
+        pretty_name: "Center Viz".to_string(),
+    });
+    viz.set_total_pixels(20);
+
+    let colors = vec![Color { r: 255, g: 0, b: 0 }];
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![100.0, 100.0]),
+        &colors,
+        &FrameTime::default(),
+    );
+    assert!(pixels.iter().all(|p| p.brightness == 1.0));
+    assert_eq!(pixels[10].color_index, 0);
+    assert_eq!(pixels[19].color_index, 1);
+    assert_eq!(pixels[1].color_index, 1);
+}
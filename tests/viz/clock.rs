@@ -0,0 +1,29 @@
+use led_speakers::viz::{Clock, FrameTimer, ManualClock, SystemClock};
+
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn frame_timer_delta() {
+    let clock = ManualClock::new();
+    let mut timer = FrameTimer::new(Arc::new(clock.clone()));
+
+    let first = timer.tick();
+    assert_eq!(first.now, Duration::ZERO);
+    assert_eq!(first.delta, 0.0);
+
+    clock.advance(Duration::from_millis(16));
+    let second = timer.tick();
+    assert_eq!(second.now, Duration::from_millis(16));
+    assert!((second.delta - 0.016).abs() < 1e-6);
+
+    // no time passed
+    assert_eq!(timer.tick().delta, 0.0);
+}
+
+#[test]
+fn system_clock_monotonic() {
+    let clock = SystemClock::new();
+    let first = clock.now();
+    assert!(clock.now() >= first);
+}
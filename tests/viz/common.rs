@@ -0,0 +1,24 @@
+use led_speakers::theme::Color;
+use led_speakers::viz::{FrameTime, PixelViz};
+
+use std::time::Duration;
+
+/// Returns `total` distinct theme colors.
+pub fn colors(total: u8) -> Vec<Color> {
+    (1..=total).map(|i| Color { r: i, g: 1, b: 1 }).collect()
+}
+
+/// Returns the frame time of an update after `delta` seconds.
+pub fn frame(delta: f32) -> FrameTime {
+    FrameTime::new(Duration::from_secs_f32(delta), delta)
+}
+
+/// Returns the indices of pixels that are turned on.
+pub fn lit(pixels: &[PixelViz]) -> Vec<usize> {
+    pixels
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.brightness > 0.0)
+        .map(|(i, _)| i)
+        .collect()
+}
@@ -0,0 +1,108 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::viz::{FirePalette, FireViz, FireVizConfig, PixelViz, Viz};
+
+use std::collections::HashMap;
+
+use crate::common::{colors, frame};
+
+fn fire_viz(palette: FirePalette) -> FireViz {
+    FireViz::new(FireVizConfig {
+        pretty_name: "Fire Viz".to_string(),
+        cooling: 55.0,
+        sparking: 1.0,
+        flicker: 0.0,
+        palette,
+    })
+}
+
+/// Returns the total brightness of all pixels.
+fn total_brightness(pixels: &[PixelViz]) -> f32 {
+    pixels.iter().map(|p| p.brightness).sum()
+}
+
+#[test]
+fn fire_viz_stays_dark_without_bass() {
+    let mut viz = fire_viz(FirePalette::Heat);
+    viz.set_total_pixels(30);
+    let silence = AudioFeatures::from_bands(vec![0.0; 9]);
+    for _ in 0..60 {
+        let pixels = viz.update(&silence, &colors(3), &frame(1.0 / 60.0));
+        assert_eq!(total_brightness(&pixels), 0.0);
+    }
+}
+
+#[test]
+fn fire_viz_bass_ignites_sparks() {
+    let mut viz = fire_viz(FirePalette::Heat);
+    viz.set_total_pixels(30);
+    let bass = AudioFeatures::from_bands(vec![100.0, 100.0, 100.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    let mut pixels = Vec::new();
+    for _ in 0..5 {
+        pixels = viz.update(&bass, &colors(3), &frame(1.0 / 60.0));
+    }
+
+    assert_eq!(pixels.len(), 30);
+    assert!(total_brightness(&pixels[..15]) > 0.0);
+    // sparks are ignited at the bottom and heat rises at most two pixels per step
+    assert_eq!(total_brightness(&pixels[15..]), 0.0);
+}
+
+#[test]
+fn fire_viz_steps_are_time_based() {
+    let mut viz = fire_viz(FirePalette::Heat);
+    viz.set_total_pixels(30);
+    let bass = AudioFeatures::from_bands(vec![100.0; 9]);
+
+    // no simulation step runs before a step's worth of time has passed
+    let pixels = viz.update(&bass, &colors(3), &frame(0.5 / 60.0));
+    assert_eq!(total_brightness(&pixels), 0.0);
+
+    let pixels = viz.update(&bass, &colors(3), &frame(0.5 / 60.0));
+    assert!(total_brightness(&pixels) > 0.0);
+}
+
+#[test]
+fn fire_viz_theme_palette() {
+    let mut viz = fire_viz(FirePalette::Theme);
+    viz.set_total_pixels(30);
+    let bass = AudioFeatures::from_bands(vec![100.0; 9]);
+    for _ in 0..60 {
+        let pixels = viz.update(&bass, &colors(4), &frame(1.0 / 60.0));
+        for pixel in pixels {
+            assert!(pixel.color_index < 4);
+            assert_eq!(pixel.red_mul, 1.0);
+        }
+    }
+}
+
+#[test]
+fn fire_viz_settings_round_trip() {
+    let mut viz = fire_viz(FirePalette::Theme);
+    let settings = viz.get_settings();
+    viz.update_settings(settings);
+    assert_eq!(viz.config, fire_viz(FirePalette::Theme).config);
+}
+
+#[test]
+fn fire_viz_invalid_settings() {
+    let mut viz = fire_viz(FirePalette::Theme);
+    viz.set_total_pixels(30);
+    let mut settings = HashMap::new();
+    settings.insert("cooling".to_string(), "-100".to_string());
+    settings.insert("sparking".to_string(), "NaN".to_string());
+    settings.insert("flicker".to_string(), "-inf".to_string());
+    settings.insert("palette".to_string(), "theme".to_string());
+    viz.update_settings(settings);
+
+    // heat stays between 0 and 1 and brightness isn't poisoned by invalid values
+    let bass = AudioFeatures::from_bands(vec![100.0; 9]);
+    let mut pixels = Vec::new();
+    for _ in 0..60 {
+        pixels = viz.update(&bass, &colors(4), &frame(1.0 / 60.0));
+        for pixel in &pixels {
+            assert!(pixel.brightness >= 0.0 && pixel.brightness <= 1.0);
+            assert!(pixel.color_index < 4);
+        }
+    }
+    assert!(total_brightness(&pixels) > 0.0);
+}
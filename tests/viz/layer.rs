@@ -0,0 +1,128 @@
+use led_speakers::settings::LayerSettings;
+use led_speakers::theme::Color;
+use led_speakers::viz::{composite, BlendMode, PixelViz};
+
+fn pixel(color_index: usize, brightness: f32) -> PixelViz {
+    PixelViz {
+        color_index,
+        brightness,
+        ..PixelViz::default()
+    }
+}
+
+fn colors() -> Vec<Color> {
+    vec![
+        Color {
+            r: 200,
+            g: 100,
+            b: 1,
+        },
+        Color {
+            r: 100,
+            g: 100,
+            b: 100,
+        },
+    ]
+}
+
+/// Composites a single pixel of the provided color index on top of `below`.
+fn composite_pixel(below: [f32; 3], pixel: PixelViz, opacity: f32, mode: BlendMode) -> [f32; 3] {
+    let mut frame = vec![below];
+    composite(&mut frame, &[pixel], &colors(), opacity, mode);
+    frame[0]
+}
+
+fn assert_rgb(actual: [f32; 3], expected: [f32; 3]) {
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert!((a - e).abs() < 0.01, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn composite_base_layer() {
+    // a single normal layer results in the pixel colors scaled by brightness
+    let mut frame = vec![[0.0; 3]; 3];
+    let pixels = vec![pixel(0, 1.0), pixel(1, 0.5), pixel(0, 0.0)];
+    composite(&mut frame, &pixels, &colors(), 1.0, BlendMode::Normal);
+    assert_rgb(frame[0], [200.0, 100.0, 1.0]);
+    assert_rgb(frame[1], [50.0, 50.0, 50.0]);
+    assert_rgb(frame[2], [0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn composite_blend_modes() {
+    let below = [100.0, 200.0, 50.0];
+    let top = pixel(1, 1.0);
+
+    assert_rgb(
+        composite_pixel(below, top.clone(), 1.0, BlendMode::Normal),
+        [100.0, 100.0, 100.0],
+    );
+    assert_rgb(
+        composite_pixel(below, top.clone(), 1.0, BlendMode::Add),
+        [200.0, 255.0, 150.0],
+    );
+    assert_rgb(
+        composite_pixel(below, top.clone(), 1.0, BlendMode::Multiply),
+        [39.22, 78.43, 19.61],
+    );
+    assert_rgb(
+        composite_pixel(below, top.clone(), 1.0, BlendMode::Screen),
+        [160.78, 221.57, 130.39],
+    );
+    assert_rgb(
+        composite_pixel(below, top, 1.0, BlendMode::Max),
+        [100.0, 200.0, 100.0],
+    );
+}
+
+#[test]
+fn composite_opacity() {
+    let below = [100.0, 200.0, 50.0];
+
+    // half opacity mixes layer and layers below
+    assert_rgb(
+        composite_pixel(below, pixel(1, 1.0), 0.5, BlendMode::Normal),
+        [100.0, 150.0, 75.0],
+    );
+
+    // pixels that are turned off keep the color of the layers below
+    assert_rgb(
+        composite_pixel(below, pixel(1, 0.0), 1.0, BlendMode::Normal),
+        below,
+    );
+    assert_rgb(composite_pixel(below, pixel(1, 1.0), 0.0, BlendMode::Add), below);
+}
+
+#[test]
+fn blend_mode_from_str() {
+    for mode in [
+        BlendMode::Normal,
+        BlendMode::Add,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Max,
+    ]
+    .iter()
+    {
+        assert_eq!(mode.to_string().parse::<BlendMode>(), Ok(*mode));
+    }
+    assert!("overlay".parse::<BlendMode>().is_err());
+}
+
+#[test]
+fn layer_settings_from_json() {
+    let layer: LayerSettings = serde_json::from_str(r#"{"viz": "sparkle_viz"}"#).unwrap();
+    assert_eq!(layer.viz, "sparkle_viz");
+    assert_eq!(layer.theme, None);
+    assert_eq!(layer.opacity, 1.0);
+    assert_eq!(layer.blend_mode, BlendMode::Normal);
+
+    let layer: LayerSettings = serde_json::from_str(
+        r#"{"viz": "sparkle_viz", "theme": "Rainbow", "opacity": 0.5, "blend_mode": "screen"}"#,
+    )
+    .unwrap();
+    assert_eq!(layer.theme, Some("Rainbow".to_string()));
+    assert_eq!(layer.opacity, 0.5);
+    assert_eq!(layer.blend_mode, BlendMode::Screen);
+}
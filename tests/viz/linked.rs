@@ -0,0 +1,107 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::settings::{LinkOrder, LinkedSettings, Output, OutputSettings, Speaker};
+use led_speakers::theme::Color;
+use led_speakers::viz::{split_linked, BlendMode, FrameTime, Layer, SolidViz, SolidVizConfig};
+
+fn output(linked: LinkedSettings) -> OutputSettings {
+    OutputSettings {
+        left: Output {
+            spi: "/dev/spidev0.0".to_string(),
+            total_leds: 3,
+        },
+        right: Output {
+            spi: "/dev/spidev1.0".to_string(),
+            total_leds: 2,
+        },
+        fps: 60.0,
+        linked,
+    }
+}
+
+fn linked(order: LinkOrder, reverse_left: bool, reverse_right: bool) -> LinkedSettings {
+    LinkedSettings {
+        enabled: true,
+        order,
+        reverse_left,
+        reverse_right,
+    }
+}
+
+/// Single strip whose pixel values are their index.
+fn frame() -> Vec<[f32; 3]> {
+    (0..5).map(|i| [i as f32; 3]).collect()
+}
+
+fn indices(frame: &[[f32; 3]]) -> Vec<usize> {
+    frame.iter().map(|p| p[0] as usize).collect()
+}
+
+#[test]
+fn linked_settings_from_json() {
+    let output: OutputSettings = serde_json::from_str(
+        r#"{"left": {"spi": "a", "total_leds": 3}, "right": {"spi": "b", "total_leds": 2}}"#,
+    )
+    .unwrap();
+    assert_eq!(output.linked, LinkedSettings::default());
+    assert!(!output.linked.enabled);
+    assert_eq!(output.linked.order, LinkOrder::LeftRight);
+
+    let linked: LinkedSettings =
+        serde_json::from_str(r#"{"enabled": true, "order": "right_left", "reverse_left": true}"#)
+            .unwrap();
+    assert_eq!(linked, self::linked(LinkOrder::RightLeft, true, false));
+}
+
+#[test]
+fn linked_total_pixels() {
+    let separate = output(LinkedSettings::default());
+    assert_eq!(separate.total_pixels(Speaker::Left), 3);
+    assert_eq!(separate.total_pixels(Speaker::Right), 2);
+
+    let linked = output(linked(LinkOrder::LeftRight, false, false));
+    assert_eq!(linked.total_pixels(Speaker::Left), 5);
+
+    // the left visualization renders both outputs
+    let mut layer = Layer::new(
+        Box::new(SolidViz::new(SolidVizConfig {
+            pretty_name: "Solid Viz".to_string(),
+        })),
+        &linked,
+        None,
+        1.0,
+        BlendMode::Normal,
+    );
+    let colors = vec![Color { r: 1, g: 1, b: 1 }];
+    let pixels = layer
+        .viz_left
+        .update(&AudioFeatures::new(0), &colors, &FrameTime::default());
+    assert_eq!(pixels.len(), 5);
+}
+
+#[test]
+fn split_linked_order() {
+    let (left, right) = split_linked(
+        &frame(),
+        &output(linked(LinkOrder::LeftRight, false, false)),
+    );
+    assert_eq!(indices(&left), vec![0, 1, 2]);
+    assert_eq!(indices(&right), vec![3, 4]);
+
+    let (left, right) = split_linked(
+        &frame(),
+        &output(linked(LinkOrder::RightLeft, false, false)),
+    );
+    assert_eq!(indices(&left), vec![2, 3, 4]);
+    assert_eq!(indices(&right), vec![0, 1]);
+}
+
+#[test]
+fn split_linked_reversal() {
+    let (left, right) = split_linked(&frame(), &output(linked(LinkOrder::LeftRight, true, false)));
+    assert_eq!(indices(&left), vec![2, 1, 0]);
+    assert_eq!(indices(&right), vec![3, 4]);
+
+    let (left, right) = split_linked(&frame(), &output(linked(LinkOrder::RightLeft, false, true)));
+    assert_eq!(indices(&left), vec![2, 3, 4]);
+    assert_eq!(indices(&right), vec![1, 0]);
+}
@@ -0,0 +1,18 @@
+mod center_viz;
+mod clock;
+mod common;
+mod fire_viz;
+mod layer;
+mod linked;
+mod plasma_viz;
+mod playlist;
+mod plugin_viz;
+mod ripple_viz;
+mod scheduler;
+mod script_viz;
+mod speakers;
+mod spectrum_viz;
+mod strobe_viz;
+mod time_based;
+mod transition;
+mod waterfall_viz;
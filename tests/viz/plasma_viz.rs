@@ -0,0 +1,103 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::theme::Color;
+use led_speakers::viz::{PixelViz, PlasmaViz, PlasmaVizConfig, Viz};
+
+use std::collections::HashMap;
+
+use crate::common::frame;
+
+fn colors() -> Vec<Color> {
+    vec![Color { r: 255, g: 1, b: 1 }, Color { r: 1, g: 1, b: 255 }]
+}
+
+fn plasma_viz(reactivity: f32) -> PlasmaViz {
+    PlasmaViz::new(PlasmaVizConfig {
+        pretty_name: "Plasma Viz".to_string(),
+        speed: 0.5,
+        scale: 0.1,
+        reactivity,
+    })
+}
+
+/// Returns the color multipliers of all pixels.
+fn multipliers(pixels: &[PixelViz]) -> Vec<(f32, f32, f32)> {
+    pixels
+        .iter()
+        .map(|p| (p.red_mul, p.green_mul, p.blue_mul))
+        .collect()
+}
+
+#[test]
+fn plasma_viz_colors_from_theme_gradient() {
+    let mut viz = plasma_viz(0.0);
+    viz.set_total_pixels(60);
+    let pixels = viz.update(&AudioFeatures::new(4), &colors(), &frame(0.0));
+
+    assert_eq!(pixels.len(), 60);
+    for pixel in &pixels {
+        assert_eq!(pixel.brightness, 1.0);
+        // colors are between the first and second theme color
+        assert!(pixel.red_mul <= 1.0 && pixel.red_mul >= 0.0);
+        assert!(pixel.blue_mul >= 1.0 && pixel.blue_mul <= 255.0);
+    }
+
+    // noise varies smoothly along the strip
+    let reds: Vec<f32> = pixels.iter().map(|p| p.red_mul).collect();
+    assert!(reds.windows(2).any(|w| w[0] != w[1]));
+    assert!(reds.windows(2).all(|w| (w[0] - w[1]).abs() < 0.3));
+}
+
+#[test]
+fn plasma_viz_moves_over_time() {
+    let mut viz = plasma_viz(0.0);
+    viz.set_total_pixels(30);
+    let silence = AudioFeatures::new(4);
+
+    let first = viz.update(&silence, &colors(), &frame(0.0));
+    let same = viz.update(&silence, &colors(), &frame(0.0));
+    assert_eq!(multipliers(&first), multipliers(&same));
+
+    let moved = viz.update(&silence, &colors(), &frame(1.0));
+    assert_ne!(multipliers(&first), multipliers(&moved));
+}
+
+#[test]
+fn plasma_viz_energy_modulates_brightness() {
+    let mut viz = plasma_viz(0.5);
+    viz.set_total_pixels(10);
+
+    let pixels = viz.update(&AudioFeatures::new(4), &colors(), &frame(1.0));
+    assert_eq!(pixels[0].brightness, 0.5);
+
+    // smoothed energy approaches full energy
+    let loud = AudioFeatures::from_bands(vec![100.0; 4]);
+    let pixels = viz.update(&loud, &colors(), &frame(0.1));
+    assert!(pixels[0].brightness > 0.5 && pixels[0].brightness < 1.0);
+    let pixels = viz.update(&loud, &colors(), &frame(2.0));
+    assert!((pixels[0].brightness - 1.0).abs() < 0.001);
+}
+
+#[test]
+fn plasma_viz_invalid_settings() {
+    let mut viz = plasma_viz(0.5);
+    viz.set_total_pixels(10);
+    let mut settings = HashMap::new();
+    settings.insert("speed".to_string(), "NaN".to_string());
+    settings.insert("scale".to_string(), "inf".to_string());
+    settings.insert("reactivity".to_string(), "2".to_string());
+    viz.update_settings(settings);
+
+    // reactivity is limited to 1, so pixels are off during silence instead of negative
+    let silence = AudioFeatures::new(4);
+    let pixels = viz.update(&silence, &colors(), &frame(1.0));
+    assert!(pixels.iter().all(|p| p.brightness == 0.0));
+
+    // the plasma still moves with valid colors
+    let loud = AudioFeatures::from_bands(vec![100.0; 4]);
+    let first = viz.update(&loud, &colors(), &frame(1.0));
+    let moved = viz.update(&loud, &colors(), &frame(1.0));
+    assert!(multipliers(&moved)
+        .iter()
+        .all(|(r, g, b)| r.is_finite() && g.is_finite() && b.is_finite()));
+    assert_ne!(multipliers(&first), multipliers(&moved));
+}
@@ -0,0 +1,276 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::settings::{PlaylistAdvance, PlaylistEntry, PlaylistSettings, Settings};
+use led_speakers::viz::{FrameTime, Playlist, SongChangeDetector, StrobeViz, StrobeVizConfig};
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+fn settings() -> Settings {
+    serde_json::from_str(
+        r#"{
+            "visualizations": {
+                "solid_viz": {"pretty_name": "Solid Viz"},
+                "center_viz": {"pretty_name": "Center Viz"}
+            },
+            "output": {
+                "left": {"spi": "/dev/spidev0.0", "total_leds": 10},
+                "right": {"spi": "/dev/spidev1.0", "total_leds": 10}
+            },
+            "themes": [
+                {"name": "Red", "colors": [[255, 0, 0]]},
+                {"name": "Blue", "colors": [[0, 0, 255]]}
+            ],
+            "transformer": {
+                "sink": "0",
+                "fft_len": 1024,
+                "total_bands": 10,
+                "lower_cutoff": 20.0,
+                "upper_cutoff": 15000.0,
+                "monstercat": 0.0,
+                "decay": 0.0,
+                "buffer_size": 4096
+            },
+            "server_host": "127.0.0.1",
+            "server_port": "8000"
+        }"#,
+    )
+    .unwrap()
+}
+
+fn entry(viz: &str, theme: Option<&str>, duration: f32, beats: u32) -> PlaylistEntry {
+    PlaylistEntry {
+        viz: viz.to_string(),
+        theme: theme.map(|t| t.to_string()),
+        duration,
+        beats,
+    }
+}
+
+fn playlist_settings(entries: Vec<PlaylistEntry>, advance: PlaylistAdvance) -> PlaylistSettings {
+    PlaylistSettings {
+        enabled: true,
+        entries,
+        shuffle: false,
+        advance,
+    }
+}
+
+fn loudness(loudness: f32) -> AudioFeatures {
+    AudioFeatures {
+        loudness,
+        ..AudioFeatures::new(0)
+    }
+}
+
+fn onset(onset: bool) -> AudioFeatures {
+    AudioFeatures {
+        onset,
+        ..loudness(-20.0)
+    }
+}
+
+/// Feeds the same features to the detector for `seconds`; returns whether a new song was detected.
+fn detect(
+    detector: &mut SongChangeDetector,
+    features: &AudioFeatures,
+    seconds: f32,
+    delta: f32,
+) -> bool {
+    let mut changed = false;
+    for _ in 0..(seconds / delta).round() as usize {
+        changed |= detector.update(features, &FrameTime::new(Duration::ZERO, delta));
+    }
+    changed
+}
+
+#[test]
+fn playlist_advances_on_timer() {
+    let settings = settings();
+    let mut playlist = Playlist::from_settings(
+        &playlist_settings(
+            vec![
+                entry("solid_viz", None, 2.0, 64),
+                entry("center_viz", Some("Blue"), 1.0, 64),
+            ],
+            PlaylistAdvance::Timer,
+        ),
+        &settings,
+    )
+    .unwrap();
+    let time = FrameTime::new(Duration::ZERO, 0.5);
+    let features = loudness(-20.0);
+
+    assert_eq!(playlist.current().viz.get_name(), "solid_viz");
+    for _ in 0..3 {
+        assert!(playlist.update(&features, &time).is_none());
+    }
+
+    let item = playlist.update(&features, &time).unwrap();
+    assert_eq!(item.viz.get_name(), "center_viz");
+    assert_eq!(item.theme.unwrap().name, "Blue");
+    assert_eq!(playlist.current_entry(), 1);
+
+    // entries have their own duration and the playlist starts over after the last entry
+    assert!(playlist.update(&features, &time).is_none());
+    let item = playlist.update(&features, &time).unwrap();
+    assert_eq!(item.viz.get_name(), "solid_viz");
+    assert!(item.theme.is_none());
+}
+
+#[test]
+fn playlist_advances_on_beats() {
+    let settings = settings();
+    let mut playlist = Playlist::from_settings(
+        &playlist_settings(
+            vec![
+                entry("solid_viz", None, 60.0, 2),
+                entry("center_viz", None, 60.0, 2),
+            ],
+            PlaylistAdvance::Beats,
+        ),
+        &settings,
+    )
+    .unwrap();
+    let time = FrameTime::new(Duration::ZERO, 1.0 / 60.0);
+
+    // an onset lasting multiple updates is counted once
+    assert!(playlist.update(&onset(true), &time).is_none());
+    assert!(playlist.update(&onset(true), &time).is_none());
+    assert!(playlist.update(&onset(false), &time).is_none());
+
+    let item = playlist.update(&onset(true), &time).unwrap();
+    assert_eq!(item.viz.get_name(), "center_viz");
+}
+
+#[test]
+fn playlist_duration_limits_beats_and_song_changes() {
+    let settings = settings();
+    for advance in [PlaylistAdvance::Beats, PlaylistAdvance::SongChange].iter() {
+        let mut playlist = Playlist::from_settings(
+            &playlist_settings(
+                vec![
+                    entry("solid_viz", None, 1.0, 64),
+                    entry("center_viz", None, 1.0, 64),
+                ],
+                *advance,
+            ),
+            &settings,
+        )
+        .unwrap();
+
+        let item = playlist.update(&loudness(-20.0), &FrameTime::new(Duration::ZERO, 1.0));
+        assert_eq!(item.unwrap().viz.get_name(), "center_viz");
+    }
+}
+
+#[test]
+fn playlist_shuffle_shows_all_entries() {
+    let settings = settings();
+    let mut playlist_settings = playlist_settings(
+        vec![
+            entry("solid_viz", None, 60.0, 64),
+            entry("center_viz", None, 60.0, 64),
+            entry("solid_viz", Some("Red"), 60.0, 64),
+        ],
+        PlaylistAdvance::Timer,
+    );
+    playlist_settings.shuffle = true;
+    let mut playlist = Playlist::from_settings(&playlist_settings, &settings).unwrap();
+
+    for _ in 0..10 {
+        // each round shows every entry once; the last entry of a round is not repeated
+        let mut shown = HashSet::new();
+        let first = playlist.current_entry();
+        shown.insert(first);
+        for _ in 0..2 {
+            playlist.advance();
+            shown.insert(playlist.current_entry());
+        }
+        assert_eq!(shown.len(), 3);
+
+        let last = playlist.current_entry();
+        playlist.advance();
+        assert_ne!(playlist.current_entry(), last);
+    }
+}
+
+#[test]
+fn playlist_skips_unavailable_entries() {
+    let mut settings = settings();
+    settings.safety.strobe_enabled = false;
+    settings
+        .visualizations
+        .push(Box::new(StrobeViz::new(StrobeVizConfig {
+            pretty_name: "Strobe Viz".to_string(),
+            flash_rate: 3.0,
+            duty_cycle: 0.3,
+            brightness_change: 0.8,
+        })));
+    let with_strobe = playlist_settings(
+        vec![
+            entry("strobe_viz", None, 1.0, 64),
+            entry("solid_viz", None, 1.0, 64),
+        ],
+        PlaylistAdvance::Timer,
+    );
+
+    // disabled flashing visualizations are never shown
+    let mut playlist = Playlist::from_settings(&with_strobe, &settings).unwrap();
+    assert_eq!(playlist.current_entry(), 1);
+    assert_eq!(playlist.advance().viz.get_name(), "solid_viz");
+    assert_eq!(playlist.current_entry(), 1);
+
+    let only_strobe = PlaylistSettings {
+        entries: vec![entry("strobe_viz", None, 1.0, 64)],
+        ..with_strobe
+    };
+    assert!(Playlist::from_settings(&only_strobe, &settings).is_none());
+}
+
+#[test]
+fn playlist_rejects_unknown_entries() {
+    let settings = settings();
+    let unknown_viz = playlist_settings(
+        vec![entry("unknown_viz", None, 1.0, 64)],
+        PlaylistAdvance::Timer,
+    );
+    let unknown_theme = playlist_settings(
+        vec![entry("solid_viz", Some("Unknown"), 1.0, 64)],
+        PlaylistAdvance::Timer,
+    );
+    let empty = playlist_settings(Vec::new(), PlaylistAdvance::Timer);
+
+    assert!(Playlist::from_settings(&unknown_viz, &settings).is_none());
+    assert!(Playlist::from_settings(&unknown_theme, &settings).is_none());
+    assert!(Playlist::from_settings(&empty, &settings).is_none());
+}
+
+#[test]
+fn song_change_after_silence_gap() {
+    let mut detector = SongChangeDetector::new();
+
+    // first sound is not a song change
+    assert!(!detect(&mut detector, &loudness(-70.0), 2.0, 0.1));
+    assert!(!detect(&mut detector, &loudness(-20.0), 5.0, 0.1));
+
+    // short pauses don't end a song
+    assert!(!detect(&mut detector, &loudness(-70.0), 0.5, 0.1));
+    assert!(!detect(&mut detector, &loudness(-20.0), 1.0, 0.1));
+
+    assert!(!detect(&mut detector, &loudness(-70.0), 1.5, 0.1));
+    assert!(detector.update(&loudness(-20.0), &FrameTime::new(Duration::ZERO, 0.1)));
+}
+
+#[test]
+fn song_change_on_energy_shift() {
+    let mut detector = SongChangeDetector::new();
+
+    // steady loudness is a single song
+    assert!(!detect(&mut detector, &loudness(-20.0), 120.0, 0.1));
+
+    // considerably louder audio starts a new song
+    assert!(detect(&mut detector, &loudness(-5.0), 5.0, 0.1));
+
+    // another shift right after a song change is not a new song
+    assert!(!detect(&mut detector, &loudness(-25.0), 10.0, 0.1));
+}
@@ -0,0 +1,218 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::settings::PluginSettings;
+use led_speakers::theme::Color;
+use led_speakers::viz::{FrameTime, PluginViz, Viz};
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Plugin lighting all pixels with the magnitude of the first band; pixel `i` uses color `i`.
+const BAND_PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $pixels (mut i32) (i32.const 0))
+  (data (i32.const 16) "{\"gain\": 0.5}\00")
+
+  (func (export "alloc") (param $size i32) (result i32)
+    (i32.const 1024))
+
+  (func (export "init") (param $pixels i32)
+    (global.set $pixels (local.get $pixels)))
+
+  (func (export "settings_schema") (result i32)
+    (i32.const 16))
+
+  (func (export "update") (param $bands i32) (param $len i32) (param $dt f32) (result i32)
+    (local $i i32)
+    (local $ptr i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (global.get $pixels)))
+        (local.set $ptr (i32.add (i32.const 2048) (i32.mul (local.get $i) (i32.const 20))))
+        (i32.store (local.get $ptr) (local.get $i))
+        (f32.store offset=4 (local.get $ptr)
+          (f32.div (f32.load (local.get $bands)) (f32.const 100)))
+        (f32.store offset=8 (local.get $ptr) (f32.const 1))
+        (f32.store offset=12 (local.get $ptr) (f32.const 1))
+        (f32.store offset=16 (local.get $ptr) (f32.const 1))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 2048))
+)
+"#;
+
+/// Plugin that never returns from `update`.
+const ENDLESS_PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param $size i32) (result i32) (i32.const 1024))
+  (func (export "init") (param $pixels i32))
+  (func (export "update") (param $bands i32) (param $len i32) (param $dt f32) (result i32)
+    (loop $forever (br $forever))
+    (i32.const 0))
+)
+"#;
+
+/// Plugin trapping in `update` while the first band is above 50, lighting all pixels otherwise.
+const TRAP_PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $pixels (mut i32) (i32.const 0))
+
+  (func (export "alloc") (param $size i32) (result i32)
+    (i32.const 1024))
+
+  (func (export "init") (param $pixels i32)
+    (global.set $pixels (local.get $pixels)))
+
+  (func (export "update") (param $bands i32) (param $len i32) (param $dt f32) (result i32)
+    (local $i i32)
+    (local $ptr i32)
+    (if (f32.gt (f32.load (local.get $bands)) (f32.const 50))
+      (then unreachable))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (global.get $pixels)))
+        (local.set $ptr (i32.add (i32.const 2048) (i32.mul (local.get $i) (i32.const 20))))
+        (f32.store offset=4 (local.get $ptr) (f32.const 1))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 2048))
+)
+"#;
+
+/// Plugin requiring more memory than allowed.
+const LARGE_PLUGIN: &str = r#"
+(module
+  (memory (export "memory") 1024)
+  (func (export "alloc") (param $size i32) (result i32) (i32.const 1024))
+  (func (export "init") (param $pixels i32))
+  (func (export "update") (param $bands i32) (param $len i32) (param $dt f32) (result i32)
+    (i32.const 0))
+)
+"#;
+
+/// Writes compiled plugins to a new directory and returns the plugin settings for it.
+fn plugin_dir(name: &str, plugins: &[(&str, &str)]) -> (PathBuf, PluginSettings) {
+    let dir = std::env::temp_dir().join(format!(
+        "led_speakers_plugins_{}_{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, plugin) in plugins {
+        fs::write(dir.join(file), wat::parse_str(plugin).unwrap()).unwrap();
+    }
+
+    let settings = PluginSettings {
+        dir: dir.to_str().unwrap().to_string(),
+        ..PluginSettings::default()
+    };
+    (dir, settings)
+}
+
+#[test]
+fn plugin_viz_from_dir() {
+    let (dir, settings) = plugin_dir(
+        "load",
+        &[
+            ("band_level.wasm", BAND_PLUGIN),
+            ("large.wasm", LARGE_PLUGIN),
+        ],
+    );
+    fs::write(dir.join("broken.wasm"), b"not a module").unwrap();
+    let mut vizzes = PluginViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // invalid modules and modules exceeding the memory limit are skipped
+    assert_eq!(vizzes.len(), 1);
+    let viz = &mut vizzes[0];
+    assert_eq!(viz.get_name(), "plugin_band_level");
+    assert_eq!(viz.get_pretty_name(), "Band Level");
+    assert_eq!(viz.get_settings()["gain"], "0.5");
+
+    viz.set_total_pixels(3);
+    let colors = vec![Color { r: 255, g: 0, b: 0 }, Color { r: 0, g: 0, b: 255 }];
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![80.0, 20.0]),
+        &colors,
+        &FrameTime::new(Duration::from_millis(16), 0.016),
+    );
+
+    assert_eq!(pixels.len(), 3);
+    assert!((pixels[0].brightness - 0.8).abs() < 1e-6);
+    // color indices wrap around the theme colors
+    assert_eq!(
+        pixels.iter().map(|p| p.color_index).collect::<Vec<usize>>(),
+        vec![0, 1, 0]
+    );
+}
+
+#[test]
+fn plugin_viz_clone_has_own_instance() {
+    let (dir, settings) = plugin_dir("clone", &[("band_level.wasm", BAND_PLUGIN)]);
+    let vizzes = PluginViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let mut left = vizzes[0].clone();
+    let mut right = vizzes[0].clone();
+    left.set_total_pixels(2);
+    right.set_total_pixels(4);
+    let input = AudioFeatures::from_bands(vec![50.0]);
+    let colors = vec![Color { r: 255, g: 0, b: 0 }];
+
+    assert_eq!(left.update(&input, &colors, &FrameTime::default()).len(), 2);
+    let right_pixels = right.update(&input, &colors, &FrameTime::default());
+    assert!(right_pixels.iter().all(|p| p.brightness == 0.5));
+}
+
+#[test]
+fn plugin_viz_runs_out_of_fuel() {
+    let (dir, settings) = plugin_dir("fuel", &[("endless.wasm", ENDLESS_PLUGIN)]);
+    let mut vizzes = PluginViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let viz = &mut vizzes[0];
+    viz.set_total_pixels(4);
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![50.0]),
+        &vec![Color { r: 255, g: 0, b: 0 }],
+        &FrameTime::default(),
+    );
+
+    assert_eq!(pixels.len(), 4);
+    assert!(pixels.iter().all(|p| p.brightness == 0.0));
+}
+
+#[test]
+fn plugin_viz_disabled_after_traps() {
+    let (dir, settings) = plugin_dir("trap", &[("trap.wasm", TRAP_PLUGIN)]);
+    let mut vizzes = PluginViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let viz = &mut vizzes[0];
+    viz.set_total_pixels(4);
+    let colors = vec![Color { r: 255, g: 0, b: 0 }];
+    let is_lit = |viz: &mut PluginViz, band: f32| -> bool {
+        let pixels = viz.update(
+            &AudioFeatures::from_bands(vec![band]),
+            &colors,
+            &FrameTime::default(),
+        );
+        pixels.iter().all(|p| p.brightness == 1.0)
+    };
+
+    // a plugin recovers from single traps with a new instance
+    assert!(!is_lit(viz, 80.0));
+    assert!(!is_lit(viz, 80.0));
+    assert!(is_lit(viz, 20.0));
+
+    // consecutive traps disable the plugin until its settings change
+    for _ in 0..3 {
+        assert!(!is_lit(viz, 80.0));
+    }
+    assert!(!is_lit(viz, 20.0));
+    viz.update_settings(viz.get_settings());
+    assert!(is_lit(viz, 20.0));
+}
@@ -0,0 +1,128 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::viz::{RipplePosition, RippleViz, RippleVizConfig, Viz};
+
+use std::collections::HashMap;
+
+use crate::common::{colors, frame, lit};
+
+fn ripple_viz(max_waves: usize) -> RippleViz {
+    RippleViz::new(RippleVizConfig {
+        pretty_name: "Ripple Viz".to_string(),
+        wave_speed: 10.0,
+        damping: 0.9,
+        max_waves,
+        position: RipplePosition::Band,
+    })
+}
+
+/// Returns features of an onset with the dominant band `band` out of `total` bands.
+fn onset(band: usize, total: usize) -> AudioFeatures {
+    let mut bands = vec![0.0; total];
+    bands[band] = 100.0;
+    let mut features = AudioFeatures::from_bands(bands);
+    features.onset = true;
+    features
+}
+
+#[test]
+fn ripple_viz_wave_spreads_and_fades() {
+    let mut viz = ripple_viz(5);
+    viz.set_total_pixels(40);
+    let silence = AudioFeatures::from_bands(vec![0.0; 2]);
+
+    // wave spawns at the center of the band's part of the strip
+    let pixels = viz.update(&onset(0, 2), &colors(2), &frame(0.0));
+    assert_eq!(pixels[10].brightness, 1.0);
+    assert_eq!(lit(&pixels), (8..13).collect::<Vec<usize>>());
+
+    // wave front spreads in both directions
+    let pixels = viz.update(&silence, &colors(2), &frame(0.5));
+    assert_eq!(pixels[10].brightness, 0.0);
+    assert!(pixels[5].brightness > 0.0 && pixels[5].brightness < 1.0);
+    assert!(pixels[15].brightness > 0.0 && pixels[15].brightness < 1.0);
+    assert_eq!(pixels[5].brightness, pixels[15].brightness);
+
+    // waves disappear once faded
+    let pixels = viz.update(&silence, &colors(2), &frame(1.0));
+    assert!(lit(&pixels).is_empty());
+}
+
+#[test]
+fn ripple_viz_waves_add_up() {
+    let mut viz = ripple_viz(5);
+    viz.set_total_pixels(20);
+    let mut features = onset(0, 2);
+    features.bands[0] = 40.0;
+
+    viz.update(&features, &colors(2), &frame(0.0));
+    features.bands = vec![0.0, 40.0];
+    let pixels = viz.update(&features, &colors(2), &frame(0.0));
+
+    // both waves are 10 pixels apart, so they overlap at none of their origins
+    assert_eq!(pixels[5].brightness, 0.4);
+    assert_eq!(pixels[15].brightness, 0.4);
+    assert!(pixels[15].red_mul > pixels[5].red_mul);
+
+    // waves spread by 5 pixels and meet in the middle
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![0.0; 2]),
+        &colors(2),
+        &frame(0.5),
+    );
+    assert!(pixels[10].brightness > pixels[9].brightness);
+    assert!(pixels[10].brightness > pixels[11].brightness);
+}
+
+#[test]
+fn ripple_viz_max_waves() {
+    let mut viz = ripple_viz(1);
+    viz.set_total_pixels(20);
+
+    viz.update(&onset(0, 2), &colors(2), &frame(0.0));
+    let pixels = viz.update(&onset(1, 2), &colors(2), &frame(0.0));
+
+    // oldest wave is replaced by the new one
+    assert_eq!(lit(&pixels), (13..18).collect::<Vec<usize>>());
+}
+
+#[test]
+fn ripple_viz_random_position() {
+    let mut viz = ripple_viz(3);
+    viz.config.position = RipplePosition::Random;
+    viz.set_total_pixels(20);
+
+    let pixels = viz.update(&onset(0, 2), &colors(2), &frame(0.0));
+    let lit_pixels = lit(&pixels);
+    assert!(!lit_pixels.is_empty() && lit_pixels.len() <= 5);
+    assert!(lit_pixels.iter().any(|i| pixels[*i].brightness == 1.0));
+}
+
+#[test]
+fn ripple_viz_invalid_settings() {
+    let mut viz = ripple_viz(5);
+    viz.set_total_pixels(20);
+    let silence = AudioFeatures::from_bands(vec![0.0; 2]);
+    let mut settings = viz.get_settings();
+    settings.insert("wave_speed".to_string(), "-5".to_string());
+    settings.insert("damping".to_string(), "2".to_string());
+    viz.update_settings(settings);
+
+    // waves don't grow brighter and leave the strip at the default speed
+    viz.update(&onset(0, 2), &colors(2), &frame(0.0));
+    let pixels = viz.update(&silence, &colors(2), &frame(0.1));
+    assert!(pixels.iter().all(|p| p.brightness <= 1.0));
+    let pixels = viz.update(&silence, &colors(2), &frame(1.0));
+    assert!(lit(&pixels).is_empty());
+
+    // values that aren't numbers don't poison the pixels
+    let mut settings = HashMap::new();
+    settings.insert("wave_speed".to_string(), "NaN".to_string());
+    settings.insert("damping".to_string(), "NaN".to_string());
+    settings.insert("max_waves".to_string(), "5".to_string());
+    settings.insert("position".to_string(), "band".to_string());
+    viz.update_settings(settings);
+    viz.update(&onset(0, 2), &colors(2), &frame(0.0));
+    let pixels = viz.update(&silence, &colors(2), &frame(0.1));
+    assert!(pixels.iter().all(|p| !p.brightness.is_nan()));
+    assert!(!lit(&pixels).is_empty());
+}
@@ -0,0 +1,65 @@
+use led_speakers::viz::{Clock, FrameScheduler, ManualClock};
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Runs frames that each take `frame_time` to compute and show.
+fn run(scheduler: &mut FrameScheduler, clock: &ManualClock, frames: usize, frame_time: Duration) {
+    for _ in 0..frames {
+        scheduler.wait();
+        clock.advance(frame_time);
+        scheduler.end_frame();
+    }
+}
+
+#[test]
+fn scheduler_keeps_target_fps() {
+    let clock = ManualClock::new();
+    let mut scheduler = FrameScheduler::new(50.0, Arc::new(clock.clone()));
+
+    // frames are due every 20ms independent of the time spent computing them
+    run(&mut scheduler, &clock, 10, Duration::from_millis(5));
+    assert_eq!(clock.now(), Duration::from_millis(185));
+    let time = scheduler.wait();
+    assert_eq!(time.now, Duration::from_millis(200));
+    assert!((time.delta - 0.02).abs() < 1e-6);
+    scheduler.end_frame();
+
+    let stats = scheduler.stats();
+    assert_eq!(stats.target_fps, 50.0);
+    assert!((stats.fps - 50.0).abs() < 0.01, "{}", stats.fps);
+    assert!((stats.frame_time_p50 - 5.0).abs() < 0.01);
+    assert!((stats.frame_time_p99 - 5.0).abs() < 0.01);
+    assert_eq!(stats.dropped_frames, 0);
+    assert_eq!(stats.total_frames, 11);
+}
+
+#[test]
+fn scheduler_counts_dropped_frames() {
+    let clock = ManualClock::new();
+    let mut scheduler = FrameScheduler::new(50.0, Arc::new(clock.clone()));
+
+    run(&mut scheduler, &clock, 9, Duration::from_millis(5));
+    // the frame at 180ms takes 70ms; the next frame is shown late at 250ms
+    // and the frames due at 220ms and 240ms are dropped
+    run(&mut scheduler, &clock, 1, Duration::from_millis(70));
+    run(&mut scheduler, &clock, 1, Duration::from_millis(5));
+
+    let stats = scheduler.stats();
+    assert_eq!(stats.dropped_frames, 2);
+    assert!((stats.frame_time_p50 - 5.0).abs() < 0.01);
+    assert!((stats.frame_time_p95 - 70.0).abs() < 0.01);
+
+    // the schedule continues from the late frame
+    let time = scheduler.wait();
+    assert_eq!(time.now, Duration::from_millis(270));
+}
+
+#[test]
+fn scheduler_stats_without_frames() {
+    let scheduler = FrameScheduler::new(60.0, Arc::new(ManualClock::new()));
+    let stats = scheduler.stats();
+    assert_eq!(stats.fps, 0.0);
+    assert_eq!(stats.frame_time_p50, 0.0);
+    assert_eq!(stats.total_frames, 0);
+}
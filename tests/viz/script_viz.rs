@@ -0,0 +1,179 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::settings::ScriptSettings;
+use led_speakers::theme::Color;
+use led_speakers::viz::{FrameTime, ScriptViz, Viz};
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Writes scripts to a new directory and returns the script settings for it.
+fn script_dir(name: &str, scripts: &[(&str, &str)]) -> (PathBuf, ScriptSettings) {
+    let dir = std::env::temp_dir().join(format!(
+        "led_speakers_scripts_{}_{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, script) in scripts {
+        fs::write(dir.join(file), script).unwrap();
+    }
+
+    let settings = ScriptSettings {
+        dir: dir.to_str().unwrap().to_string(),
+        ..ScriptSettings::default()
+    };
+    (dir, settings)
+}
+
+#[test]
+fn script_viz_from_dir() {
+    let (dir, settings) = script_dir(
+        "load",
+        &[
+            (
+                "band_pulse.rhai",
+                r#"
+                fn default_settings() {
+                    #{ scale: 0.5 }
+                }
+
+                let result = [];
+                for i in 0..pixels {
+                    result.push(#{ color: i, brightness: bands[0] / 100.0 * settings.scale });
+                }
+                result
+                "#,
+            ),
+            ("broken.rhai", "let result = ["),
+            ("notes.txt", "not a script"),
+        ],
+    );
+    let mut vizzes = ScriptViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // scripts that don't compile and other files are skipped
+    assert_eq!(vizzes.len(), 1);
+    let viz = &mut vizzes[0];
+    assert_eq!(viz.get_name(), "script_band_pulse");
+    assert_eq!(viz.get_pretty_name(), "Band Pulse");
+    assert_eq!(viz.get_settings()["scale"], "0.5");
+
+    viz.set_total_pixels(3);
+    let colors = vec![Color { r: 255, g: 0, b: 0 }, Color { r: 0, g: 0, b: 255 }];
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![80.0]),
+        &colors,
+        &FrameTime::new(Duration::from_millis(16), 0.016),
+    );
+
+    assert_eq!(pixels.len(), 3);
+    assert!((pixels[0].brightness - 0.4).abs() < 1e-6);
+    // color indices wrap around the theme colors
+    assert_eq!(
+        pixels.iter().map(|p| p.color_index).collect::<Vec<usize>>(),
+        vec![0, 1, 0]
+    );
+}
+
+#[test]
+fn script_viz_by_name() {
+    let (dir, settings) = script_dir("name", &[]);
+
+    // scripts added after the directory has been loaded are found by their identifier
+    assert!(ScriptViz::load("script_added", &settings).is_none());
+    fs::write(dir.join("added.rhai"), "[]").unwrap();
+    let viz = ScriptViz::load("script_added", &settings);
+    let builtin = ScriptViz::load("solid_viz", &settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(viz.unwrap().get_name(), "script_added");
+    assert!(builtin.is_none());
+}
+
+#[test]
+fn script_viz_keeps_state() {
+    let (dir, settings) = script_dir(
+        "state",
+        &[(
+            "counter.rhai",
+            r#"
+            if !("count" in state) {
+                state.count = 0.0;
+            }
+            state.count += 1.0;
+            [#{ brightness: state.count / 10.0 }]
+            "#,
+        )],
+    );
+    let mut vizzes = ScriptViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let viz = &mut vizzes[0];
+    viz.set_total_pixels(2);
+    let input = AudioFeatures::from_bands(vec![0.0]);
+    let colors = vec![Color { r: 255, g: 0, b: 0 }];
+    viz.update(&input, &colors, &FrameTime::default());
+    let pixels = viz.update(&input, &colors, &FrameTime::default());
+
+    assert!((pixels[0].brightness - 0.2).abs() < 1e-6);
+    // pixels the script doesn't return are off
+    assert_eq!(pixels[1].brightness, 0.0);
+}
+
+#[test]
+fn script_viz_aborts_endless_loop() {
+    let (dir, settings) = script_dir("loop", &[("endless.rhai", "loop {}")]);
+    let mut vizzes = ScriptViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let viz = &mut vizzes[0];
+    viz.set_total_pixels(4);
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![50.0]),
+        &vec![Color { r: 255, g: 0, b: 0 }],
+        &FrameTime::default(),
+    );
+
+    assert!(pixels.iter().all(|p| p.brightness == 0.0));
+}
+
+#[test]
+fn script_viz_load_new() {
+    let (dir, settings) = script_dir("new", &[("old.rhai", "[]"), ("new.rhai", "[]")]);
+    let vizzes = ScriptViz::load_new(&settings, &["script_old".to_string()]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(vizzes.len(), 1);
+    assert_eq!(vizzes[0].get_name(), "script_new");
+}
+
+#[test]
+fn script_viz_prints() {
+    let (dir, settings) = script_dir(
+        "print",
+        &[(
+            "chatty.rhai",
+            r#"
+            for i in 0..100 {
+                print(`pixel ${i}`);
+                debug(i);
+            }
+            [#{ brightness: 0.5 }]
+            "#,
+        )],
+    );
+    let mut vizzes = ScriptViz::load_dir(&settings);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // printed messages are rate-limited and don't abort the script
+    let viz = &mut vizzes[0];
+    viz.set_total_pixels(1);
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![0.0]),
+        &vec![Color { r: 255, g: 0, b: 0 }],
+        &FrameTime::default(),
+    );
+
+    assert_eq!(pixels[0].brightness, 0.5);
+}
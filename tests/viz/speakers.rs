@@ -0,0 +1,116 @@
+use led_speakers::settings::{LinkedSettings, Output, OutputSettings, Speaker};
+use led_speakers::theme::{Theme, ThemeMode};
+use led_speakers::viz::{
+    BlendMode, Layer, OutputThemes, SolidViz, SolidVizConfig, SparkleViz, SparkleVizConfig, Viz,
+};
+
+use std::collections::HashMap;
+
+fn output() -> OutputSettings {
+    OutputSettings {
+        left: Output {
+            spi: "/dev/spidev0.0".to_string(),
+            total_leds: 10,
+        },
+        right: Output {
+            spi: "/dev/spidev1.0".to_string(),
+            total_leds: 20,
+        },
+        fps: 60.0,
+        linked: LinkedSettings::default(),
+    }
+}
+
+fn sparkle_viz() -> Box<dyn Viz> {
+    Box::new(SparkleViz::new(SparkleVizConfig {
+        pretty_name: "Sparkle Viz".to_string(),
+        speed: 5.0,
+        falloff: 0.1,
+        max_ignite: 2.0,
+    }))
+}
+
+fn solid_viz() -> Box<dyn Viz> {
+    Box::new(SolidViz::new(SolidVizConfig {
+        pretty_name: "Solid Viz".to_string(),
+    }))
+}
+
+fn theme(name: &str) -> Theme {
+    Theme {
+        name: name.to_string(),
+        colors: Vec::new(),
+        mode: ThemeMode::Bands,
+    }
+}
+
+#[test]
+fn speaker_from_str() {
+    for speaker in [Speaker::Left, Speaker::Right].iter() {
+        assert_eq!(speaker.to_string().parse::<Speaker>(), Ok(*speaker));
+    }
+    assert!("center".parse::<Speaker>().is_err());
+}
+
+#[test]
+fn layer_viz_per_speaker() {
+    let output = output();
+    let mut layer = Layer::new(sparkle_viz(), &output, None, 1.0, BlendMode::Normal);
+    assert_eq!(layer.viz(Speaker::Left).get_name(), "sparkle_viz");
+    assert_eq!(layer.viz(Speaker::Right).get_name(), "sparkle_viz");
+
+    layer.set_viz(Speaker::Right, solid_viz(), &output);
+    assert_eq!(layer.viz(Speaker::Left).get_name(), "sparkle_viz");
+    assert_eq!(layer.viz(Speaker::Right).get_name(), "solid_viz");
+}
+
+#[test]
+fn layer_viz_settings_per_speaker() {
+    let output = output();
+    let mut layer = Layer::new(sparkle_viz(), &output, None, 1.0, BlendMode::Normal);
+
+    let mut settings = HashMap::new();
+    settings.insert("speed".to_string(), "7".to_string());
+    layer.update_output_viz_settings(Speaker::Left, &settings);
+    assert_eq!(layer.viz(Speaker::Left).get_settings()["speed"], "7");
+    assert_eq!(layer.viz(Speaker::Right).get_settings()["speed"], "5");
+
+    // settings of both speakers are replaced
+    layer.set_viz(Speaker::Right, solid_viz(), &output);
+    settings.insert("falloff".to_string(), "0.5".to_string());
+    layer.update_viz_settings(&settings);
+    assert_eq!(layer.viz(Speaker::Left).get_settings()["falloff"], "0.5");
+    assert_eq!(layer.viz(Speaker::Left).get_settings()["max_ignite"], "0");
+    assert_eq!(layer.viz(Speaker::Right).get_name(), "solid_viz");
+}
+
+#[test]
+fn layer_merge_viz_settings() {
+    let output = output();
+    let mut layer = Layer::new(sparkle_viz(), &output, None, 1.0, BlendMode::Normal);
+
+    // settings that are not provided keep their current value
+    let mut settings = HashMap::new();
+    settings.insert("falloff".to_string(), "0.5".to_string());
+    layer.merge_viz_settings(&settings);
+    for speaker in [Speaker::Left, Speaker::Right] {
+        assert_eq!(layer.viz(speaker).get_settings()["falloff"], "0.5");
+        assert_eq!(layer.viz(speaker).get_settings()["max_ignite"], "2");
+    }
+}
+
+#[test]
+fn output_themes_per_speaker() {
+    let mut themes = OutputThemes::new(theme("Rainbow"));
+    assert_eq!(themes.get(Speaker::Left).name, "Rainbow");
+    assert_eq!(themes.get(Speaker::Right).name, "Rainbow");
+
+    themes.set(Some(Speaker::Right), theme("Blue"));
+    assert_eq!(themes.get(Speaker::Left).name, "Rainbow");
+    assert_eq!(themes.get(Speaker::Right).name, "Blue");
+
+    // without a speaker the theme is shared again
+    themes.set(None, theme("Red"));
+    assert_eq!(themes.left.name, "Red");
+    assert_eq!(themes.right.name, "Red");
+}
@@ -0,0 +1,108 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::viz::{SpectrumOrientation, SpectrumViz, SpectrumVizConfig, Viz};
+
+use std::collections::HashMap;
+
+use crate::common::{colors, frame, lit};
+
+fn spectrum_viz(orientation: SpectrumOrientation, gradient: bool) -> SpectrumViz {
+    SpectrumViz::new(SpectrumVizConfig {
+        pretty_name: "Spectrum Viz".to_string(),
+        bands_per_strip: 0,
+        orientation,
+        gradient,
+        peak_hold: 0.5,
+        peak_fall_speed: 0.5,
+    })
+}
+
+#[test]
+fn spectrum_viz_segments() {
+    let mut viz = spectrum_viz(SpectrumOrientation::BottomUp, false);
+    viz.set_total_pixels(20);
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![50.0, 100.0]),
+        &colors(2),
+        &frame(0.0),
+    );
+
+    assert_eq!(lit(&pixels), (0..5).chain(10..20).collect::<Vec<usize>>());
+    assert_eq!(pixels[0].color_index, 0);
+    assert_eq!(pixels[10].color_index, 1);
+}
+
+#[test]
+fn spectrum_viz_center_out_gradient() {
+    let mut viz = spectrum_viz(SpectrumOrientation::CenterOut, true);
+    viz.set_total_pixels(10);
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![40.0]),
+        &colors(5),
+        &frame(0.0),
+    );
+
+    assert_eq!(lit(&pixels), vec![3, 4, 5, 6]);
+    // colors change from the center to the ends
+    assert_eq!(pixels[4].color_index, 0);
+    assert_eq!(pixels[3].color_index, 1);
+}
+
+#[test]
+fn spectrum_viz_peak_hold() {
+    let mut viz = spectrum_viz(SpectrumOrientation::BottomUp, false);
+    viz.set_total_pixels(10);
+    viz.update(
+        &AudioFeatures::from_bands(vec![80.0]),
+        &colors(1),
+        &frame(0.0),
+    );
+
+    // peak is held at the maximum level
+    let silence = AudioFeatures::from_bands(vec![0.0]);
+    let pixels = viz.update(&silence, &colors(1), &frame(0.4));
+    assert_eq!(lit(&pixels), vec![7]);
+
+    // peak falls after the hold time
+    viz.update(&silence, &colors(1), &frame(0.2));
+    let pixels = viz.update(&silence, &colors(1), &frame(0.4));
+    assert_eq!(lit(&pixels), vec![5]);
+
+    let pixels = viz.update(&silence, &colors(1), &frame(2.0));
+    assert!(lit(&pixels).is_empty());
+}
+
+#[test]
+fn spectrum_viz_invalid_settings() {
+    let mut viz = spectrum_viz(SpectrumOrientation::BottomUp, false);
+    viz.set_total_pixels(10);
+    let mut settings = viz.get_settings();
+    settings.insert("peak_hold".to_string(), "NaN".to_string());
+    settings.insert("peak_fall_speed".to_string(), "inf".to_string());
+    viz.update_settings(settings);
+    viz.update(
+        &AudioFeatures::from_bands(vec![80.0]),
+        &colors(1),
+        &frame(0.0),
+    );
+
+    // invalid settings fall back to the defaults
+    let silence = AudioFeatures::from_bands(vec![0.0]);
+    let pixels = viz.update(&silence, &colors(1), &frame(0.4));
+    assert_eq!(lit(&pixels), vec![7]);
+    viz.update(&silence, &colors(1), &frame(0.2));
+    let pixels = viz.update(&silence, &colors(1), &frame(0.4));
+    assert_eq!(lit(&pixels), vec![5]);
+
+    // peaks don't rise with negative fall speeds
+    let mut settings = HashMap::new();
+    settings.insert("peak_hold".to_string(), "-1".to_string());
+    settings.insert("peak_fall_speed".to_string(), "-1".to_string());
+    viz.update_settings(settings);
+    viz.update(
+        &AudioFeatures::from_bands(vec![80.0]),
+        &colors(1),
+        &frame(0.0),
+    );
+    let pixels = viz.update(&silence, &colors(1), &frame(2.0));
+    assert_eq!(lit(&pixels), vec![7]);
+}
@@ -0,0 +1,202 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::theme::Color;
+use led_speakers::viz::{
+    FrameTime, StrobeColorMode, StrobeViz, StrobeVizConfig, Viz, MAX_BRIGHTNESS_CHANGE,
+    MAX_FLASH_RATE,
+};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::common::colors;
+
+fn strobe_viz(flash_rate: f32, brightness_change: f32) -> StrobeViz {
+    StrobeViz::new(StrobeVizConfig {
+        pretty_name: "Strobe Viz".to_string(),
+        flash_rate,
+        duty_cycle: 0.5,
+        brightness_change,
+        color_mode: StrobeColorMode::DominantBand,
+    })
+}
+
+/// Returns features of a frame with an onset on the band `band` out of two bands.
+fn onset(band: usize) -> AudioFeatures {
+    let mut bands = vec![0.0; 2];
+    bands[band] = 100.0;
+    let mut features = AudioFeatures::from_bands(bands);
+    features.onset = true;
+    features
+}
+
+/// Returns the frame time of an update at `now` seconds at 60 frames per second.
+fn at(now: f32) -> FrameTime {
+    FrameTime::new(Duration::from_secs_f32(now), 1.0 / 60.0)
+}
+
+/// Returns whether pixels are lit by a flash.
+fn is_lit(viz: &mut StrobeViz, input: &AudioFeatures, now: f32) -> bool {
+    viz.update(input, &colors(3), &at(now))[0].brightness == 1.0
+}
+
+#[test]
+fn strobe_viz_limits_flash_rate() {
+    let mut viz = strobe_viz(20.0, 0.5);
+    viz.set_total_pixels(4);
+
+    // onsets in every frame for 10 seconds
+    let mut flashes = 0;
+    let mut was_lit = false;
+    for frame in 0..600 {
+        let lit = is_lit(&mut viz, &onset(0), frame as f32 / 60.0);
+        if lit && !was_lit {
+            flashes += 1;
+        }
+        was_lit = lit;
+    }
+
+    assert!(flashes as f32 <= MAX_FLASH_RATE * 10.0);
+    assert!(flashes as f32 >= MAX_FLASH_RATE * 10.0 - 1.0);
+}
+
+#[test]
+fn strobe_viz_limits_flash_rate_across_outputs() {
+    let mut left = strobe_viz(20.0, 0.5);
+    left.set_total_pixels(4);
+    let mut right = left.clone();
+    let silence = AudioFeatures::new(2);
+
+    // onsets of both outputs alternate for 10 seconds
+    let mut flashes = 0;
+    let mut was_lit = false;
+    for frame in 0..600 {
+        let now = frame as f32 / 60.0;
+        let (left_input, right_input) = if frame % 2 == 0 {
+            (onset(0), silence.clone())
+        } else {
+            (silence.clone(), onset(0))
+        };
+        let left_lit = is_lit(&mut left, &left_input, now);
+        let right_lit = is_lit(&mut right, &right_input, now);
+
+        let lit = left_lit || right_lit;
+        if lit && !was_lit {
+            flashes += 1;
+        }
+        was_lit = lit;
+    }
+
+    assert!(flashes as f32 <= MAX_FLASH_RATE * 10.0);
+}
+
+#[test]
+fn strobe_viz_limits_brightness_change() {
+    let mut viz = strobe_viz(2.0, 1.0);
+    viz.set_total_pixels(4);
+    let silence = AudioFeatures::new(2);
+
+    assert!(is_lit(&mut viz, &onset(0), 0.0));
+
+    // pixels are lit for half of the minimum time between flashes
+    assert!(is_lit(&mut viz, &silence, 0.2));
+    let pixels = viz.update(&silence, &colors(3), &at(0.3));
+    assert!((pixels[0].brightness - (1.0 - MAX_BRIGHTNESS_CHANGE)).abs() < 1e-6);
+}
+
+#[test]
+fn strobe_viz_limits_luminance_change() {
+    let mut viz = strobe_viz(MAX_FLASH_RATE, MAX_BRIGHTNESS_CHANGE);
+    viz.set_total_pixels(4);
+    let colors = vec![
+        Color::from_vec(&vec![20, 20, 20]),
+        Color::from_vec(&vec![255; 3]),
+    ];
+
+    // flashes alternate between a dark and a bright theme color for 10 seconds
+    let mut previous = 0.0;
+    let mut brightest: f32 = 0.0;
+    for frame in 0..600 {
+        let input = onset((frame / 20) % 2);
+        let pixels = viz.update(&input, &colors, &at(frame as f32 / 60.0));
+        let luminance = pixels[0].brightness * colors[pixels[0].color_index].luminance();
+
+        assert!(luminance - previous <= MAX_BRIGHTNESS_CHANGE + 1e-6);
+        assert!(previous - luminance <= MAX_BRIGHTNESS_CHANGE + 1e-6);
+        previous = luminance;
+        brightest = brightest.max(luminance);
+    }
+
+    // the bright color is dimmed, but still flashes brighter than the dark color
+    assert!(brightest > colors[0].luminance());
+    assert!(brightest < colors[1].luminance());
+}
+
+#[test]
+fn strobe_viz_invalid_settings() {
+    let mut viz = strobe_viz(2.0, 0.5);
+    viz.set_total_pixels(4);
+    let mut settings = HashMap::new();
+    settings.insert("flash_rate".to_string(), "-1".to_string());
+    settings.insert("duty_cycle".to_string(), "NaN".to_string());
+    settings.insert("brightness_change".to_string(), "NaN".to_string());
+    viz.update_settings(settings);
+
+    assert!(is_lit(&mut viz, &onset(0), 0.0));
+    let brightness = viz.update(&AudioFeatures::new(2), &colors(3), &at(0.3))[0].brightness;
+    assert!((1.0 - MAX_BRIGHTNESS_CHANGE..1.0).contains(&brightness));
+
+    // negative flash rates don't fall back to the maximum flash rate
+    assert!(!is_lit(&mut viz, &onset(0), 1.0 / MAX_FLASH_RATE + 0.05));
+}
+
+#[test]
+fn strobe_viz_colors() {
+    let mut left = strobe_viz(2.0, 0.5);
+    left.set_total_pixels(4);
+    let mut right = left.clone();
+
+    // flashes show the color of the dominant band on both outputs
+    let pixels = left.update(&onset(1), &colors(3), &at(0.0));
+    assert_eq!(pixels[0].color_index, 1);
+    let pixels = right.update(&AudioFeatures::new(2), &colors(3), &at(0.0));
+    assert_eq!(pixels[0].color_index, 1);
+    assert_eq!(pixels[0].brightness, 1.0);
+
+    // the color is kept until the next flash
+    let pixels = left.update(&onset(0), &colors(3), &at(0.1));
+    assert_eq!(pixels[0].color_index, 1);
+    let pixels = left.update(&onset(0), &colors(3), &at(0.5));
+    assert_eq!(pixels[0].color_index, 0);
+}
+
+#[test]
+fn strobe_viz_cycles_colors() {
+    let mut left = strobe_viz(2.0, 0.5);
+    left.config.color_mode = StrobeColorMode::Cycle;
+    left.set_total_pixels(4);
+    let mut right = left.clone();
+
+    // each flash shows the next theme color, independent of the dominant band
+    let mut indices = vec![];
+    for flash in 0..4 {
+        let now = flash as f32 * 0.5;
+        indices.push(left.update(&onset(0), &colors(3), &at(now))[0].color_index);
+        assert_eq!(
+            right.update(&AudioFeatures::new(2), &colors(3), &at(now))[0].color_index,
+            indices[flash]
+        );
+    }
+    assert_eq!(indices, vec![1, 2, 0, 1]);
+}
+
+#[test]
+fn strobe_viz_color_mode_settings() {
+    let mut viz = strobe_viz(2.0, 0.5);
+    let mut settings = viz.get_settings();
+    assert_eq!(settings["color_mode"], "dominant_band");
+
+    settings.insert("color_mode".to_string(), "cycle".to_string());
+    viz.update_settings(settings);
+    assert_eq!(viz.config.color_mode, StrobeColorMode::Cycle);
+    assert_eq!(viz.get_settings()["color_mode"], "cycle");
+}
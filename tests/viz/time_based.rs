@@ -0,0 +1,128 @@
+use led_speakers::features::{AudioFeatures, BandSource};
+use led_speakers::theme::Color;
+use led_speakers::viz::{
+    FadingBeatViz, FadingBeatVizConfig, FrameTime, PixelViz, RotatingViz, RotatingVizConfig,
+    SolidBeatViz, SolidBeatVizConfig, SparkleViz, SparkleVizConfig, Viz,
+};
+
+use std::time::Duration;
+
+fn colors() -> Vec<Color> {
+    vec![
+        Color {
+            r: 200,
+            g: 100,
+            b: 100,
+        },
+        Color {
+            r: 100,
+            g: 100,
+            b: 200,
+        },
+    ]
+}
+
+/// Updates the viz `updates` times with a fixed delta in seconds and returns the last pixels.
+fn run(viz: &mut dyn Viz, input: &AudioFeatures, updates: usize, delta: f32) -> Vec<PixelViz> {
+    let mut pixels = Vec::new();
+    for i in 0..updates {
+        let now = Duration::from_secs_f32(delta * (i + 1) as f32);
+        pixels = viz.update(input, &colors(), &FrameTime::new(now, delta));
+    }
+    pixels
+}
+
+#[test]
+fn solid_beat_viz_fades_with_sub_second_precision() {
+    let mut viz = SolidBeatViz::new(SolidBeatVizConfig {
+        pretty_name: "Solid Beat Viz".to_string(),
+        fade_colors: true,
+        fade_duration: 2,
+        band_source: BandSource::All,
+    });
+    viz.set_total_pixels(10);
+    let input = AudioFeatures::from_bands(vec![100.0, 100.0]);
+
+    // half-way through the fade after 1 second, independent of the update rate
+    for (updates, delta) in [(1, 1.0), (10, 0.1), (100, 0.01)].iter() {
+        let mut viz = viz.clone();
+        let pixels = run(&mut viz, &input, *updates, *delta);
+        assert!((pixels[0].red_mul - 0.75).abs() < 0.01, "{}", pixels[0].red_mul);
+        assert!((pixels[0].blue_mul - 1.5).abs() < 0.01, "{}", pixels[0].blue_mul);
+    }
+
+    // fade completes after fade_duration
+    let pixels = run(&mut viz, &input, 21, 0.1);
+    assert_eq!(pixels[0].color_index, 1);
+}
+
+#[test]
+fn fading_beat_viz_fades_with_sub_second_precision() {
+    let mut viz = FadingBeatViz::new(FadingBeatVizConfig {
+        pretty_name: "Fading Beat Viz".to_string(),
+        fade_duration: 1,
+        fade_threshold: 0,
+        frequency_magnitude_buffer_size: 1,
+        band_source: BandSource::All,
+    });
+    viz.set_total_pixels(10);
+
+    // change of the dominant band starts fading
+    run(&mut viz, &AudioFeatures::from_bands(vec![10.0, 100.0]), 1, 0.1);
+    let pixels = run(&mut viz, &AudioFeatures::from_bands(vec![10.0, 100.0]), 5, 0.1);
+    assert!((pixels[0].red_mul - 0.75).abs() < 0.01, "{}", pixels[0].red_mul);
+}
+
+#[test]
+fn sparkle_viz_falloff_independent_of_update_rate() {
+    let mut viz = SparkleViz::new(SparkleVizConfig {
+        pretty_name: "Sparkle Viz".to_string(),
+        speed: 100.0,
+        falloff: 0.9,
+        max_ignite: 10.0,
+    });
+    viz.set_total_pixels(10);
+
+    // ignite all pixels
+    let pixels = run(&mut viz, &AudioFeatures::from_bands(vec![100.0]), 1, 0.011);
+    assert!(pixels.iter().all(|p| p.brightness > 0.0));
+
+    let silence = AudioFeatures::from_bands(vec![0.0]);
+    let mut slow = viz.clone();
+    let slow_pixels = run(&mut slow, &silence, 2, 1.0 / 30.0);
+    let mut fast = viz.clone();
+    let fast_pixels = run(&mut fast, &silence, 8, 1.0 / 120.0);
+    for (slow, fast) in slow_pixels.iter().zip(fast_pixels.iter()) {
+        assert!((slow.brightness - fast.brightness).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn rotating_viz_moves_by_speed() {
+    let mut viz = RotatingViz::new(RotatingVizConfig {
+        pretty_name: "Rotating Viz".to_string(),
+        speed: 10.0,
+        falloff: 0.0,
+    });
+    viz.set_total_pixels(100);
+
+    // pixels without a color have all multipliers set to 0
+    let assigned = |pixels: &Vec<PixelViz>| -> Vec<bool> {
+        pixels.iter().map(|p| p.red_mul > 0.0).collect()
+    };
+
+    // assign colors to some pixels; silence keeps them assigned but doesn't assign new ones
+    let before = assigned(&run(&mut viz, &AudioFeatures::from_bands(vec![50.0, 50.0]), 1, 0.0));
+    assert!(before.contains(&true));
+    let silence = AudioFeatures::from_bands(vec![0.0, 0.0]);
+
+    // pixels move by 10 positions per second, so not yet after 0.075s
+    let after = assigned(&run(&mut viz, &silence, 3, 0.025));
+    assert_eq!(after, before);
+
+    // but by one position after 0.1s
+    let after = assigned(&run(&mut viz, &silence, 1, 0.025));
+    let mut rotated = before.clone();
+    rotated.rotate_left(1);
+    assert_eq!(after, rotated);
+}
@@ -0,0 +1,124 @@
+use led_speakers::settings::{TransitionCurve, TransitionSettings};
+use led_speakers::theme::{Theme, ThemeMode};
+use led_speakers::viz::{FrameTime, Layer, OutputThemes, Transition};
+
+use std::time::Duration;
+
+fn transition(duration: f32, curve: TransitionCurve) -> Transition {
+    named_transition("Test", duration, curve)
+}
+
+fn named_transition(name: &str, duration: f32, curve: TransitionCurve) -> Transition {
+    let theme = Theme {
+        name: name.to_string(),
+        colors: Vec::new(),
+        mode: ThemeMode::Bands,
+    };
+    Transition::new(
+        Vec::new(),
+        OutputThemes::new(theme),
+        TransitionSettings { duration, curve },
+    )
+}
+
+fn advance(transition: &mut Transition, seconds: f32) {
+    transition.update(&FrameTime::new(Duration::ZERO, seconds));
+}
+
+#[test]
+fn transition_settings_defaults() {
+    let settings: TransitionSettings = serde_json::from_str("{}").unwrap();
+    assert_eq!(settings, TransitionSettings::default());
+    assert_eq!(settings.duration, 1.0);
+    assert_eq!(settings.curve, TransitionCurve::Linear);
+
+    let settings: TransitionSettings =
+        serde_json::from_str(r#"{"duration": 0.5, "curve": "wipe"}"#).unwrap();
+    assert_eq!(settings.curve, TransitionCurve::Wipe);
+}
+
+#[test]
+fn linear_and_ease_crossfade() {
+    let mut linear = transition(2.0, TransitionCurve::Linear);
+    let mut ease = transition(2.0, TransitionCurve::Ease);
+    assert_eq!(linear.mix(0, 10), 0.0);
+    assert_eq!(ease.mix(0, 10), 0.0);
+
+    advance(&mut linear, 0.5);
+    advance(&mut ease, 0.5);
+    assert!((linear.mix(0, 10) - 0.25).abs() < 1e-6);
+    assert!((ease.mix(0, 10) - 0.15625).abs() < 1e-6);
+
+    // both curves are half-way at half of the duration
+    advance(&mut linear, 0.5);
+    advance(&mut ease, 0.5);
+    assert!((linear.mix(9, 10) - 0.5).abs() < 1e-6);
+    assert!((ease.mix(9, 10) - 0.5).abs() < 1e-6);
+    assert!(!linear.is_done());
+
+    advance(&mut linear, 1.0);
+    assert!(linear.is_done());
+    assert_eq!(linear.mix(0, 10), 1.0);
+}
+
+#[test]
+fn wipe_spreads_from_center() {
+    let mut wipe = transition(1.0, TransitionCurve::Wipe);
+    assert!((0..10).all(|i| wipe.mix(i, 10) == 0.0));
+
+    advance(&mut wipe, 0.5);
+    assert_eq!(wipe.mix(4, 10), 1.0);
+    assert_eq!(wipe.mix(5, 10), 1.0);
+    assert_eq!(wipe.mix(0, 10), 0.0);
+    assert_eq!(wipe.mix(9, 10), 0.0);
+
+    advance(&mut wipe, 0.5);
+    assert!((0..10).all(|i| wipe.mix(i, 10) == 1.0));
+}
+
+#[test]
+fn blend_previous_frame() {
+    let mut linear = transition(1.0, TransitionCurve::Linear);
+    advance(&mut linear, 0.5);
+
+    let previous = vec![[100.0, 0.0, 0.0]];
+    let mut frame = vec![[0.0, 100.0, 0.0]];
+    linear.blend(&previous, &mut frame);
+    assert_eq!(frame[0], [50.0, 50.0, 0.0]);
+}
+
+#[test]
+fn interrupted_transition_keeps_blending() {
+    let mut first = named_transition("First", 1.0, TransitionCurve::Linear);
+    advance(&mut first, 0.5);
+    let mut second = named_transition("Second", 1.0, TransitionCurve::Linear);
+    second.previous = Some(Box::new(first));
+
+    // each theme stands for layers showing a single value
+    let mut composite = |_: &mut [Layer], themes: &OutputThemes| {
+        let value = if themes.left.name == "First" {
+            0.0
+        } else {
+            100.0
+        };
+        (vec![[value; 3]], vec![[value; 3]])
+    };
+
+    // the second transition starts from the half-way blend of the first one
+    let (left, right) =
+        second.previous_frames(&FrameTime::new(Duration::ZERO, 0.0), &mut composite);
+    assert_eq!(left[0], [50.0; 3]);
+    assert_eq!(right[0], [50.0; 3]);
+
+    // the first transition keeps running and is dropped once done
+    let (left, _) = second.previous_frames(&FrameTime::new(Duration::ZERO, 0.5), &mut composite);
+    assert_eq!(left[0], [100.0; 3]);
+    assert!(second.previous.is_none());
+}
+
+#[test]
+fn zero_duration_switches_instantly() {
+    let transition = transition(0.0, TransitionCurve::Ease);
+    assert!(transition.is_done());
+    assert_eq!(transition.mix(0, 10), 1.0);
+}
@@ -0,0 +1,108 @@
+use led_speakers::features::AudioFeatures;
+use led_speakers::viz::{PixelViz, Viz, WaterfallColorSource, WaterfallViz, WaterfallVizConfig};
+
+use std::collections::HashMap;
+
+use crate::common::{colors, frame};
+
+fn waterfall_viz(color_source: WaterfallColorSource) -> WaterfallViz {
+    WaterfallViz::new(WaterfallVizConfig {
+        pretty_name: "Waterfall Viz".to_string(),
+        color_source,
+        scroll_speed: 10.0,
+        max_centroid: 8000.0,
+    })
+}
+
+/// Returns the color index of each pixel that is turned on.
+fn lit_colors(pixels: &[PixelViz]) -> Vec<usize> {
+    pixels
+        .iter()
+        .filter(|p| p.brightness > 0.0)
+        .map(|p| p.color_index)
+        .collect()
+}
+
+#[test]
+fn waterfall_viz_scrolls_history() {
+    let mut viz = waterfall_viz(WaterfallColorSource::DominantBand);
+    viz.set_total_pixels(5);
+
+    viz.update(
+        &AudioFeatures::from_bands(vec![100.0, 0.0]),
+        &colors(2),
+        &frame(0.1),
+    );
+    let pixels = viz.update(
+        &AudioFeatures::from_bands(vec![0.0, 50.0]),
+        &colors(2),
+        &frame(0.1),
+    );
+
+    assert_eq!(pixels.len(), 5);
+    // newest pixel is at the start of the strip
+    assert_eq!(lit_colors(&pixels), vec![1, 0]);
+    assert_eq!(pixels[0].brightness, 0.5);
+    assert_eq!(pixels[1].brightness, 1.0);
+}
+
+#[test]
+fn waterfall_viz_scroll_speed_is_time_based() {
+    let mut viz = waterfall_viz(WaterfallColorSource::DominantBand);
+    viz.set_total_pixels(10);
+    let features = AudioFeatures::from_bands(vec![100.0]);
+
+    // frames shorter than a pixel's worth of time don't scroll
+    let pixels = viz.update(&features, &colors(1), &frame(0.05));
+    assert!(lit_colors(&pixels).is_empty());
+
+    let pixels = viz.update(&features, &colors(1), &frame(0.05));
+    assert_eq!(lit_colors(&pixels).len(), 1);
+
+    // long frames scroll multiple pixels
+    let pixels = viz.update(&features, &colors(1), &frame(0.3));
+    assert_eq!(lit_colors(&pixels).len(), 4);
+
+    let pixels = viz.update(&features, &colors(1), &frame(10.0));
+    assert_eq!(lit_colors(&pixels).len(), 10);
+}
+
+#[test]
+fn waterfall_viz_spectral_centroid() {
+    let mut viz = waterfall_viz(WaterfallColorSource::SpectralCentroid);
+    viz.set_total_pixels(3);
+    let mut features = AudioFeatures::from_bands(vec![100.0]);
+
+    features.spectral_centroid = 8000.0;
+    viz.update(&features, &colors(5), &frame(0.1));
+    features.spectral_centroid = 4000.0;
+    viz.update(&features, &colors(5), &frame(0.1));
+    features.spectral_centroid = 100_000.0;
+    let pixels = viz.update(&features, &colors(5), &frame(0.1));
+
+    assert_eq!(lit_colors(&pixels), vec![4, 2, 4]);
+}
+
+#[test]
+fn waterfall_viz_invalid_settings() {
+    let mut viz = waterfall_viz(WaterfallColorSource::SpectralCentroid);
+    viz.set_total_pixels(10);
+    let mut settings = HashMap::new();
+    settings.insert("color_source".to_string(), "spectral_centroid".to_string());
+    settings.insert("scroll_speed".to_string(), "NaN".to_string());
+    settings.insert("max_centroid".to_string(), "inf".to_string());
+    viz.update_settings(settings);
+    let mut features = AudioFeatures::from_bands(vec![100.0]);
+    features.spectral_centroid = 8000.0;
+
+    // invalid settings fall back to the defaults of 60 pixels per second and 8000 Hz
+    let pixels = viz.update(&features, &colors(5), &frame(0.05));
+    assert_eq!(lit_colors(&pixels), vec![4, 4, 4]);
+
+    // the strip doesn't scroll backwards
+    let mut settings = viz.get_settings();
+    settings.insert("scroll_speed".to_string(), "-10".to_string());
+    viz.update_settings(settings);
+    let pixels = viz.update(&features, &colors(5), &frame(1.0));
+    assert!(lit_colors(&pixels).is_empty());
+}